@@ -1,10 +1,35 @@
 use bevy::prelude::{Mat2, Vec2};
 use hex_chess_lib::Coord;
 
-const SQRT_3: f32 = 1.7320508075688772;
+const SQRT_3: f32 = 1.732_050_8;
 
 const LAYOUT_FLAT: Mat2 = Mat2::from_cols(Vec2::new(3. / 2., SQRT_3 / 2.), Vec2::new(0., SQRT_3));
 
+/// which team's side of the board is drawn at the bottom of the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoardOrientation {
+    #[default]
+    White,
+    Black,
+}
+
+impl BoardOrientation {
+    pub fn flip(self) -> Self {
+        match self {
+            BoardOrientation::White => BoardOrientation::Black,
+            BoardOrientation::Black => BoardOrientation::White,
+        }
+    }
+
+    // black's view is white's turned half way around the center
+    fn apply(self, pixel: Vec2) -> Vec2 {
+        match self {
+            BoardOrientation::White => pixel,
+            BoardOrientation::Black => -pixel,
+        }
+    }
+}
+
 fn axial_round(v: Vec2) -> Coord {
     let v = v.extend(-v.x - v.y);
     let mut rv = v.round();
@@ -17,11 +42,11 @@ fn axial_round(v: Vec2) -> Coord {
     Coord::new(rv.x as i32, rv.y as i32)
 }
 
-pub fn flat_hex_to_pixel(hex: Coord, size: f32) -> Vec2 {
+pub fn flat_hex_to_pixel(hex: Coord, size: f32, orientation: BoardOrientation) -> Vec2 {
     let hex = Vec2::new(hex.q as f32, hex.r as f32);
-    size * LAYOUT_FLAT * hex
+    orientation.apply(size * LAYOUT_FLAT * hex)
 }
 
-pub fn pixel_to_flat_hex(hex: Vec2, size: f32) -> Coord {
-    axial_round(LAYOUT_FLAT.inverse() * hex / size)
+pub fn pixel_to_flat_hex(hex: Vec2, size: f32, orientation: BoardOrientation) -> Coord {
+    axial_round(LAYOUT_FLAT.inverse() * orientation.apply(hex) / size)
 }
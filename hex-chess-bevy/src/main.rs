@@ -1,31 +1,57 @@
 mod hex_rect;
+mod network;
 
 use std::time::Duration;
 
-use crate::hex_rect::{flat_hex_to_pixel, pixel_to_flat_hex};
+use crate::hex_rect::{flat_hex_to_pixel, pixel_to_flat_hex, BoardOrientation};
 use bevy::{
-    input::{mouse::MouseButtonInput, ButtonState},
+    input::{
+        mouse::MouseButtonInput,
+        touch::{TouchInput, TouchPhase},
+        ButtonState,
+    },
     prelude::*,
     render::camera::{RenderTarget, ScalingMode},
     sprite::MaterialMesh2dBundle,
+    tasks::{AsyncComputeTaskPool, Task},
     utils::HashMap,
 };
-use bevy_easings::{Ease, EaseFunction, EaseMethod, EasingType, EasingsPlugin};
+use bevy_easings::{Ease, EaseFunction, EaseMethod, EasingComponent, EasingType, EasingsPlugin};
 use bevy_embedded_assets::EmbeddedAssetPlugin;
-use hex_chess_lib::{Coord, Game};
+use futures_lite::future;
+use hex_chess_lib::{
+    board::board_hexes,
+    engine::{self, SearchLimits},
+    BoardKey, Coord, Game, GameBuilder, HexBoard, Move, Name, Team, TimeControl,
+};
 
-const N: i32 = 5;
 const RADIUS: f32 = 50.0;
 const ATLAS_SIZE: (usize, usize) = (6, 2);
+/// the width of the side panel, which the camera shifts the board away from
+const PANEL_WIDTH: f32 = 260.0;
+/// the height of the panel when it sits under the board on a tall screen
+const PANEL_HEIGHT: f32 = 320.0;
+/// how long a finger has to rest on a piece to show where it can go
+const LONG_PRESS: Duration = Duration::from_millis(500);
+/// how far in pixels a finger can drift and still count as resting
+const TOUCH_SLOP: f32 = 12.0;
+/// the most full moves the panel lists, keeping the latest in view
+const MOVE_LOG_LINES: usize = 28;
 
 #[derive(Component)]
 struct MainCamera;
 
 type PieceSprites = HashMap<Coord, Entity>;
 
+struct PiecesAtlas(Handle<TextureAtlas>);
+
 struct HexMaterials {
     mat_hover: Handle<ColorMaterial>,
     mat_selected: Handle<ColorMaterial>,
+    mat_last_move: Handle<ColorMaterial>,
+    mat_legal: Handle<ColorMaterial>,
+    mat_capture: Handle<ColorMaterial>,
+    mat_check: Handle<ColorMaterial>,
     mat_light: Handle<ColorMaterial>,
     mat_mid: Handle<ColorMaterial>,
     mat_dark: Handle<ColorMaterial>,
@@ -37,6 +63,10 @@ impl FromWorld for HexMaterials {
         Self {
             mat_hover: materials.add(ColorMaterial::from(Color::rgb(0.95, 0.51, 0.5))),
             mat_selected: materials.add(ColorMaterial::from(Color::rgb(0.54, 0.2, 0.2))),
+            mat_last_move: materials.add(ColorMaterial::from(Color::rgb(0.87, 0.8, 0.45))),
+            mat_legal: materials.add(ColorMaterial::from(Color::rgb(0.72, 0.78, 0.45))),
+            mat_capture: materials.add(ColorMaterial::from(Color::rgb(0.85, 0.55, 0.35))),
+            mat_check: materials.add(ColorMaterial::from(Color::rgb(0.9, 0.3, 0.25))),
             mat_light: materials.add(ColorMaterial::from(Color::rgb(1.0, 0.81, 0.62))),
             mat_mid: materials.add(ColorMaterial::from(Color::rgb(0.82, 0.55, 0.27))),
             mat_dark: materials.add(ColorMaterial::from(Color::rgb(0.91, 0.68, 0.44))),
@@ -44,10 +74,62 @@ impl FromWorld for HexMaterials {
     }
 }
 
+struct Sounds {
+    move_piece: Handle<AudioSource>,
+    capture: Handle<AudioSource>,
+    check: Handle<AudioSource>,
+    checkmate: Handle<AudioSource>,
+}
+
+impl FromWorld for Sounds {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.get_resource::<AssetServer>().unwrap();
+        Self {
+            move_piece: asset_server.load("sounds/move.wav"),
+            capture: asset_server.load("sounds/capture.wav"),
+            check: asset_server.load("sounds/check.wav"),
+            checkmate: asset_server.load("sounds/checkmate.wav"),
+        }
+    }
+}
+
+struct UiFont(Handle<Font>);
+
+impl FromWorld for UiFont {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.get_resource::<AssetServer>().unwrap();
+        Self(asset_server.load("fonts/DejaVuSans.ttf"))
+    }
+}
+
+/// sent to throw away the game and set up a new one
+#[derive(Debug, Clone, Copy)]
+struct RestartGame;
+
+/// sent to turn the board around so the other team plays up the screen
+#[derive(Debug, Clone, Copy)]
+struct FlipBoard;
+
+/// sent whenever a move is played on the board
+#[derive(Debug, Clone, Copy)]
+struct PieceMoved {
+    /// the move as played, naming what a promoting pawn became
+    mv: Move,
+    team: Team,
+    capture: bool,
+    check: bool,
+    checkmate: bool,
+    /// what a pawn reaching the last rank became
+    promotion: Option<hex_chess_lib::Piece>,
+}
+
 #[derive(Debug)]
 struct SelectedHex {
     hover: Option<Coord>,
     selected: Option<Coord>,
+    /// where the selected piece can go, in order, and whether each move
+    /// captures
+    moves: Vec<(Coord, bool)>,
 }
 
 impl SelectedHex {
@@ -55,8 +137,29 @@ impl SelectedHex {
         Self {
             hover: None,
             selected: None,
+            moves: Vec::new(),
         }
     }
+
+    fn select(&mut self, from: Coord, board: &HexBoard) {
+        self.selected = Some(from);
+        self.moves = board
+            .legal_moves(from)
+            .into_iter()
+            .map(|to| (to, board.capture_square(from, to).is_some()))
+            .collect();
+    }
+
+    /// show where the piece on `from` can go without picking it up
+    fn preview(&mut self, from: Coord, board: &HexBoard) {
+        self.select(from, board);
+        self.selected = None;
+    }
+
+    fn deselect(&mut self) {
+        self.selected = None;
+        self.moves.clear();
+    }
 }
 
 impl Default for SelectedHex {
@@ -65,22 +168,138 @@ impl Default for SelectedHex {
     }
 }
 
+/// the hexes the most recent move was made from and to
+#[derive(Debug, Default)]
+struct LastMove(Option<(Coord, Coord)>);
+
+/// which team is played from the gui - the bot plays the other one, unless
+/// it's played over the network
+struct PlayerConfig {
+    human: Team,
+    /// whether the other team is played by someone connected to a server
+    remote: bool,
+    /// how far ahead and how long the bot searches for
+    bot_limits: SearchLimits,
+    /// the clocks new games start with, or `None` to play untimed
+    time_control: Option<TimeControl>,
+}
+
+impl PlayerConfig {
+    /// a fresh game on this config's clocks
+    fn new_game(&self) -> Game {
+        match self.time_control {
+            Some(time_control) => Game::with_time_control(time_control),
+            None => Game::new(),
+        }
+    }
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            human: Team::White,
+            remote: false,
+            bot_limits: SearchLimits {
+                depth: Some(4),
+                time: Some(Duration::from_secs(2)),
+                nodes: None,
+            },
+            time_control: Some(TimeControl {
+                initial: Duration::from_secs(5 * 60),
+                increment: Duration::from_secs(3),
+            }),
+        }
+    }
+}
+
+/// the bot's search while it runs, along with the key of the position it
+/// started from
+#[derive(Default)]
+struct BotSearch(Option<(Task<Option<Move>>, BoardKey)>);
+
+/// the hex of the piece being dragged by the cursor, if any
+#[derive(Debug, Default)]
+struct Dragging(Option<Coord>);
+
+/// the mouse, or a finger on a touch screen, in window coordinates counting up
+/// from the bottom left like the cursor position
+#[derive(Debug, Default)]
+struct Pointer {
+    /// where it is, if it's over the window
+    position: Option<Vec2>,
+    /// where a finger was last seen, which is kept between touch events
+    last_touch: Option<Vec2>,
+    /// where and when the finger now down touched, while it hasn't moved
+    /// away or been held long enough to count as a long press
+    resting: Option<(Vec2, Duration)>,
+    /// where a finger was held still for `LONG_PRESS` this frame
+    long_press: Option<Vec2>,
+    /// left button presses and releases this frame, along with touches
+    /// starting and ending, with where each one happened
+    presses: Vec<(ButtonState, Vec2)>,
+}
+
+impl Pointer {
+    /// where the pointer was pressed this frame, if it was
+    fn just_pressed(&self) -> Option<Vec2> {
+        self.presses
+            .iter()
+            .find(|(state, _)| *state == ButtonState::Pressed)
+            .map(|&(_, position)| position)
+    }
+}
+
+/// how long the team to move has been thinking since its clock was last
+/// charged, which is kept apart from the game so it only changes on moves
+#[derive(Debug, Default)]
+struct TurnClock(Duration);
+
+/// whether move sounds are switched off
+#[derive(Debug, Default)]
+struct Muted(bool);
+
+/// whether clicks edit the position rather than play moves
+#[derive(Debug, Default)]
+struct Editing(bool);
+
+/// a pawn move to the last rank waiting on the player to pick what the pawn
+/// becomes, which holds up every other click
+#[derive(Debug, Default)]
+struct PendingPromotion(Option<Move>);
+
 fn color_tiles(
+    game: Res<Game>,
     selected: Res<SelectedHex>,
+    last_move: Res<LastMove>,
     hex_materials: Res<HexMaterials>,
     mut tiles: Query<(&HexCoord, &mut Handle<ColorMaterial>)>,
 ) {
+    // the king of the team to move, if it's in check
+    let checked = game
+        .board
+        .king_position(game.turn)
+        .filter(|_| game.in_check(game.turn));
     for (HexCoord { coord }, mut material) in tiles.iter_mut() {
         *material = if selected.selected.is_some() && selected.selected.unwrap() == *coord {
             hex_materials.mat_selected.clone()
         } else if selected.hover.is_some() && selected.hover.unwrap() == *coord {
             hex_materials.mat_hover.clone()
-        } else if coord.norm_squared() % 3 == 0 {
-            hex_materials.mat_mid.clone()
-        } else if (*coord - (1, 0).into()).norm_squared() % 3 == 0 {
-            hex_materials.mat_dark.clone()
+        } else if let Ok(i) = selected.moves.binary_search_by_key(coord, |&(to, _)| to) {
+            if selected.moves[i].1 {
+                hex_materials.mat_capture.clone()
+            } else {
+                hex_materials.mat_legal.clone()
+            }
+        } else if checked == Some(*coord) {
+            hex_materials.mat_check.clone()
+        } else if matches!(last_move.0, Some((from, to)) if from == *coord || to == *coord) {
+            hex_materials.mat_last_move.clone()
         } else {
-            hex_materials.mat_light.clone()
+            match coord.color_index() {
+                0 => hex_materials.mat_mid.clone(),
+                1 => hex_materials.mat_dark.clone(),
+                _ => hex_materials.mat_light.clone(),
+            }
         };
     }
 }
@@ -93,6 +312,60 @@ struct HexCoord {
 #[derive(Debug, Clone, Copy, Component)]
 struct Piece;
 
+/// where `piece` is in the pieces atlas
+fn atlas_index(hex_chess_lib::Piece { team, name }: hex_chess_lib::Piece) -> usize {
+    ATLAS_SIZE.0 * team as usize + name as usize
+}
+
+fn spawn_piece(
+    commands: &mut Commands,
+    pieces_atlas: &Handle<TextureAtlas>,
+    orientation: BoardOrientation,
+    coord: Coord,
+    piece: hex_chess_lib::Piece,
+) -> Entity {
+    let pixel = flat_hex_to_pixel(coord, RADIUS, orientation);
+    commands
+        .spawn_bundle(SpatialBundle {
+            transform: Transform::from_translation(pixel.extend(1.0)),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: atlas_index(piece),
+                    ..default()
+                },
+                texture_atlas: pieces_atlas.clone(),
+                transform: Transform::from_scale(Vec3::splat(0.8)),
+                ..default()
+            });
+        })
+        .insert(Piece)
+        .id()
+}
+
+// spawn a sprite for every piece on the board
+fn spawn_pieces(
+    commands: &mut Commands,
+    pieces_atlas: &Handle<TextureAtlas>,
+    orientation: BoardOrientation,
+    board: &HexBoard,
+    piece_sprites: &mut PieceSprites,
+) {
+    for (coord, piece) in board.iter() {
+        let entity = spawn_piece(commands, pieces_atlas, orientation, coord, piece);
+        piece_sprites.insert(coord, entity);
+    }
+}
+
+fn despawn_pieces(commands: &mut Commands, piece_sprites: &mut PieceSprites) {
+    for (_, entity) in piece_sprites.drain() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -101,16 +374,18 @@ fn setup(
     mut pieces_atlases: ResMut<Assets<TextureAtlas>>,
     game: Res<Game>,
     mut piece_sprites: ResMut<PieceSprites>,
+    orientation: Res<BoardOrientation>,
 ) {
     commands
         .spawn_bundle(Camera2dBundle {
             projection: OrthographicProjection {
                 scaling_mode: ScalingMode::Auto {
-                    min_width: 900.0,
+                    min_width: 900.0 + PANEL_WIDTH,
                     min_height: 1000.0,
                 },
                 ..default()
             },
+            transform: Transform::from_xyz(PANEL_WIDTH / 2.0, 0.0, 999.9),
             ..default()
         })
         .insert(MainCamera);
@@ -126,47 +401,28 @@ fn setup(
 
     let hex_mesh = meshes.add(shape::RegularPolygon::new(RADIUS, 6).into());
 
-    for q in -N..=N {
-        let r1 = (-N).max(-q - N);
-        let r2 = N.min(-q + N);
-        for r in r1..=r2 {
-            let coord = Coord::new(q, r);
-            let pixel = flat_hex_to_pixel(coord, RADIUS);
-
-            if let Ok(hex_chess_lib::Piece { team, name, .. }) = game.board.get(coord) {
-                let piece = commands
-                    .spawn_bundle(SpatialBundle {
-                        transform: Transform::from_translation(pixel.extend(1.0)),
-                        ..default()
-                    })
-                    .with_children(|parent| {
-                        parent.spawn_bundle(SpriteSheetBundle {
-                            sprite: TextureAtlasSprite {
-                                index: ATLAS_SIZE.0 * *team as usize + *name as usize,
-                                ..default()
-                            },
-                            texture_atlas: pieces_atlas_handle.clone(),
-                            transform: Transform::from_scale(Vec3::splat(0.8)),
-                            ..default()
-                        });
-                    })
-                    .insert(Piece)
-                    .id();
+    for coord in board_hexes(game.board.radius()) {
+        let pixel = flat_hex_to_pixel(coord, RADIUS, *orientation);
 
-                piece_sprites.insert(coord, piece);
-            }
-
-            commands
-                .spawn_bundle(MaterialMesh2dBundle {
-                    mesh: hex_mesh.clone().into(),
-                    material: hex_materials.mat_mid.clone(),
-                    transform: Transform::from_translation(pixel.extend(0.))
-                        .with_rotation(Quat::from_axis_angle(Vec3::Z, std::f32::consts::FRAC_PI_6)),
-                    ..default()
-                })
-                .insert(HexCoord { coord });
-        }
+        commands
+            .spawn_bundle(MaterialMesh2dBundle {
+                mesh: hex_mesh.clone().into(),
+                material: hex_materials.mat_mid.clone(),
+                transform: Transform::from_translation(pixel.extend(0.))
+                    .with_rotation(Quat::from_axis_angle(Vec3::Z, std::f32::consts::FRAC_PI_6)),
+                ..default()
+            })
+            .insert(HexCoord { coord });
     }
+
+    spawn_pieces(
+        &mut commands,
+        &pieces_atlas_handle,
+        *orientation,
+        &game.board,
+        &mut piece_sprites,
+    );
+    commands.insert_resource(PiecesAtlas(pieces_atlas_handle));
 }
 
 fn screen_to_world(
@@ -181,15 +437,169 @@ fn screen_to_world(
     world_pos.truncate()
 }
 
+// slide a piece sprite over to the center of `to`
+fn ease_piece(
+    commands: &mut Commands,
+    orientation: BoardOrientation,
+    entity: Entity,
+    transform: Transform,
+    to: Coord,
+) {
+    commands.entity(entity).insert(transform.ease_to(
+        Transform::from_translation(flat_hex_to_pixel(to, RADIUS, orientation).extend(1.0)),
+        EaseMethod::EaseFunction(EaseFunction::QuadraticOut),
+        EasingType::Once {
+            duration: Duration::from_millis(200),
+        },
+    ));
+}
+
+// try to play a move, updating the sprites if it works
+#[allow(clippy::too_many_arguments)]
+fn play_move(
+    commands: &mut Commands,
+    orientation: BoardOrientation,
+    game: &mut Game,
+    piece_sprites: &mut PieceSprites,
+    q_piece_transforms: &Query<&mut Transform, With<Piece>>,
+    last_move: &mut LastMove,
+    moved: &mut EventWriter<PieceMoved>,
+    turn_clock: &mut TurnClock,
+    mv: Move,
+) -> bool {
+    // the time spent on this move counts whether or not it's legal
+    game.tick(std::mem::take(&mut turn_clock.0));
+    if game.finished() {
+        eprintln!("game already finished - {}", game.result());
+        return false;
+    }
+    let Move { from, to, .. } = mv;
+    let record = match game.make_move(mv) {
+        Ok(record) => record,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+
+    // move the piece sprite
+    let entity = piece_sprites.remove(&from).unwrap();
+    let transform = *q_piece_transforms.get(entity).unwrap();
+    // delete the captured piece if there is one
+    if let Some(captured_at) = record.captured_at() {
+        let captured = piece_sprites.remove(&captured_at).unwrap();
+        commands.entity(captured).despawn_recursive();
+    }
+    ease_piece(commands, orientation, entity, transform, to);
+    piece_sprites.insert(to, entity);
+
+    last_move.0 = Some((from, to));
+    moved.send(PieceMoved {
+        mv: record.mv,
+        team: record.piece.team,
+        capture: record.captured.is_some(),
+        check: record.check,
+        checkmate: record.checkmate,
+        promotion: record
+            .mv
+            .promotion
+            .map(|name| hex_chess_lib::Piece::new(name, record.piece.team)),
+    });
+    true
+}
+
+// hold a legal pawn move to the last rank back until a piece is picked for it,
+// leaving the pawn sitting on the hex it's going to
+fn wait_for_promotion(
+    commands: &mut Commands,
+    orientation: BoardOrientation,
+    game: &Game,
+    piece_sprites: &PieceSprites,
+    q_piece_transforms: &Query<&mut Transform, With<Piece>>,
+    pending: &mut PendingPromotion,
+    mv: Move,
+) -> bool {
+    if !game.promotes(mv) || game.preview(mv).is_err() {
+        return false;
+    }
+    let entity = piece_sprites[&mv.from];
+    let transform = *q_piece_transforms.get(entity).unwrap();
+    ease_piece(commands, orientation, entity, transform, mv.to);
+    pending.0 = Some(mv);
+    true
+}
+
+// gather the left mouse button and touches into one pointer, so the board
+// plays the same either way
+fn pointer_system(
+    time: Res<Time>,
+    wnds: Res<Windows>,
+    mut mouse_button_events: EventReader<MouseButtonInput>,
+    mut touch_events: EventReader<TouchInput>,
+    mut pointer: ResMut<Pointer>,
+) {
+    let wnd = wnds.get_primary().unwrap();
+    let cursor = wnd.cursor_position();
+    pointer.presses.clear();
+    for event in mouse_button_events.iter() {
+        if let (MouseButton::Left, Some(position)) = (event.button, cursor) {
+            pointer.presses.push((event.state, position));
+        }
+    }
+    for touch in touch_events.iter() {
+        // bevy only turns touches the right way up on phones
+        let position = if cfg!(any(target_os = "android", target_os = "ios")) {
+            touch.position
+        } else {
+            Vec2::new(touch.position.x, wnd.height() - touch.position.y)
+        };
+        pointer.last_touch = Some(position);
+        match touch.phase {
+            TouchPhase::Started => {
+                pointer.presses.push((ButtonState::Pressed, position));
+                pointer.resting = Some((position, time.time_since_startup()));
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                pointer.presses.push((ButtonState::Released, position));
+                pointer.resting = None;
+            }
+            TouchPhase::Moved => {
+                if matches!(pointer.resting, Some((start, _)) if start.distance(position) > TOUCH_SLOP)
+                {
+                    pointer.resting = None;
+                }
+            }
+        }
+    }
+    pointer.position = cursor.or(pointer.last_touch);
+
+    pointer.long_press = None;
+    if let Some((position, since)) = pointer.resting {
+        if time.time_since_startup() - since >= LONG_PRESS {
+            pointer.long_press = Some(position);
+            pointer.resting = None;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn piece_click_system(
     mut commands: Commands,
-    mut mouse_button_events: EventReader<MouseButtonInput>,
+    pointer: Res<Pointer>,
     wnds: Res<Windows>,
     q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     mut game: ResMut<Game>,
     mut piece_sprites: ResMut<PieceSprites>,
     mut q_piece_transforms: Query<&mut Transform, With<Piece>>,
     mut select: ResMut<SelectedHex>,
+    mut last_move: ResMut<LastMove>,
+    mut dragging: ResMut<Dragging>,
+    orientation: Res<BoardOrientation>,
+    mut moved: EventWriter<PieceMoved>,
+    config: Res<PlayerConfig>,
+    editing: Res<Editing>,
+    mut pending: ResMut<PendingPromotion>,
+    mut turn_clock: ResMut<TurnClock>,
 ) {
     let (camera, camera_transform) = q_camera.single();
 
@@ -199,61 +609,951 @@ fn piece_click_system(
         wnds.get_primary().unwrap()
     };
 
-    if let Some(screen_pos) = wnd.cursor_position() {
-        let world_pos = screen_to_world(
+    let to_world = |screen_pos| {
+        screen_to_world(
             screen_pos,
             Vec2::new(wnd.width(), wnd.height()),
             camera,
             camera_transform,
-        );
-        let hex_pos = pixel_to_flat_hex(world_pos, RADIUS);
-
-        // set hovered tile
-        select.hover = Some(hex_pos);
+        )
+    };
 
-        for event in mouse_button_events.iter() {
-            if event.button == MouseButton::Left && event.state == ButtonState::Pressed {
-                if game.board.get(hex_pos).is_ok()
+    for &(state, screen_pos) in pointer.presses.iter() {
+        if editing.0 || pending.0.is_some() {
+            continue;
+        }
+        let hex_pos = pixel_to_flat_hex(to_world(screen_pos), RADIUS, *orientation);
+        match state {
+            ButtonState::Pressed => {
+                if game.turn != config.human {
+                    // wait for the bot, letting go of any moves a long press
+                    // showed
+                    select.deselect();
+                } else if game.board.get(hex_pos).is_ok()
                     && game.board.get(hex_pos).unwrap().team == game.turn
                 {
-                    select.selected = Some(hex_pos);
+                    select.select(hex_pos, &game.board);
+                    dragging.0 = Some(hex_pos);
                 } else if let Some(from) = select.selected {
-                    if !game.finished() {
-                        match game.move_piece(from, hex_pos) {
-                            Ok(_) => {
-                                // move the piece sprite
-                                let entity = piece_sprites.remove(&from).unwrap();
-                                let transform = q_piece_transforms.get_mut(entity).unwrap();
-                                // delete the captured piece if there is one
-                                if let Some(_) = piece_sprites.get(&hex_pos) {
-                                    let captured = piece_sprites.remove(&hex_pos).unwrap();
-                                    commands.entity(captured).despawn_recursive();
-                                }
-                                commands.entity(entity).insert(
-                                    transform.ease_to(
-                                        Transform::from_translation(
-                                            flat_hex_to_pixel(hex_pos, RADIUS)
-                                                .extend(transform.translation.z),
-                                        ),
-                                        EaseMethod::EaseFunction(EaseFunction::QuadraticOut),
-                                        EasingType::Once {
-                                            duration: Duration::from_millis(200),
-                                        },
-                                    ),
-                                );
-                                piece_sprites.insert(hex_pos, entity);
-
-                                select.selected = None;
-                            }
-                            Err(e) => eprintln!("{}", e),
-                        }
-                    } else {
-                        eprintln!("game already finished - won by {}", game.turn.flip());
+                    let mv = Move::new(from, hex_pos);
+                    if wait_for_promotion(
+                        &mut commands,
+                        *orientation,
+                        &game,
+                        &piece_sprites,
+                        &q_piece_transforms,
+                        &mut pending,
+                        mv,
+                    ) || play_move(
+                        &mut commands,
+                        *orientation,
+                        &mut game,
+                        &mut piece_sprites,
+                        &q_piece_transforms,
+                        &mut last_move,
+                        &mut moved,
+                        &mut turn_clock,
+                        mv,
+                    ) {
+                        select.deselect();
                     }
+                } else {
+                    select.deselect();
+                }
+            }
+            ButtonState::Released => {
+                let from = match dragging.0.take() {
+                    Some(from) => from,
+                    None => continue,
+                };
+                // dropping back on the origin leaves the piece selected so
+                // it can still be moved with a second click
+                let mv = Move::new(from, hex_pos);
+                if hex_pos != from
+                    && (wait_for_promotion(
+                        &mut commands,
+                        *orientation,
+                        &game,
+                        &piece_sprites,
+                        &q_piece_transforms,
+                        &mut pending,
+                        mv,
+                    ) || play_move(
+                        &mut commands,
+                        *orientation,
+                        &mut game,
+                        &mut piece_sprites,
+                        &q_piece_transforms,
+                        &mut last_move,
+                        &mut moved,
+                        &mut turn_clock,
+                        mv,
+                    ))
+                {
+                    select.deselect();
+                } else if let Some(&entity) = piece_sprites.get(&from) {
+                    let transform = *q_piece_transforms.get(entity).unwrap();
+                    ease_piece(&mut commands, *orientation, entity, transform, from);
                 }
             }
         }
     }
+
+    if let Some(screen_pos) = pointer.position {
+        let world_pos = to_world(screen_pos);
+        // set hovered tile
+        select.hover = Some(pixel_to_flat_hex(world_pos, RADIUS, *orientation));
+
+        // the dragged piece follows the pointer above everything else
+        if let Some(&entity) = dragging.0.and_then(|from| piece_sprites.get(&from)) {
+            let mut transform = q_piece_transforms.get_mut(entity).unwrap();
+            transform.translation = world_pos.extend(2.0);
+        }
+    }
+}
+
+// show where the piece under a long press can go, whichever team it's on.
+// only the team to move can be picked up, so the others are just shown
+fn long_press_system(
+    pointer: Res<Pointer>,
+    wnds: Res<Windows>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    orientation: Res<BoardOrientation>,
+    game: Res<Game>,
+    editing: Res<Editing>,
+    mut select: ResMut<SelectedHex>,
+) {
+    let screen_pos = match pointer.long_press {
+        Some(screen_pos) if !editing.0 => screen_pos,
+        _ => return,
+    };
+    let (camera, camera_transform) = q_camera.single();
+    let wnd = if let RenderTarget::Window(id) = camera.target {
+        wnds.get(id).unwrap()
+    } else {
+        wnds.get_primary().unwrap()
+    };
+    let world_pos = screen_to_world(
+        screen_pos,
+        Vec2::new(wnd.width(), wnd.height()),
+        camera,
+        camera_transform,
+    );
+    let hex_pos = pixel_to_flat_hex(world_pos, RADIUS, *orientation);
+    // a piece picked up to move already shows its moves
+    if game.board.get(hex_pos).is_ok() && select.selected != Some(hex_pos) {
+        select.preview(hex_pos, &game.board);
+    }
+}
+
+// search for the bot's move off the main thread, then play it once found
+#[allow(clippy::too_many_arguments)]
+fn bot_system(
+    mut commands: Commands,
+    config: Res<PlayerConfig>,
+    mut search: ResMut<BotSearch>,
+    mut game: ResMut<Game>,
+    mut piece_sprites: ResMut<PieceSprites>,
+    q_piece_transforms: Query<&mut Transform, With<Piece>>,
+    mut last_move: ResMut<LastMove>,
+    mut moved: EventWriter<PieceMoved>,
+    orientation: Res<BoardOrientation>,
+    editing: Res<Editing>,
+    mut turn_clock: ResMut<TurnClock>,
+) {
+    if game.turn == config.human || config.remote || game.finished() || editing.0 {
+        search.0 = None;
+        return;
+    }
+
+    let key = game.board.transposition_key(game.turn);
+    let (task, started_from) = match &mut search.0 {
+        Some(search) => search,
+        None => {
+            let board = game.board.clone();
+            let team = game.turn;
+            let limits = config.bot_limits;
+            let task = AsyncComputeTaskPool::get()
+                .spawn(async move { engine::search(&board, team, limits) });
+            search.0 = Some((task, key));
+            return;
+        }
+    };
+
+    if let Some(mv) = future::block_on(future::poll_once(task)) {
+        // the board changed under the search (undo or restart), so its answer
+        // is no good
+        let stale = *started_from != key;
+        search.0 = None;
+        if let (Some(mv), false) = (mv, stale) {
+            play_move(
+                &mut commands,
+                *orientation,
+                &mut game,
+                &mut piece_sprites,
+                &q_piece_transforms,
+                &mut last_move,
+                &mut moved,
+                &mut turn_clock,
+                mv,
+            );
+        }
+    }
+}
+
+// give each move a sound - checkmate outranks check, which outranks a capture
+fn move_sound_system(
+    mut moved: EventReader<PieceMoved>,
+    audio: Res<Audio>,
+    sounds: Res<Sounds>,
+    muted: Res<Muted>,
+) {
+    for event in moved.iter() {
+        if muted.0 {
+            continue;
+        }
+        let sound = if event.checkmate {
+            &sounds.checkmate
+        } else if event.check {
+            &sounds.check
+        } else if event.capture {
+            &sounds.capture
+        } else {
+            &sounds.move_piece
+        };
+        audio.play(sound.clone());
+    }
+}
+
+// switch the sounds off or back on with M
+fn mute_system(keys: Res<Input<KeyCode>>, mut muted: ResMut<Muted>) {
+    if keys.just_pressed(KeyCode::M) {
+        muted.0 = !muted.0;
+    }
+}
+
+// drop the selection on a right click or escape, putting back a dragged piece
+#[allow(clippy::too_many_arguments)]
+fn deselect_system(
+    mut commands: Commands,
+    mouse_buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    piece_sprites: Res<PieceSprites>,
+    q_piece_transforms: Query<&Transform, With<Piece>>,
+    mut select: ResMut<SelectedHex>,
+    mut dragging: ResMut<Dragging>,
+    orientation: Res<BoardOrientation>,
+    mut pending: ResMut<PendingPromotion>,
+) {
+    if mouse_buttons.just_pressed(MouseButton::Right) || keys.just_pressed(KeyCode::Escape) {
+        select.deselect();
+        // backing out of a promotion puts the pawn back where it was
+        let waiting = pending.0.take().map(|mv| mv.from);
+        if let Some(from) = dragging.0.take().or(waiting) {
+            if let Some(&entity) = piece_sprites.get(&from) {
+                let transform = *q_piece_transforms.get(entity).unwrap();
+                ease_piece(&mut commands, *orientation, entity, transform, from);
+            }
+        }
+    }
+}
+
+// take back the last move with U, or play it again with Y, and rebuild the
+// sprites from the restored board
+#[allow(clippy::too_many_arguments)]
+fn undo_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut game: ResMut<Game>,
+    mut piece_sprites: ResMut<PieceSprites>,
+    mut select: ResMut<SelectedHex>,
+    mut last_move: ResMut<LastMove>,
+    pieces_atlas: Res<PiecesAtlas>,
+    orientation: Res<BoardOrientation>,
+    editing: Res<Editing>,
+    mut pending: ResMut<PendingPromotion>,
+    mut turn_clock: ResMut<TurnClock>,
+    config: Res<PlayerConfig>,
+) {
+    // the history doesn't know about edits, and the other side of a network
+    // game can't take its moves back
+    if editing.0 || config.remote {
+        return;
+    }
+    let changed = if keys.just_pressed(KeyCode::U) {
+        game.undo()
+    } else if keys.just_pressed(KeyCode::Y) {
+        game.redo()
+    } else {
+        false
+    };
+    if changed {
+        despawn_pieces(&mut commands, &mut piece_sprites);
+        spawn_pieces(
+            &mut commands,
+            &pieces_atlas.0,
+            *orientation,
+            &game.board,
+            &mut piece_sprites,
+        );
+        select.deselect();
+        pending.0 = None;
+        turn_clock.0 = Duration::ZERO;
+        last_move.0 = game
+            .history()
+            .last()
+            .map(|record| (record.mv.from, record.mv.to));
+    }
+}
+
+// start a new game with R or the game over button, replacing every piece
+// sprite
+#[allow(clippy::too_many_arguments)]
+fn restart_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut restarts: EventReader<RestartGame>,
+    mut game: ResMut<Game>,
+    mut piece_sprites: ResMut<PieceSprites>,
+    mut select: ResMut<SelectedHex>,
+    mut last_move: ResMut<LastMove>,
+    mut dragging: ResMut<Dragging>,
+    pieces_atlas: Res<PiecesAtlas>,
+    orientation: Res<BoardOrientation>,
+    mut pending: ResMut<PendingPromotion>,
+    mut turn_clock: ResMut<TurnClock>,
+    config: Res<PlayerConfig>,
+) {
+    // read every event so none are left over for the next frame
+    // a network game is started by the server
+    if (restarts.iter().count() > 0 || keys.just_pressed(KeyCode::R)) && !config.remote {
+        *game = config.new_game();
+        despawn_pieces(&mut commands, &mut piece_sprites);
+        spawn_pieces(
+            &mut commands,
+            &pieces_atlas.0,
+            *orientation,
+            &game.board,
+            &mut piece_sprites,
+        );
+        *select = SelectedHex::default();
+        last_move.0 = None;
+        dragging.0 = None;
+        pending.0 = None;
+        turn_clock.0 = Duration::ZERO;
+    }
+}
+
+/// one of the pieces offered in place of a promoting pawn
+#[derive(Debug, Clone, Copy, Component)]
+struct PromotionChoice(Name);
+
+// lay out a row of the pieces a pawn can become over the hex it's promoting
+// on, and play the move with whichever one gets clicked
+#[allow(clippy::too_many_arguments)]
+fn promotion_picker_system(
+    mut commands: Commands,
+    pointer: Res<Pointer>,
+    wnds: Res<Windows>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut game: ResMut<Game>,
+    mut piece_sprites: ResMut<PieceSprites>,
+    q_piece_transforms: Query<&mut Transform, With<Piece>>,
+    mut last_move: ResMut<LastMove>,
+    mut moved: EventWriter<PieceMoved>,
+    orientation: Res<BoardOrientation>,
+    pieces_atlas: Res<PiecesAtlas>,
+    mut pending: ResMut<PendingPromotion>,
+    mut turn_clock: ResMut<TurnClock>,
+    q_choices: Query<(Entity, &PromotionChoice, &GlobalTransform)>,
+) {
+    let mv = match pending.0 {
+        Some(mv) if !orientation.is_changed() => mv,
+        _ => {
+            for (entity, _, _) in q_choices.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            return;
+        }
+    };
+
+    if q_choices.is_empty() {
+        // keep the row clear of the board's left and right edges
+        let center = flat_hex_to_pixel(mv.to, RADIUS, *orientation);
+        let center = Vec2::new(center.x.clamp(-3.0 * RADIUS, 3.0 * RADIUS), center.y);
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgba(0.0, 0.0, 0.0, 0.6),
+                    custom_size: Some(Vec2::new(8.0 * RADIUS, 2.0 * RADIUS)),
+                    ..default()
+                },
+                transform: Transform::from_translation(center.extend(3.0)),
+                ..default()
+            })
+            .with_children(|parent| {
+                for (i, name) in Name::PROMOTIONS.into_iter().enumerate() {
+                    let x = (i as f32 - 1.5) * 2.0 * RADIUS;
+                    parent
+                        .spawn_bundle(SpriteSheetBundle {
+                            sprite: TextureAtlasSprite {
+                                index: atlas_index(hex_chess_lib::Piece::new(name, game.turn)),
+                                ..default()
+                            },
+                            texture_atlas: pieces_atlas.0.clone(),
+                            transform: Transform::from_xyz(x, 0.0, 0.1)
+                                .with_scale(Vec3::splat(0.8)),
+                            ..default()
+                        })
+                        .insert(PromotionChoice(name));
+                }
+            });
+        return;
+    }
+
+    let screen_pos = match pointer.just_pressed() {
+        Some(screen_pos) => screen_pos,
+        None => return,
+    };
+    let (camera, camera_transform) = q_camera.single();
+    let wnd = if let RenderTarget::Window(id) = camera.target {
+        wnds.get(id).unwrap()
+    } else {
+        wnds.get_primary().unwrap()
+    };
+    let world_pos = screen_to_world(
+        screen_pos,
+        Vec2::new(wnd.width(), wnd.height()),
+        camera,
+        camera_transform,
+    );
+    let picked = q_choices
+        .iter()
+        .find(|(_, _, transform)| transform.translation().truncate().distance(world_pos) < RADIUS);
+    if let Some((_, &PromotionChoice(name), _)) = picked {
+        pending.0 = None;
+        play_move(
+            &mut commands,
+            *orientation,
+            &mut game,
+            &mut piece_sprites,
+            &q_piece_transforms,
+            &mut last_move,
+            &mut moved,
+            &mut turn_clock,
+            Move::promoting(mv.from, mv.to, name),
+        );
+    }
+}
+
+// show a promoted pawn as what it became
+fn promotion_sprite_system(
+    mut moved: EventReader<PieceMoved>,
+    piece_sprites: Res<PieceSprites>,
+    q_children: Query<&Children, With<Piece>>,
+    mut q_sprites: Query<&mut TextureAtlasSprite>,
+) {
+    for event in moved.iter() {
+        let promotion = match event.promotion {
+            Some(promotion) => promotion,
+            None => continue,
+        };
+        // the sprite is gone already if the game was restarted straight away
+        let children = match piece_sprites.get(&event.mv.to).map(|&e| q_children.get(e)) {
+            Some(Ok(children)) => children,
+            _ => continue,
+        };
+        for &child in children.iter() {
+            if let Ok(mut sprite) = q_sprites.get_mut(child) {
+                sprite.index = atlas_index(promotion);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+struct CapturedPiece;
+
+const TRAY_ROW: usize = 8;
+
+// lay out the pieces each team has taken in rows in the corner on its side of
+// the board, redrawing them whenever the game or the view changes
+fn captured_tray_system(
+    mut commands: Commands,
+    game: Res<Game>,
+    orientation: Res<BoardOrientation>,
+    pieces_atlas: Res<PiecesAtlas>,
+    trays: Query<Entity, With<CapturedPiece>>,
+) {
+    if !game.is_changed() && !orientation.is_changed() {
+        return;
+    }
+    for entity in trays.iter() {
+        commands.entity(entity).despawn();
+    }
+    for team in [Team::White, Team::Black] {
+        let at_bottom = matches!(
+            (team, *orientation),
+            (Team::White, BoardOrientation::White) | (Team::Black, BoardOrientation::Black)
+        );
+        let y: f32 = if at_bottom { -470.0 } else { 470.0 };
+        for (i, piece) in game.captured(team).iter().enumerate() {
+            let (row, col) = (i / TRAY_ROW, i % TRAY_ROW);
+            let pixel = Vec2::new(
+                -430.0 + 32.0 * col as f32,
+                y - y.signum() * 32.0 * row as f32,
+            );
+            commands
+                .spawn_bundle(SpriteSheetBundle {
+                    sprite: TextureAtlasSprite {
+                        index: ATLAS_SIZE.0 * piece.team as usize + piece.name as usize,
+                        ..default()
+                    },
+                    texture_atlas: pieces_atlas.0.clone(),
+                    transform: Transform::from_translation(pixel.extend(1.0))
+                        .with_scale(Vec3::splat(0.35)),
+                    ..default()
+                })
+                .insert(CapturedPiece);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+struct Panel;
+
+#[derive(Debug, Clone, Copy, Component)]
+struct StatusText;
+
+#[derive(Debug, Clone, Copy, Component)]
+struct MoveLogText;
+
+#[derive(Debug, Clone, Copy, Component)]
+struct FlipButton;
+
+#[derive(Debug, Clone, Copy, Component)]
+struct ClockText;
+
+fn setup_panel(mut commands: Commands, font: Res<UiFont>) {
+    let text_style = |font_size| TextStyle {
+        font: font.0.clone(),
+        font_size,
+        color: Color::rgb(0.15, 0.15, 0.15),
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(PANEL_WIDTH), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    ..default()
+                },
+                // columns run upwards, so reverse them to read top down
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: UiRect::all(Val::Px(16.0)),
+                // under the board there isn't room for the whole move log
+                overflow: Overflow::Hidden,
+                ..default()
+            },
+            color: Color::rgb(0.8, 0.88, 0.92).into(),
+            ..default()
+        })
+        .insert(Panel)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle::from_section("", text_style(28.0)))
+                .insert(StatusText);
+            parent
+                .spawn_bundle(
+                    TextBundle::from_section("", text_style(28.0)).with_style(Style {
+                        margin: UiRect {
+                            top: Val::Px(16.0),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                )
+                .insert(ClockText);
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.0), Val::Px(40.0)),
+                        margin: UiRect {
+                            top: Val::Px(16.0),
+                            ..default()
+                        },
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: Color::rgb(0.6, 0.72, 0.8).into(),
+                    ..default()
+                })
+                .insert(FlipButton)
+                .with_children(|button| {
+                    button
+                        .spawn_bundle(TextBundle::from_section("Flip Board (F)", text_style(20.0)));
+                });
+            parent
+                .spawn_bundle(
+                    TextBundle::from_section("", text_style(20.0)).with_style(Style {
+                        margin: UiRect {
+                            top: Val::Px(16.0),
+                            ..default()
+                        },
+                        ..default()
+                    }),
+                )
+                .insert(MoveLogText);
+        });
+}
+
+// fit the board and panel to the window's shape, with the panel beside the
+// board on wide screens and under it on tall ones like upright phones
+fn layout_system(
+    wnds: Res<Windows>,
+    mut portrait: Local<Option<bool>>,
+    mut q_camera: Query<(&mut OrthographicProjection, &mut Transform), With<MainCamera>>,
+    mut q_panel: Query<&mut Style, With<Panel>>,
+) {
+    let wnd = wnds.get_primary().unwrap();
+    let tall = wnd.height() > wnd.width();
+    if *portrait == Some(tall) {
+        return;
+    }
+    *portrait = Some(tall);
+
+    let (mut projection, mut transform) = q_camera.single_mut();
+    let mut style = q_panel.single_mut();
+    if tall {
+        projection.scaling_mode = ScalingMode::Auto {
+            min_width: 900.0,
+            min_height: 1000.0 + PANEL_HEIGHT,
+        };
+        transform.translation.x = 0.0;
+        transform.translation.y = -PANEL_HEIGHT / 2.0;
+        style.size = Size::new(Val::Percent(100.0), Val::Px(PANEL_HEIGHT));
+        style.position = UiRect {
+            left: Val::Px(0.0),
+            bottom: Val::Px(0.0),
+            ..default()
+        };
+    } else {
+        projection.scaling_mode = ScalingMode::Auto {
+            min_width: 900.0 + PANEL_WIDTH,
+            min_height: 1000.0,
+        };
+        transform.translation.x = PANEL_WIDTH / 2.0;
+        transform.translation.y = 0.0;
+        style.size = Size::new(Val::Px(PANEL_WIDTH), Val::Percent(100.0));
+        style.position = UiRect {
+            right: Val::Px(0.0),
+            top: Val::Px(0.0),
+            ..default()
+        };
+    }
+}
+
+/// a clock reading like `4:05`, rounding up so a clock only shows `0:00` once
+/// it has run out
+fn format_clock(time: Duration) -> String {
+    let seconds = time.as_millis().div_ceil(1000);
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// run the clock of the team to move, only touching the game when its flag
+// falls, and count both clocks down in the panel
+fn clock_system(
+    time: Res<Time>,
+    mut game: ResMut<Game>,
+    mut turn_clock: ResMut<TurnClock>,
+    editing: Res<Editing>,
+    mut q_text: Query<&mut Text, With<ClockText>>,
+) {
+    let left = match game.remaining(game.turn) {
+        Some(left) if !game.finished() && !editing.0 => left,
+        _ => {
+            for mut text in q_text.iter_mut() {
+                text.sections[0].value.clear();
+            }
+            return;
+        }
+    };
+    turn_clock.0 += time.delta();
+    if turn_clock.0 >= left {
+        game.tick(std::mem::take(&mut turn_clock.0));
+    }
+    let shown = |team: Team| {
+        let left = game.remaining(team).unwrap_or_default();
+        if team == game.turn {
+            left.saturating_sub(turn_clock.0)
+        } else {
+            left
+        }
+    };
+    let clocks = format!(
+        "{} {}\n{} {}",
+        Team::White,
+        format_clock(shown(Team::White)),
+        Team::Black,
+        format_clock(shown(Team::Black)),
+    );
+    for mut text in q_text.iter_mut() {
+        text.sections[0].value = clocks.clone();
+    }
+}
+
+// show whose turn it is, whether they're in check, and the latest moves
+fn panel_system(
+    game: Res<Game>,
+    mut status: Query<&mut Text, (With<StatusText>, Without<MoveLogText>)>,
+    mut move_log: Query<&mut Text, (With<MoveLogText>, Without<StatusText>)>,
+) {
+    if !game.is_changed() {
+        return;
+    }
+    let status_line = if game.finished() {
+        game.result().to_string()
+    } else if game.in_check(game.turn) {
+        format!("{}'s turn\ncheck!", game.turn)
+    } else {
+        format!("{}'s turn", game.turn)
+    };
+    for mut text in status.iter_mut() {
+        text.sections[0].value = status_line.clone();
+    }
+
+    let moves = game.move_list();
+    let shown = &moves[moves.len().saturating_sub(MOVE_LOG_LINES)..];
+    for mut text in move_log.iter_mut() {
+        text.sections[0].value = shown.join("\n");
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+struct GameOverOverlay;
+
+#[derive(Debug, Clone, Copy, Component)]
+struct NewGameButton;
+
+// cover the board with the result and a button for a new game once the game
+// ends, and take it away again if the end is undone
+fn game_over_system(
+    mut commands: Commands,
+    game: Res<Game>,
+    font: Res<UiFont>,
+    overlays: Query<Entity, With<GameOverOverlay>>,
+) {
+    let shown = !overlays.is_empty();
+    if !game.finished() {
+        for entity in overlays.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    if shown {
+        return;
+    }
+    let text_style = |font_size| TextStyle {
+        font: font.0.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                // columns run upwards, so reverse them to read top down
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            ..default()
+        })
+        .insert(GameOverOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle::from_section(
+                game.result().to_string(),
+                text_style(48.0),
+            ));
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(220.0), Val::Px(64.0)),
+                        margin: UiRect::all(Val::Px(24.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: Color::rgb(0.54, 0.2, 0.2).into(),
+                    ..default()
+                })
+                .insert(NewGameButton)
+                .with_children(|button| {
+                    button.spawn_bundle(TextBundle::from_section("New Game", text_style(32.0)));
+                });
+        });
+}
+
+fn new_game_button_system(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<NewGameButton>)>,
+    mut restarts: EventWriter<RestartGame>,
+) {
+    if buttons
+        .iter()
+        .any(|&interaction| interaction == Interaction::Clicked)
+    {
+        restarts.send(RestartGame);
+    }
+}
+
+fn flip_button_system(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<FlipButton>)>,
+    mut flips: EventWriter<FlipBoard>,
+) {
+    if buttons
+        .iter()
+        .any(|&interaction| interaction == Interaction::Clicked)
+    {
+        flips.send(FlipBoard);
+    }
+}
+
+// turn the board around with F or the panel's button, moving every tile and
+// piece to match
+/// move every tile to where it sits with the board turned to `orientation`
+fn place_tiles(
+    orientation: BoardOrientation,
+    q_tiles: &mut Query<(&HexCoord, &mut Transform), Without<Piece>>,
+) {
+    for (HexCoord { coord }, mut transform) in q_tiles.iter_mut() {
+        transform.translation = flat_hex_to_pixel(*coord, RADIUS, orientation).extend(0.0);
+    }
+}
+
+fn flip_board_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut flips: EventReader<FlipBoard>,
+    mut orientation: ResMut<BoardOrientation>,
+    piece_sprites: Res<PieceSprites>,
+    mut q_tiles: Query<(&HexCoord, &mut Transform), Without<Piece>>,
+    mut q_piece_transforms: Query<&mut Transform, With<Piece>>,
+) {
+    // read every event so none are left over for the next frame
+    if flips.iter().count() == 0 && !keys.just_pressed(KeyCode::F) {
+        return;
+    }
+    *orientation = orientation.flip();
+
+    place_tiles(*orientation, &mut q_tiles);
+    for (&coord, &entity) in piece_sprites.iter() {
+        // an easing still in flight would drag the piece back to the old side
+        commands
+            .entity(entity)
+            .remove::<EasingComponent<Transform>>();
+        let mut transform = q_piece_transforms.get_mut(entity).unwrap();
+        transform.translation = flat_hex_to_pixel(coord, RADIUS, *orientation).extend(1.0);
+    }
+}
+
+/// the piece a click in edit mode puts in place of `piece`, going through
+/// every white piece, then every black one, then back to empty
+fn next_edit_piece(piece: Option<hex_chess_lib::Piece>) -> Option<hex_chess_lib::Piece> {
+    const NAMES: [Name; 6] = [
+        Name::Pawn,
+        Name::Knight,
+        Name::Bishop,
+        Name::Rook,
+        Name::Queen,
+        Name::King,
+    ];
+    let mut order = [Team::White, Team::Black]
+        .into_iter()
+        .flat_map(|team| NAMES.map(|name| hex_chess_lib::Piece::new(name, team)));
+    match piece {
+        None => order.next(),
+        Some(piece) => order.skip_while(|&p| p != piece).nth(1),
+    }
+}
+
+// toggle edit mode with E, where clicking a hex cycles the piece on it. the
+// edited position is checked before play resumes from it
+#[allow(clippy::too_many_arguments)]
+fn edit_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    pointer: Res<Pointer>,
+    mut editing: ResMut<Editing>,
+    mut game: ResMut<Game>,
+    mut piece_sprites: ResMut<PieceSprites>,
+    mut select: ResMut<SelectedHex>,
+    mut last_move: ResMut<LastMove>,
+    mut dragging: ResMut<Dragging>,
+    pieces_atlas: Res<PiecesAtlas>,
+    orientation: Res<BoardOrientation>,
+    config: Res<PlayerConfig>,
+    mut turn_clock: ResMut<TurnClock>,
+) {
+    // both sides of a network game have to agree on the position
+    if config.remote {
+        return;
+    }
+    if keys.just_pressed(KeyCode::E) {
+        if !editing.0 {
+            editing.0 = true;
+            select.deselect();
+            dragging.0 = None;
+            last_move.0 = None;
+            return;
+        }
+        let mut builder = game
+            .board
+            .iter()
+            .fold(GameBuilder::new(), |builder, (c, piece)| {
+                builder.place(c, piece)
+            })
+            .turn(game.turn);
+        // the edited position starts on fresh clocks
+        if let Some(time_control) = config.time_control {
+            builder = builder.time_control(time_control);
+        }
+        match builder.build() {
+            Ok(edited) => {
+                *game = edited;
+                editing.0 = false;
+                turn_clock.0 = Duration::ZERO;
+            }
+            Err(e) => eprintln!("can't play from this position - {}", e),
+        }
+        return;
+    }
+
+    if !editing.0 || pointer.just_pressed().is_none() {
+        return;
+    }
+    let coord = match select.hover {
+        Some(coord) if coord.in_bounds(game.board.radius()) => coord,
+        _ => return,
+    };
+    if let Some(entity) = piece_sprites.remove(&coord) {
+        commands.entity(entity).despawn_recursive();
+    }
+    match next_edit_piece(game.board.get(coord).ok().copied()) {
+        Some(piece) => {
+            game.board.place(coord, piece);
+            let entity = spawn_piece(&mut commands, &pieces_atlas.0, *orientation, coord, piece);
+            piece_sprites.insert(coord, entity);
+        }
+        None => {
+            game.board.remove(coord);
+        }
+    }
 }
 
 fn main() {
@@ -261,20 +1561,63 @@ fn main() {
         .insert_resource(ClearColor(Color::rgb(0.89, 0.97, 1.0)))
         .insert_resource(WindowDescriptor {
             title: "Hexagonal Chess".to_string(),
-            width: 900.,
+            width: 900. + PANEL_WIDTH,
             height: 1000.,
+            // on the web, draw into the page's canvas and fill its container
+            #[cfg(target_arch = "wasm32")]
+            canvas: Some("#hex-chess".to_string()),
+            #[cfg(target_arch = "wasm32")]
+            fit_canvas_to_parent: true,
             ..default()
         })
         .add_plugins_with(DefaultPlugins, |group| {
             group.add_before::<bevy::asset::AssetPlugin, _>(EmbeddedAssetPlugin)
         })
         .add_plugin(EasingsPlugin)
+        .add_plugin(network::NetworkPlugin)
         .init_resource::<HexMaterials>()
         .init_resource::<PieceSprites>()
         .init_resource::<SelectedHex>()
-        .init_resource::<Game>()
+        .init_resource::<LastMove>()
+        .init_resource::<Dragging>()
+        .init_resource::<Pointer>()
+        .init_resource::<BoardOrientation>()
+        .init_resource::<Sounds>()
+        .init_resource::<Muted>()
+        .init_resource::<PlayerConfig>()
+        .init_resource::<TurnClock>()
+        .init_resource::<BotSearch>()
+        .init_resource::<Editing>()
+        .init_resource::<PendingPromotion>()
+        .init_resource::<UiFont>()
+        .add_event::<PieceMoved>()
+        .add_event::<RestartGame>()
+        .add_event::<FlipBoard>()
+        .insert_resource(PlayerConfig::default().new_game())
         .add_startup_system(setup)
+        .add_startup_system(setup_panel)
         .add_system(color_tiles)
+        .add_system(pointer_system.before(piece_click_system))
         .add_system(piece_click_system)
+        // after the clicks so the one that picks a piece isn't read as a move
+        .add_system(promotion_picker_system.after(piece_click_system))
+        .add_system(promotion_sprite_system)
+        .add_system(bot_system)
+        .add_system(mute_system)
+        .add_system(move_sound_system.after(mute_system))
+        .add_system(deselect_system)
+        .add_system(undo_system)
+        .add_system(restart_system)
+        .add_system(game_over_system)
+        .add_system(panel_system)
+        .add_system(clock_system)
+        .add_system(new_game_button_system)
+        .add_system(flip_button_system)
+        .add_system(flip_board_system)
+        .add_system(captured_tray_system.after(flip_board_system))
+        // after the clicks so the hex under a touch is already hovered
+        .add_system(edit_system.after(piece_click_system))
+        .add_system(long_press_system.after(pointer_system))
+        .add_system(layout_system)
         .run();
 }
@@ -0,0 +1,538 @@
+//! playing someone else through a hex-chess-server
+//!
+//! the game goes online when started with `--server ws://host:port`, adding
+//! `--join CODE` to take the empty seat in someone else's game - or in a
+//! browser, with `?server=` and `?join=` in the page's url. without a server
+//! the plugin does nothing and the bot plays the other side as usual.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use hex_chess_lib::{
+    net::{self, ClientMsg, Message, MoveMsg, RemoteGame, ServerMsg},
+    Team,
+};
+
+use crate::{
+    despawn_pieces, hex_rect::BoardOrientation, piece_click_system, place_tiles, play_move,
+    promotion_picker_system, spawn_pieces, Dragging, Game, HexCoord, LastMove, PendingPromotion,
+    Piece, PieceMoved, PieceSprites, PiecesAtlas, PlayerConfig, SelectedHex, TurnClock, UiFont,
+};
+
+use self::transport::Connection;
+
+/// how long to wait before trying a lost server again
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// what a connection has to tell the game, queued up until the next frame
+enum NetEvent {
+    Connected,
+    Message(ServerMsg),
+    Disconnected(String),
+}
+
+type Inbox = Arc<Mutex<VecDeque<NetEvent>>>;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod transport {
+    //! a websocket client on a plain tcp stream, with a thread reading
+    //! whatever the server sends
+
+    use std::{
+        io,
+        net::TcpStream,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    use hex_chess_lib::{
+        net::ClientMsg,
+        ws::{self, Frame, Reader, Side},
+    };
+
+    use super::{Inbox, NetEvent};
+
+    pub struct Connection {
+        inbox: Inbox,
+        /// the stream to write to, once the handshake is done
+        stream: Arc<Mutex<Option<TcpStream>>>,
+    }
+
+    impl Connection {
+        /// start connecting to `url` in the background
+        pub fn open(url: &str) -> Self {
+            let inbox = Inbox::default();
+            let stream = Arc::new(Mutex::new(None));
+            let (events, writer, url) = (Arc::clone(&inbox), Arc::clone(&stream), url.to_string());
+            thread::spawn(move || {
+                let reason = match run(&url, &events, &writer) {
+                    Ok(()) => "the server closed the connection".to_string(),
+                    Err(e) => e.to_string(),
+                };
+                *writer.lock().unwrap() = None;
+                events
+                    .lock()
+                    .unwrap()
+                    .push_back(NetEvent::Disconnected(reason));
+            });
+            Self { inbox, stream }
+        }
+
+        pub fn poll(&self) -> Option<NetEvent> {
+            self.inbox.lock().unwrap().pop_front()
+        }
+
+        pub fn send(&self, msg: &ClientMsg) {
+            let text = serde_json::to_string(msg).expect("client messages serialize");
+            if let Some(stream) = self.stream.lock().unwrap().as_mut() {
+                // a failed write shows up as the reader losing the connection
+                let _ = ws::write_text(stream, Side::Client, &text);
+            }
+        }
+    }
+
+    /// connect and read messages until the connection goes away
+    fn run(url: &str, events: &Inbox, writer: &Mutex<Option<TcpStream>>) -> io::Result<()> {
+        let address = url.strip_prefix("ws://").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only ws:// servers are supported",
+            )
+        })?;
+        let (host, path) = match address.split_once('/') {
+            Some((host, path)) => (host, format!("/{}", path)),
+            None => (address, "/".to_string()),
+        };
+        let mut stream = TcpStream::connect(host)?;
+        ws::request_upgrade(&mut stream, host, &path)?;
+        *writer.lock().unwrap() = Some(stream.try_clone()?);
+        events.lock().unwrap().push_back(NetEvent::Connected);
+
+        let mut reader = Reader::new(stream);
+        loop {
+            match reader.read_frame()? {
+                Frame::Close => return Ok(()),
+                Frame::Ping(payload) => {
+                    if let Some(stream) = writer.lock().unwrap().as_mut() {
+                        ws::write_pong(stream, Side::Client, &payload)?;
+                    }
+                }
+                Frame::Text(text) => match serde_json::from_str(&text) {
+                    Ok(msg) => events.lock().unwrap().push_back(NetEvent::Message(msg)),
+                    Err(e) => eprintln!("unreadable message from the server - {}", e),
+                },
+                Frame::Ignored => {}
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod transport {
+    //! the browser's own websockets
+
+    use std::sync::Arc;
+
+    use hex_chess_lib::net::ClientMsg;
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use web_sys::{Event, MessageEvent, WebSocket};
+
+    use super::{Inbox, NetEvent};
+
+    pub struct Connection {
+        inbox: Inbox,
+        socket: Option<WebSocket>,
+        /// the socket's callbacks, which have to live as long as it can call them
+        _callbacks: Vec<Closure<dyn FnMut(Event)>>,
+    }
+
+    impl Connection {
+        /// start connecting to `url` in the background
+        pub fn open(url: &str) -> Self {
+            let inbox = Inbox::default();
+            let socket = match WebSocket::new(url) {
+                Ok(socket) => socket,
+                Err(_) => {
+                    inbox
+                        .lock()
+                        .unwrap()
+                        .push_back(NetEvent::Disconnected(format!("can't connect to {}", url)));
+                    return Self {
+                        inbox,
+                        socket: None,
+                        _callbacks: Vec::new(),
+                    };
+                }
+            };
+            let callback = |handle: fn(Event) -> Option<NetEvent>| {
+                let inbox = Arc::clone(&inbox);
+                Closure::wrap(Box::new(move |event: Event| {
+                    if let Some(event) = handle(event) {
+                        inbox.lock().unwrap().push_back(event);
+                    }
+                }) as Box<dyn FnMut(Event)>)
+            };
+            let on_open = callback(|_| Some(NetEvent::Connected));
+            let on_message = callback(|event| {
+                let text = event.unchecked_into::<MessageEvent>().data().as_string()?;
+                match serde_json::from_str(&text) {
+                    Ok(msg) => Some(NetEvent::Message(msg)),
+                    Err(e) => {
+                        web_sys::console::error_1(
+                            &format!("unreadable message from the server - {}", e).into(),
+                        );
+                        None
+                    }
+                }
+            });
+            // errors are always followed by a close
+            let on_close = callback(|_| {
+                Some(NetEvent::Disconnected(
+                    "the connection was closed".to_string(),
+                ))
+            });
+            socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+            socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+            socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+            Self {
+                inbox,
+                socket: Some(socket),
+                _callbacks: vec![on_open, on_message, on_close],
+            }
+        }
+
+        pub fn poll(&self) -> Option<NetEvent> {
+            self.inbox.lock().unwrap().pop_front()
+        }
+
+        pub fn send(&self, msg: &ClientMsg) {
+            let text = serde_json::to_string(msg).expect("client messages serialize");
+            if let Some(socket) = &self.socket {
+                // a failed send shows up as the socket closing
+                let _ = socket.send_with_str(&text);
+            }
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            // the callbacks go with the connection, so the socket can't call them
+            if let Some(socket) = &self.socket {
+                socket.set_onopen(None);
+                socket.set_onmessage(None);
+                socket.set_onclose(None);
+                let _ = socket.close();
+            }
+        }
+    }
+}
+
+/// the server to play through and the game to join there
+struct NetConfig {
+    url: String,
+    join: Option<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn net_config() -> Option<NetConfig> {
+    let mut args = std::env::args().skip(1);
+    let (mut url, mut join) = (None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--server" => url = args.next(),
+            "--join" => join = args.next(),
+            _ => {}
+        }
+    }
+    Some(NetConfig { url: url?, join })
+}
+
+#[cfg(target_arch = "wasm32")]
+fn net_config() -> Option<NetConfig> {
+    let search = web_sys::window()?.location().search().ok()?;
+    let params = web_sys::UrlSearchParams::new_with_str(&search).ok()?;
+    Some(NetConfig {
+        url: params.get("server")?,
+        join: params.get("join"),
+    })
+}
+
+/// where this side stands with the server
+struct Online {
+    url: String,
+    /// the lobby being played in, or the one to join
+    code: Option<String>,
+    /// what gets the seat back after losing the connection
+    token: Option<String>,
+    /// whether the server has handed over a game to play
+    seated: bool,
+    /// counts down to trying the server again after losing it
+    reconnect: Option<Timer>,
+    status: String,
+}
+
+impl Online {
+    /// what to say to the server to get (back) into the game
+    fn hello(&self, config: &PlayerConfig) -> ClientMsg {
+        match (&self.code, &self.token) {
+            (Some(code), Some(token)) => ClientMsg::Rejoin {
+                code: code.clone(),
+                token: token.clone(),
+            },
+            (Some(code), None) => ClientMsg::Join { code: code.clone() },
+            (None, _) => ClientMsg::Create {
+                team: config.human,
+                time_control: config.time_control,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+struct NetworkStatusText;
+
+/// plays the other team through a game server when one is given
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        let config = match net_config() {
+            Some(config) => config,
+            None => return,
+        };
+        app.insert_non_send_resource(Connection::open(&config.url))
+            .insert_resource(Online {
+                status: format!("connecting to {}", config.url),
+                url: config.url,
+                code: config.join,
+                token: None,
+                seated: false,
+                reconnect: None,
+            })
+            .add_startup_system(setup_network)
+            // before the clicks so a local move is always the last one played
+            // when it's sent
+            .add_system(receive_system.before(piece_click_system))
+            .add_system(
+                send_system
+                    .after(piece_click_system)
+                    .after(promotion_picker_system),
+            )
+            .add_system(reconnect_system)
+            .add_system(network_status_system);
+    }
+}
+
+fn setup_network(mut commands: Commands, mut config: ResMut<PlayerConfig>, font: Res<UiFont>) {
+    config.remote = true;
+    commands
+        .spawn_bundle(
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: font.0.clone(),
+                    font_size: 20.0,
+                    color: Color::rgb(0.15, 0.15, 0.15),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(16.0),
+                    bottom: Val::Px(16.0),
+                    ..default()
+                },
+                ..default()
+            }),
+        )
+        .insert(NetworkStatusText);
+}
+
+/// apply whatever the server sent since the last frame
+#[allow(clippy::too_many_arguments)]
+fn receive_system(
+    mut commands: Commands,
+    connection: NonSend<Connection>,
+    mut online: ResMut<Online>,
+    mut config: ResMut<PlayerConfig>,
+    mut game: ResMut<Game>,
+    mut piece_sprites: ResMut<PieceSprites>,
+    q_piece_transforms: Query<&mut Transform, With<Piece>>,
+    mut q_tiles: Query<(&HexCoord, &mut Transform), Without<Piece>>,
+    mut orientation: ResMut<BoardOrientation>,
+    pieces_atlas: Res<PiecesAtlas>,
+    mut select: ResMut<SelectedHex>,
+    mut last_move: ResMut<LastMove>,
+    mut dragging: ResMut<Dragging>,
+    mut pending: ResMut<PendingPromotion>,
+    mut turn_clock: ResMut<TurnClock>,
+    mut moved: EventWriter<PieceMoved>,
+) {
+    while let Some(event) = connection.poll() {
+        let msg = match event {
+            NetEvent::Connected => {
+                online.reconnect = None;
+                online.status = "connected".to_string();
+                connection.send(&online.hello(&config));
+                continue;
+            }
+            NetEvent::Disconnected(reason) => {
+                online.seated = false;
+                online.reconnect = Some(Timer::new(RECONNECT_DELAY, false));
+                online.status = format!("lost the server ({}) - retrying", reason);
+                continue;
+            }
+            NetEvent::Message(msg) => msg,
+        };
+        let remote = config.human.flip();
+        match msg {
+            ServerMsg::Created { code, token } => {
+                online.status = format!("waiting for an opponent - game code {}", code);
+                online.code = Some(code);
+                online.token = Some(token);
+            }
+            ServerMsg::Seated { token } => online.token = Some(token),
+            // this side only ever asks for a seat
+            ServerMsg::Spectating { .. } => {}
+            ServerMsg::Game(msg @ Message::NewGame { .. }) => {
+                let RemoteGame {
+                    game: new_game,
+                    local,
+                } = match RemoteGame::join(&msg) {
+                    Ok(remote_game) => remote_game,
+                    Err(e) => {
+                        online.status = format!("couldn't start the game - {}", e);
+                        continue;
+                    }
+                };
+                *game = new_game;
+                config.human = local;
+                online.seated = true;
+
+                // the local team plays up the screen
+                let facing = match local {
+                    Team::White => BoardOrientation::White,
+                    Team::Black => BoardOrientation::Black,
+                };
+                if *orientation != facing {
+                    *orientation = facing;
+                    place_tiles(*orientation, &mut q_tiles);
+                }
+                despawn_pieces(&mut commands, &mut piece_sprites);
+                spawn_pieces(
+                    &mut commands,
+                    &pieces_atlas.0,
+                    *orientation,
+                    &game.board,
+                    &mut piece_sprites,
+                );
+                *select = SelectedHex::default();
+                last_move.0 = None;
+                dragging.0 = None;
+                pending.0 = None;
+                turn_clock.0 = Duration::ZERO;
+            }
+            ServerMsg::Game(Message::Move(MoveMsg { mv, ply, checksum })) => {
+                let in_step = online.seated
+                    && game.turn == remote
+                    && ply == net::ply(&game)
+                    && play_move(
+                        &mut commands,
+                        *orientation,
+                        &mut game,
+                        &mut piece_sprites,
+                        &q_piece_transforms,
+                        &mut last_move,
+                        &mut moved,
+                        &mut turn_clock,
+                        mv,
+                    );
+                if !in_step || net::checksum(&game.to_fen()) != checksum {
+                    // the server's game is the real one, so start again from it
+                    online.status = "out of step with the server - resyncing".to_string();
+                    connection.send(&online.hello(&config));
+                }
+            }
+            ServerMsg::Game(msg) => {
+                let applied = match msg {
+                    Message::Resign => game.resign(remote),
+                    Message::DrawOffer => {
+                        online.status = "your opponent offers a draw".to_string();
+                        game.offer_draw(remote)
+                    }
+                    Message::DrawAccept => game.accept_draw(remote),
+                    Message::Chat(text) => {
+                        online.status = format!("{}: {}", remote, text);
+                        Ok(())
+                    }
+                    Message::NewGame { .. } | Message::Move(_) => unreachable!(),
+                };
+                if let Err(e) = applied {
+                    eprintln!("{}", e);
+                }
+            }
+            ServerMsg::Opponent { connected } => {
+                online.status = if connected {
+                    format!("playing {}", remote)
+                } else {
+                    "your opponent is away".to_string()
+                };
+            }
+            ServerMsg::GameOver(result) => online.status = result.to_string(),
+            ServerMsg::Error(reason) => online.status = format!("server: {}", reason),
+        }
+    }
+}
+
+/// pass the local team's moves on to the server
+fn send_system(
+    connection: NonSend<Connection>,
+    online: Res<Online>,
+    config: Res<PlayerConfig>,
+    game: Res<Game>,
+    mut moved: EventReader<PieceMoved>,
+) {
+    for event in moved.iter() {
+        // moves made while offline are undone by the game the server sends back
+        if !online.seated || event.team != config.human {
+            continue;
+        }
+        connection.send(&ClientMsg::Game(Message::Move(MoveMsg {
+            mv: event.mv,
+            ply: net::ply(&game) - 1,
+            checksum: net::checksum(&game.to_fen()),
+        })));
+    }
+}
+
+fn reconnect_system(
+    time: Res<Time>,
+    mut connection: NonSendMut<Connection>,
+    mut online: ResMut<Online>,
+) {
+    let ready = match online.reconnect.as_mut() {
+        Some(timer) => timer.tick(time.delta()).finished(),
+        None => return,
+    };
+    if ready {
+        online.reconnect = None;
+        online.status = format!("connecting to {}", online.url);
+        *connection = Connection::open(&online.url);
+    }
+}
+
+fn network_status_system(
+    online: Res<Online>,
+    mut q_text: Query<&mut Text, With<NetworkStatusText>>,
+) {
+    if !online.is_changed() {
+        return;
+    }
+    for mut text in q_text.iter_mut() {
+        text.sections[0].value = online.status.clone();
+    }
+}
@@ -1,8 +1,16 @@
 use crate::{
     coord::Coord,
+    moves::Move,
     piece::{Name, Piece, Team},
+    tables, zobrist,
+};
+use std::{
+    collections::HashSet,
+    error::Error,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
 };
-use std::{collections::HashMap, error::Error, fmt};
 
 type Hex = (Coord, Piece);
 
@@ -27,8 +35,72 @@ const STARTING_PIECES: &[(Coord, Piece)] = &[
     (Coord::new(-4, -1), Piece::new(Name::Pawn, Team::White)),
 ];
 
+const MCCOOEY_PIECES: &[(Coord, Piece)] = &[
+    (Coord::new(0, -5), Piece::new(Name::Bishop, Team::White)),
+    (Coord::new(0, -4), Piece::new(Name::Bishop, Team::White)),
+    (Coord::new(0, -3), Piece::new(Name::Bishop, Team::White)),
+    (Coord::new(1, -5), Piece::new(Name::King, Team::White)),
+    (Coord::new(-1, -4), Piece::new(Name::Queen, Team::White)),
+    (Coord::new(-1, -3), Piece::new(Name::Knight, Team::White)),
+    (Coord::new(1, -4), Piece::new(Name::Knight, Team::White)),
+    (Coord::new(-2, -3), Piece::new(Name::Rook, Team::White)),
+    (Coord::new(2, -5), Piece::new(Name::Rook, Team::White)),
+    (Coord::new(3, -5), Piece::new(Name::Pawn, Team::White)),
+    (Coord::new(2, -4), Piece::new(Name::Pawn, Team::White)),
+    (Coord::new(1, -3), Piece::new(Name::Pawn, Team::White)),
+    (Coord::new(0, -2), Piece::new(Name::Pawn, Team::White)),
+    (Coord::new(-1, -2), Piece::new(Name::Pawn, Team::White)),
+    (Coord::new(-2, -2), Piece::new(Name::Pawn, Team::White)),
+    (Coord::new(-3, -2), Piece::new(Name::Pawn, Team::White)),
+];
+
+/// the starting layouts, which are drawn for the default board and slid back
+/// against the edge of bigger ones
+///
+/// shafran's variant isn't supported. it's played on an irregular 70 hex
+/// board, and every board here is a regular hexagon - the dense cells, the
+/// notation, fen and the frontends would all need a cell mask for its shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Glinski,
+    McCooey,
+}
+
+impl Variant {
+    /// the white half of the layout - black is its reflection
+    fn starting_pieces(&self) -> &'static [Hex] {
+        match self {
+            Variant::Glinski => STARTING_PIECES,
+            Variant::McCooey => MCCOOEY_PIECES,
+        }
+    }
+
+    /// the smallest board this layout fits on with the middle row left
+    /// between the two teams
+    pub fn min_radius(&self) -> i32 {
+        let front = self.starting_pieces().iter().map(|(c, _piece)| c.r).max();
+        HexBoard::DEFAULT_RADIUS + 1 + front.unwrap_or(-1)
+    }
+
+    /// both teams' pieces on a board of radius `n`
+    fn layout(&self, n: i32) -> impl Iterator<Item = Hex> {
+        let back = Coord::new(0, HexBoard::DEFAULT_RADIUS - n);
+        let white = self
+            .starting_pieces()
+            .iter()
+            .map(move |&(c, piece)| (c + back, piece));
+        white.clone().chain(reflect_team(white))
+    }
+}
+
+/// every hex on a board with `n` hexes between the center and each edge,
+/// going through them by `q` and then `r`
+pub fn board_hexes(n: i32) -> impl Iterator<Item = Coord> {
+    Coord::ZERO.range(n)
+}
+
 fn reflect_team<'a>(pieces: impl Iterator<Item = Hex> + 'a) -> impl Iterator<Item = Hex> + 'a {
-    pieces.map(|(p, piece)| (p.reflect_q(), piece.clone().flip_team()))
+    pieces.map(|(p, piece)| (p.reflect_q(), piece.flip_team()))
 }
 
 #[derive(Debug, thiserror::Error, Clone, Copy, PartialEq, Eq)]
@@ -39,6 +111,12 @@ pub enum MoveErrorType {
     InvalidMove(Piece),
     #[error("{0} collided with on path")]
     CollisionOnPath(Piece),
+    #[error("{0} does not get the king out of check")]
+    InCheck(Piece),
+    #[error("{0} would put its own king in check")]
+    MovesIntoCheck(Piece),
+    #[error("{0} cannot capture a king")]
+    CannotCaptureKing(Piece),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -70,143 +148,630 @@ pub enum GetError {
     NoPiece(Coord),
 }
 
+#[derive(Debug, thiserror::Error, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    #[error("{0} is out of bounds")]
+    OutOfBounds(Coord),
+    #[error("more than one piece placed at {0}")]
+    Occupied(Coord),
+    #[error("{0} has more than one king")]
+    MultipleKings(Team),
+    #[error("a board can't have a radius of {0}")]
+    InvalidRadius(i32),
+    #[error("the starting layout doesn't fit on a board of radius {0}")]
+    LayoutDoesNotFit(i32),
+}
+
+/// the pieces stored densely with a slot for every hex on the board, going
+/// through the hexes by `q` and then `r`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cells {
+    radius: i32,
+    slots: Box<[Option<Piece>]>,
+    /// where each team's king is, kept up to date by `insert` and `remove`
+    kings: [Option<Coord>; 2],
+}
+
+impl Cells {
+    fn new(radius: i32) -> Cells {
+        let hexes = 3 * radius * (radius + 1) + 1;
+        Cells {
+            radius,
+            slots: vec![None; hexes as usize].into_boxed_slice(),
+            kings: [None; 2],
+        }
+    }
+
+    /// every hex on the board, in slot order
+    fn coords(&self) -> impl Iterator<Item = Coord> {
+        board_hexes(self.radius)
+    }
+
+    fn index(&self, c: Coord) -> Option<usize> {
+        let n = self.radius;
+        if !c.in_bounds(n) {
+            return None;
+        }
+        // the columns left of the center grow by one hex each and the ones
+        // right of it shrink by one
+        let columns = c.q + n;
+        let before = if c.q <= 0 {
+            columns * (n + 1) + columns * (columns - 1) / 2
+        } else {
+            let center = n * (n + 1) + n * (n - 1) / 2;
+            center + c.q * (2 * n + 1) - c.q * (c.q - 1) / 2
+        };
+        Some((before + c.r - (-n).max(-c.q - n)) as usize)
+    }
+
+    fn get(&self, c: Coord) -> Option<&Piece> {
+        self.index(c).and_then(|i| self.slots[i].as_ref())
+    }
+
+    fn contains(&self, c: Coord) -> bool {
+        self.get(c).is_some()
+    }
+
+    /// panics if `c` is off the board
+    fn insert(&mut self, c: Coord, piece: Piece) -> Option<Piece> {
+        let i = self
+            .index(c)
+            .unwrap_or_else(|| panic!("{} is off the board", c));
+        let old = self.slots[i].replace(piece);
+        self.forget_king(c, old);
+        if piece.name == Name::King {
+            self.kings[piece.team as usize] = Some(c);
+        }
+        old
+    }
+
+    fn remove(&mut self, c: Coord) -> Option<Piece> {
+        let old = self.index(c).and_then(|i| self.slots[i].take());
+        self.forget_king(c, old);
+        old
+    }
+
+    /// look for another king if `old` was the one being tracked on `c`
+    fn forget_king(&mut self, c: Coord, old: Option<Piece>) {
+        if let Some(old) = old.filter(|p| p.name == Name::King) {
+            let team = old.team as usize;
+            if self.kings[team] == Some(c) {
+                let other = self
+                    .iter()
+                    .find(|(_c, p)| p.name == Name::King && p.team == old.team)
+                    .map(|(c, _p)| c);
+                self.kings[team] = other;
+            }
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (Coord, Piece)> + '_ {
+        self.coords()
+            .zip(self.slots.iter())
+            .filter_map(|(c, slot)| slot.map(|p| (c, p)))
+    }
+}
+
+/// identifies a position for a transposition table, see
+/// `HexBoard::transposition_key`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BoardKey(pub u64);
+
+/// what `HexBoard::make` changed, for `HexBoard::unmake` to put back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Undo {
+    mv: Move,
+    captured: Option<Piece>,
+    /// where the captured piece stood, which is only not `mv.to` en passant
+    captured_at: Coord,
+    en_passant: Option<Coord>,
+    from_unmoved: bool,
+    to_unmoved: bool,
+    /// whether a pawn became something else on reaching the last rank
+    promoted: bool,
+    checkers: [Vec<Coord>; 2],
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "BoardData", try_from = "BoardData")
+)]
 pub struct HexBoard {
-    pieces: HashMap<Coord, Piece>,
+    pieces: Cells,
     checkers: [Vec<Coord>; 2],
+    /// pawns that haven't moved yet and may still double step
+    unmoved_pawns: HashSet<Coord>,
+    /// the hex a pawn skipped over with a double step on the last move, which
+    /// an enemy pawn may capture onto as if it had only stepped once
+    en_passant: Option<Coord>,
 }
 
 impl HexBoard {
-    const N: i32 = 5;
+    /// the radius of glinski's board
+    pub const DEFAULT_RADIUS: i32 = 5;
 
     pub fn new() -> HexBoard {
+        Self::with_radius(Self::DEFAULT_RADIUS)
+    }
+
+    /// create an empty board with `n` hexes between the center and each edge
+    pub fn with_radius(n: i32) -> HexBoard {
         HexBoard {
-            pieces: HashMap::new(),
+            pieces: Cells::new(n),
             checkers: Default::default(),
+            unmoved_pawns: HashSet::new(),
+            en_passant: None,
         }
     }
 
     /// create a new board initialized with both teams from glinski's chess
+    ///
+    /// the layout only makes sense on the default radius
     pub fn new_initialize() -> HexBoard {
+        Self::new_variant(Variant::Glinski)
+    }
+
+    /// create a new board initialized with both teams in the layout of `variant`
+    pub fn new_variant(variant: Variant) -> HexBoard {
+        Self::new_variant_with_radius(variant, Self::DEFAULT_RADIUS)
+            .expect("every variant fits the default board")
+    }
+
+    /// create a board with `n` hexes between the center and each edge, set up
+    /// with `variant`'s layout against its edges
+    ///
+    /// fails if the board is smaller than `variant.min_radius()`
+    pub fn new_variant_with_radius(variant: Variant, n: i32) -> Result<HexBoard, PlacementError> {
+        if n < variant.min_radius() {
+            return Err(PlacementError::LayoutDoesNotFit(n));
+        }
+        let mut b = Self::with_radius(n);
+        for (c, piece) in variant.layout(n) {
+            b.pieces.insert(c, piece);
+        }
+        b.unmoved_pawns = b
+            .pieces
+            .iter()
+            .filter(|(_c, p)| p.name == Name::Pawn)
+            .map(|(c, _p)| c)
+            .collect();
+
+        Ok(b)
+    }
+
+    /// create a board on the default radius from a list of pieces, checking that
+    /// every piece is on the board, no two pieces share a hex, and neither
+    /// team has more than one king
+    ///
+    /// every pawn is treated as not having moved yet
+    pub fn from_pieces(
+        iter: impl IntoIterator<Item = (Coord, Piece)>,
+    ) -> Result<HexBoard, PlacementError> {
         let mut b = Self::new();
+        let mut kings = [0; 2];
 
-        b.pieces.extend(STARTING_PIECES.iter().cloned());
-        b.pieces
-            .extend(reflect_team(STARTING_PIECES.iter().cloned()));
+        for (c, piece) in iter {
+            if !b.in_bounds(c) {
+                return Err(PlacementError::OutOfBounds(c));
+            }
+            if b.pieces.insert(c, piece).is_some() {
+                return Err(PlacementError::Occupied(c));
+            }
+            if piece.name == Name::Pawn {
+                b.unmoved_pawns.insert(c);
+            }
+            if piece.name == Name::King {
+                kings[piece.team as usize] += 1;
+                if kings[piece.team as usize] > 1 {
+                    return Err(PlacementError::MultipleKings(piece.team));
+                }
+            }
+        }
 
-        b
+        b.update_checkers();
+        Ok(b)
     }
 
-    #[allow(dead_code)]
+    /// put `piece` on `c`, replacing anything already there
+    ///
+    /// a placed pawn is treated as not having moved yet. panics if `c` is off
+    /// the board
     pub fn place(&mut self, c: Coord, piece: Piece) {
         self.pieces.insert(c, piece);
+        if piece.name == Name::Pawn {
+            self.unmoved_pawns.insert(c);
+        } else {
+            self.unmoved_pawns.remove(&c);
+        }
+        self.update_checkers();
     }
 
-    pub fn get(&self, c: Coord) -> Result<&Piece, GetError> {
-        self.pieces.get(&c).ok_or_else(|| GetError::NoPiece(c))
+    /// treat only the pawns standing where glinski's layout starts them on a
+    /// board of this radius as not having moved yet, for positions that don't
+    /// record it
+    pub(crate) fn reset_unmoved_pawns(&mut self) {
+        let starts: Vec<Hex> = Variant::Glinski
+            .layout(self.radius())
+            .filter(|(_c, piece)| piece.name == Name::Pawn)
+            .collect();
+        self.unmoved_pawns = self
+            .iter()
+            .filter(|&(c, piece)| starts.contains(&(c, piece)))
+            .map(|(c, _piece)| c)
+            .collect();
     }
 
-    fn between(f: Coord, t: Coord) -> impl Iterator<Item = Coord> {
-        let v = t - f;
-        // movement along an axis
-        let (axial_len, uv) = if v.is_axis() {
-            let axial_len = v.length();
-            let uv = v / axial_len;
-            (axial_len, uv)
-        } else {
-            // bishops only collide with every other hex - maybe need to find
-            // a better way to structure this
-            let axial_len = ((v.norm_squared() / 3) as f32).sqrt() as i32;
-            let uv = v / axial_len;
-            (axial_len, uv)
+    /// take whatever is on `c` off the board
+    pub fn remove(&mut self, c: Coord) -> Option<Piece> {
+        let piece = self.pieces.remove(c);
+        self.unmoved_pawns.remove(&c);
+        self.update_checkers();
+        piece
+    }
+
+    /// whether nothing is standing on `c`
+    pub fn is_empty(&self, c: Coord) -> bool {
+        !self.pieces.contains(c)
+    }
+
+    pub fn radius(&self) -> i32 {
+        self.pieces.radius
+    }
+
+    /// the number of pieces `team` has on the board
+    pub fn piece_count(&self, team: Team) -> usize {
+        self.pieces.iter().filter(|(_c, p)| p.team == team).count()
+    }
+
+    /// the number of `name` pieces `team` has on the board
+    pub fn count(&self, name: Name, team: Team) -> usize {
+        self.pieces
+            .iter()
+            .filter(|(_c, p)| *p == Piece::new(name, team))
+            .count()
+    }
+
+    /// white's material minus black's, in pawns
+    pub fn material_balance(&self) -> i32 {
+        self.pieces
+            .iter()
+            .map(|(_c, p)| match p.team {
+                Team::White => p.name.value(),
+                Team::Black => -p.name.value(),
+            })
+            .sum()
+    }
+
+    /// whether neither team has enough left to ever checkmate - two bare kings,
+    /// or a single knight or bishop alongside them
+    pub fn insufficient_material(&self) -> bool {
+        let mut others = self.pieces.iter().filter(|(_c, p)| p.name != Name::King);
+        match (others.next(), others.next()) {
+            (None, _) => true,
+            (Some((_c, p)), None) => matches!(p.name, Name::Knight | Name::Bishop),
+            _ => false,
+        }
+    }
+
+    /// every hex on the board, whether or not anything is on it
+    pub fn hexes(&self) -> impl Iterator<Item = Coord> {
+        self.pieces.coords()
+    }
+
+    /// where the king of `team` is, or `None` if it has no king on the board
+    pub fn king_position(&self, team: Team) -> Option<Coord> {
+        self.pieces.kings[team as usize]
+    }
+
+    /// a zobrist hash of just the pieces on the board
+    ///
+    /// this leaves out whose turn it is, which pawns may double step and the
+    /// en passant hex, so boards with the same pieces can differ in their
+    /// moves. `transposition_key` hashes the whole position
+    pub fn pieces_zobrist(&self) -> u64 {
+        self.pieces
+            .iter()
+            .fold(0, |hash, (c, piece)| hash ^ zobrist::piece(c, piece))
+    }
+
+    /// a zobrist hash of the position with `turn` to move, covering the
+    /// pieces, which pawns may still double step and the en passant hex, so
+    /// positions with the same key have the same moves available
+    ///
+    /// it leaves out the halfmove clock so transpositions reached by different
+    /// move orders match
+    pub fn transposition_key(&self, turn: Team) -> BoardKey {
+        let mut key = self
+            .unmoved_pawns
+            .iter()
+            .fold(self.pieces_zobrist(), |hash, &c| {
+                hash ^ zobrist::unmoved_pawn(c)
+            });
+        if let Some(c) = self.en_passant {
+            key ^= zobrist::en_passant(c);
+        }
+        if turn == Team::Black {
+            key ^= zobrist::BLACK_TO_MOVE;
+        }
+        BoardKey(key)
+    }
+
+    /// render the board with letters rather than chess glyphs, the same as
+    /// formatting it with `{:#}`
+    pub fn render_ascii(&self) -> String {
+        format!("{:#}", self)
+    }
+
+    /// render the board with `q` labeled along the top and bottom and `r` down
+    /// the left
+    ///
+    /// hexes with the same `q` run diagonally down to the right, so each `q` is
+    /// labeled where its line meets either the top or the bottom row
+    pub fn render_labeled(&self) -> String {
+        let n = self.radius();
+        let margin = (-n).to_string().len() + 1;
+        // label the hexes `qs` in row `r`, right aligned over each hex
+        let q_labels = |r: i32, qs: RangeInclusive<i32>| {
+            let mut line = vec![' '; margin + (4 * n + 5) as usize];
+            for q in qs {
+                let label = q.to_string();
+                let end = margin + (2 * q + r + 2 * n + 2) as usize;
+                for (i, c) in label.chars().enumerate() {
+                    line[end + 1 - label.len() + i] = c;
+                }
+            }
+            line.into_iter().collect::<String>().trim_end().to_string()
         };
 
-        let mut n = 1;
-        std::iter::from_fn(move || {
-            let cell = if n < axial_len {
-                Some(f + uv * n)
+        let grid = self.to_string();
+        let lines: Vec<_> = grid.lines().collect();
+        let mut out = q_labels(-n, 0..=n);
+        out.push('\n');
+        for (i, line) in lines.iter().enumerate() {
+            if i == 0 || i == lines.len() - 1 {
+                out += &format!("{:1$}{2}\n", "", margin, line);
             } else {
-                None
-            };
+                let r = i as i32 - 1 - n;
+                out += &format!("{:>1$} {2}\n", r, margin - 1, line);
+            }
+        }
+        out += &q_labels(n, -n..=0);
+        out
+    }
+
+    fn in_bounds(&self, c: Coord) -> bool {
+        c.in_bounds(self.radius())
+    }
 
-            n += 1;
-            cell
-        })
+    /// iterate over every occupied hex in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (Coord, Piece)> + '_ {
+        self.pieces.iter()
+    }
+
+    pub fn get(&self, c: Coord) -> Result<&Piece, GetError> {
+        self.pieces.get(c).ok_or(GetError::NoPiece(c))
+    }
+
+    /// the hex skipped over by a pawn's double step on the last move, which
+    /// can be captured onto en passant
+    pub fn en_passant(&self) -> Option<Coord> {
+        self.en_passant
+    }
+
+    /// the hex of the piece that moving from `from` to `to` would capture,
+    /// which is `to` unless the move takes a pawn en passant
+    ///
+    /// this doesn't check that the move is legal
+    pub fn capture_square(&self, from: Coord, to: Coord) -> Option<Coord> {
+        if self.pieces.contains(to) {
+            return Some(to);
+        }
+        self.en_passant_victim(self.pieces.get(from)?, to)
+    }
+
+    /// mark `c` as just skipped over by the double step of a pawn of `team`,
+    /// returning false and leaving the board alone if no such step fits the
+    /// pieces on the board
+    pub(crate) fn set_en_passant(&mut self, c: Coord, team: Team) -> bool {
+        let forward = Self::pawn_forward(team);
+        let possible = self.in_bounds(c - forward)
+            && self.is_empty(c - forward)
+            && self.in_bounds(c)
+            && self.is_empty(c)
+            && matches!(self.pieces.get(c + forward), Some(p) if p.name == Name::Pawn && p.team == team);
+        if possible {
+            self.en_passant = Some(c);
+        }
+        possible
+    }
+
+    /// the direction pawns of `team` move in
+    pub(crate) fn pawn_forward(team: Team) -> Coord {
+        match team {
+            Team::White => Coord::new(0, 1),
+            Team::Black => Coord::new(0, -1),
+        }
+    }
+
+    /// whether a pawn of `team` on `c` is at the far edge with no hex ahead
+    /// of it, where it would promote
+    pub fn is_last_rank(&self, c: Coord, team: Team) -> bool {
+        self.in_bounds(c) && !self.in_bounds(c + Self::pawn_forward(team))
+    }
+
+    /// the enemy pawn `piece` would take en passant by moving to `to`
+    fn en_passant_victim(&self, piece: &Piece, to: Coord) -> Option<Coord> {
+        if piece.name != Name::Pawn || self.en_passant != Some(to) {
+            return None;
+        }
+        let victim = to - Self::pawn_forward(piece.team);
+        matches!(self.pieces.get(victim), Some(p) if p.name == Name::Pawn && p.team != piece.team)
+            .then_some(victim)
+    }
+
+    /// the hexes whose centers lie strictly between `f` and `t`, which for
+    /// a knight's jump is none of them
+    fn between(f: Coord, t: Coord) -> impl Iterator<Item = Coord> {
+        let (step, steps) = f.delta_to(t).split();
+        (1..steps).map(move |n| f + step * n)
     }
 
     fn collides(&self, f: Coord, t: Coord) -> bool {
         // never inclusive
         for cell in Self::between(f, t) {
-            if self.pieces.contains_key(&cell) {
+            if self.pieces.contains(cell) {
                 return true;
             }
         }
         false
     }
 
-    fn update_checkers(&mut self) {
-        let kings = self.pieces.iter().filter(|(_c, p)| p.name == Name::King);
-        for (&pos, king) in kings {
-            let mut checkers = Vec::new();
-            let enemy_coords = self
-                .pieces
-                .iter()
-                .filter(|(_c, p)| p.team == king.team.flip());
-            for (&enemy_pos, enemy) in enemy_coords {
-                if self.unchecked_can_move(enemy, enemy_pos, pos).is_ok() {
-                    checkers.push(pos);
-                }
+    /// the coords of every piece of `team` that could capture on `coord`
+    fn attackers(&self, coord: Coord, team: Team) -> impl Iterator<Item = Coord> + '_ {
+        // knights and kings move the same way in both directions, so look out
+        // from `coord` for them rather than checking every piece
+        let tables = self.has_tables();
+        let jumpers = [Name::Knight, Name::King]
+            .into_iter()
+            .filter(move |_| tables)
+            .flat_map(move |name| {
+                tables::targets(name, coord).iter().copied().filter(
+                    move |&c| matches!(self.pieces.get(c), Some(p) if p.team == team && p.name == name),
+                )
+            });
+        let others = self
+            .pieces
+            .iter()
+            .filter(move |&(c, p)| {
+                p.team == team
+                    && c != coord
+                    && !(tables && matches!(p.name, Name::Knight | Name::King))
+                    && p.verify_move(c, coord).is_some_and(|m| m.capture)
+                    && !self.collides(c, coord)
+            })
+            .map(|(c, _p)| c);
+        jumpers.chain(others)
+    }
+
+    /// the coords of every piece of `team` that could capture on `coord`,
+    /// whatever is standing there, in sorted order
+    pub fn attackers_of(&self, coord: Coord, team: Team) -> Vec<Coord> {
+        let mut attackers: Vec<Coord> = self.attackers(coord, team).collect();
+        attackers.sort_unstable();
+        attackers
+    }
+
+    /// every hex a piece of `team` could capture on, whatever is standing there
+    ///
+    /// this goes out from each piece of `team` once, so is quicker than asking
+    /// `attacked_by` about every hex
+    pub fn attacked_hexes(&self, team: Team) -> HashSet<Coord> {
+        let tables = self.has_tables();
+        let mut attacked = HashSet::new();
+        for (from, piece) in self.pieces.iter().filter(|(_c, p)| p.team == team) {
+            if tables && matches!(piece.name, Name::Knight | Name::King) {
+                attacked.extend(tables::targets(piece.name, from).iter().copied());
+            } else {
+                attacked.extend(self.hexes().filter(|&to| {
+                    to != from
+                        && piece.verify_move(from, to).is_some_and(|m| m.capture)
+                        && !self.collides(from, to)
+                }));
             }
-            self.checkers[king.team as usize] = checkers;
         }
+        attacked
     }
 
-    const ADJACENTS: &[Coord] = &[
-        Coord::new(1, 0),
-        Coord::new(1, -1),
-        Coord::new(0, -1),
-        Coord::new(-1, 0),
-        Coord::new(-1, 1),
-        Coord::new(0, 1),
-    ];
+    /// whether the precomputed knight and king tables fit this board
+    fn has_tables(&self) -> bool {
+        self.radius() == Self::DEFAULT_RADIUS
+    }
 
-    pub fn is_checkmated(&self, team: Team) -> bool {
-        let checkers = &self.checkers[team as usize];
-        if checkers.is_empty() {
-            return false;
+    /// whether any piece of `team` attacks `coord`, whatever is standing there
+    ///
+    /// pawns only attack their capture diagonals, not the hex in front of them
+    pub fn attacked_by(&self, coord: Coord, team: Team) -> bool {
+        self.attackers(coord, team).next().is_some()
+    }
+
+    fn update_checkers(&mut self) {
+        let mut checkers: [Vec<Coord>; 2] = Default::default();
+        for team in [Team::White, Team::Black] {
+            if let Some(pos) = self.king_position(team) {
+                checkers[team as usize] = self.attackers(pos, team.flip()).collect();
+            }
         }
+        self.checkers = checkers;
+    }
 
-        let (&coord, king) = self
-            .pieces
-            .iter()
-            .find(|(_c, p)| p.name == Name::King && p.team == team)
-            .unwrap();
-
-        // can the king move out of check?
-        let mut projected = self.clone();
-        for &adjacent in Self::ADJACENTS {
-            let target = coord + adjacent;
-            if projected.unchecked_can_move(king, coord, target).is_ok() {
-                projected.teleport(coord, target);
-                projected.update_checkers();
-                if projected.checkers[team as usize].is_empty() {
-                    return false;
+    pub(crate) const ADJACENTS: &[Coord] = &Coord::ADJACENTS;
+
+    pub(crate) const DIAGONALS: &[Coord] = &Coord::DIAGONALS;
+
+    /// the pieces of `team` that can't leave the line between their king and
+    /// an enemy rook, bishop or queen, as `(pinned, pinner)` pairs
+    pub fn pinned_pieces(&self, team: Team) -> Vec<(Coord, Coord)> {
+        let king = match self.king_position(team) {
+            Some(king) => king,
+            None => return Vec::new(),
+        };
+        let mut pinned = Vec::new();
+        for &step in Self::ADJACENTS.iter().chain(Self::DIAGONALS) {
+            let mut ray = (1..)
+                .map(|n| king + step * n)
+                .take_while(|&c| self.in_bounds(c))
+                .filter_map(|c| self.pieces.get(c).map(|p| (c, p)));
+            if let (Some((own, p)), Some((enemy, e))) = (ray.next(), ray.next()) {
+                if p.team == team
+                    && e.team != team
+                    && matches!(e.name, Name::Rook | Name::Bishop | Name::Queen)
+                    && e.verify_move(enemy, king).is_some()
+                {
+                    pinned.push((own, enemy));
                 }
             }
         }
+        pinned
+    }
+
+    /// whether the king of `team` is currently attacked
+    ///
+    /// the checkers are recalculated by every method that changes the board, so
+    /// this is always up to date
+    pub fn is_in_check(&self, team: Team) -> bool {
+        !self.checkers(team).is_empty()
+    }
+
+    /// the positions of the enemy pieces giving check to `team`
+    pub fn checkers(&self, team: Team) -> &[Coord] {
+        &self.checkers[team as usize]
+    }
 
-        // // can another piece block check?
-        // for &checker in checkers {
-        //     for (&e_coord, enemy) in self.pieces.iter() {
-        //         for between in Self::between(coord, checker) {
-        //             if self.unchecked_can_move(enemy, e_coord, between).is_ok() {
-        //                 return false;
-        //             }
-        //         }
-        //     }
-        // }
+    /// whether `team` is in check with no legal move, counting moves that
+    /// capture or block the checker as well as ones by the king
+    pub fn is_checkmated(&self, team: Team) -> bool {
+        self.is_in_check(team) && !self.has_legal_move(team)
+    }
 
-        true
+    /// whether `team` isn't in check but has no legal move
+    pub fn is_stalemated(&self, team: Team) -> bool {
+        !self.is_in_check(team) && !self.has_legal_move(team)
+    }
+
+    /// like `!all_legal_moves(team).is_empty()` but stops at the first move
+    fn has_legal_move(&self, team: Team) -> bool {
+        // the king is the most likely piece to have a move, so try it first
+        let king = self.king_position(team);
+        king.into_iter()
+            .chain(
+                self.pieces
+                    .iter()
+                    .filter(|&(c, p)| p.team == team && Some(c) != king)
+                    .map(|(c, _p)| c),
+            )
+            .any(|from| !self.legal_moves(from).is_empty())
     }
 
     pub fn can_move(&self, from: Coord, to: Coord) -> Result<(), MoveError> {
@@ -216,28 +781,134 @@ impl HexBoard {
             to,
         })?;
 
-        if self.checkers[piece.team as usize].is_empty() {
-            self.unchecked_can_move(piece, from, to)
+        self.unchecked_can_move(piece, from, to)?;
+
+        // is our king safe after the move?
+        if !self.king_attacked_after(piece.team, from, to) {
+            Ok(())
         } else {
-            // are we out of check after the move?
-            let mut projected = self.clone();
-            projected.teleport(from, to);
-            projected.update_checkers();
-            if projected.checkers[piece.team as usize].is_empty() {
-                Ok(())
+            let err_type = if self.checkers[piece.team as usize].is_empty() {
+                MoveErrorType::MovesIntoCheck(*piece)
             } else {
-                Err(MoveError {
-                    err_type: MoveErrorType::InvalidMove(*piece),
-                    from,
-                    to,
-                })
-            }
+                MoveErrorType::InCheck(*piece)
+            };
+            Err(MoveError { err_type, from, to })
         }
     }
 
+    /// whether the king of `team` would be attacked once the piece on `from`
+    /// moves to `to`, worked out without changing the board
+    fn king_attacked_after(&self, team: Team, from: Coord, to: Coord) -> bool {
+        // a pawn taken en passant isn't on `to`, and can open up a line
+        let captured = self.capture_square(from, to);
+        let occupied =
+            |c: Coord| c == to || (c != from && Some(c) != captured && self.pieces.contains(c));
+        let king = match self.pieces.get(from) {
+            Some(p) if p.name == Name::King => to,
+            _ => match self.king_position(team) {
+                Some(c) => c,
+                None => return false,
+            },
+        };
+        self.pieces
+            .iter()
+            // anything on `to` has just been captured
+            .filter(|&(c, p)| p.team != team && c != to && Some(c) != captured)
+            .any(|(c, p)| {
+                p.verify_move(c, king).is_some_and(|m| m.capture)
+                    && !Self::between(c, king).any(occupied)
+            })
+    }
+
+    /// play a move without checking it is legal, keeping what is needed to
+    /// take it back with `unmake`
+    pub fn make(&mut self, mv: Move) -> Undo {
+        let piece = self.pieces.remove(mv.from).unwrap();
+        let captured_at = self.en_passant_victim(&piece, mv.to).unwrap_or(mv.to);
+        let captured = self.pieces.remove(captured_at);
+        let promoted = piece.name == Name::Pawn && self.is_last_rank(mv.to, piece.team);
+        let landed = if promoted {
+            Piece::new(mv.promotion.unwrap_or(Name::Queen), piece.team)
+        } else {
+            piece
+        };
+        self.pieces.insert(mv.to, landed);
+        // a double step leaves the hex it skipped open to capture for a move
+        let double_step = piece.name == Name::Pawn && (mv.to - mv.from).length() == 2;
+        let en_passant = double_step.then(|| mv.from + Self::pawn_forward(piece.team));
+        let undo = Undo {
+            mv,
+            captured,
+            captured_at,
+            en_passant: std::mem::replace(&mut self.en_passant, en_passant),
+            from_unmoved: self.unmoved_pawns.remove(&mv.from),
+            to_unmoved: self.unmoved_pawns.remove(&captured_at),
+            promoted,
+            checkers: std::mem::take(&mut self.checkers),
+        };
+        self.update_checkers();
+        undo
+    }
+
+    /// take back a move played by `make`, which must be the last one made
+    pub fn unmake(&mut self, undo: Undo) {
+        let mut piece = self.pieces.remove(undo.mv.to).unwrap();
+        if undo.promoted {
+            piece.name = Name::Pawn;
+        }
+        self.pieces.insert(undo.mv.from, piece);
+        if let Some(captured) = undo.captured {
+            self.pieces.insert(undo.captured_at, captured);
+        }
+        if undo.from_unmoved {
+            self.unmoved_pawns.insert(undo.mv.from);
+        }
+        if undo.to_unmoved {
+            self.unmoved_pawns.insert(undo.captured_at);
+        }
+        self.en_passant = undo.en_passant;
+        self.checkers = undo.checkers;
+    }
+
+    /// every hex the piece on `from` can legally move to in sorted order, which
+    /// is empty if there is no piece there
+    pub fn legal_moves(&self, from: Coord) -> Vec<Coord> {
+        let piece = match self.get(from) {
+            Ok(piece) => piece,
+            Err(_) => return Vec::new(),
+        };
+        let mut moves: Vec<Coord> =
+            if self.has_tables() && matches!(piece.name, Name::Knight | Name::King) {
+                tables::targets(piece.name, from)
+                    .iter()
+                    .copied()
+                    .filter(|&to| self.can_move(from, to).is_ok())
+                    .collect()
+            } else {
+                self.pieces
+                    .coords()
+                    // rule out the hexes the piece can't reach before the expensive check
+                    .filter(|&to| piece.verify_move(from, to).is_some())
+                    .filter(|&to| self.can_move(from, to).is_ok())
+                    .collect()
+            };
+        moves.sort_unstable();
+        moves
+    }
+
+    /// every legal move for `team` as `(from, to)` pairs, sorted by `from` and
+    /// then `to`
+    pub fn all_legal_moves(&self, team: Team) -> Vec<(Coord, Coord)> {
+        self.pieces
+            .iter()
+            .filter(|(_c, p)| p.team == team)
+            .flat_map(|(from, _p)| self.legal_moves(from).into_iter().map(move |to| (from, to)))
+            .collect()
+    }
+
     fn unchecked_can_move(&self, piece: &Piece, from: Coord, to: Coord) -> Result<(), MoveError> {
         // is the destination in bounds?
-        if to.q.abs() > Self::N || to.r.abs() > Self::N || to.s().abs() > Self::N {
+        if !self.in_bounds(to) {
             return Err(MoveError {
                 err_type: MoveErrorType::InvalidMove(*piece),
                 from,
@@ -246,7 +917,7 @@ impl HexBoard {
         }
 
         // can the piece do that? can it capture or just move or both?
-        let possible = piece.verify_move(from, to).ok_or_else(|| MoveError {
+        let possible = piece.verify_move(from, to).ok_or(MoveError {
             err_type: MoveErrorType::InvalidMove(*piece),
             from,
             to,
@@ -254,11 +925,25 @@ impl HexBoard {
 
         // if it can't capture and there is a piece there if can't work
         // if it can't move normally and there isn't a piece there then it can't work
-        if (!possible.capture && self.pieces.contains_key(&to))
+        if (!possible.capture && self.pieces.contains(to))
             || (possible.capture
-                && self.pieces.contains_key(&to)
-                && self.pieces.get(&to).unwrap().team == piece.team)
-            || (!possible._move && !self.pieces.contains_key(&to))
+                && self.pieces.contains(to)
+                && self.pieces.get(to).unwrap().team == piece.team)
+            || (!possible._move
+                && !self.pieces.contains(to)
+                && self.en_passant_victim(piece, to).is_none())
+        {
+            return Err(MoveError {
+                err_type: MoveErrorType::InvalidMove(*piece),
+                from,
+                to,
+            });
+        }
+
+        // only pawns that haven't moved get to double step
+        if piece.name == Name::Pawn
+            && (to - from).length() == 2
+            && !self.unmoved_pawns.contains(&from)
         {
             return Err(MoveError {
                 err_type: MoveErrorType::InvalidMove(*piece),
@@ -276,21 +961,98 @@ impl HexBoard {
             });
         }
 
+        // kings are mated, never taken
+        if matches!(self.pieces.get(to), Some(p) if p.name == Name::King) {
+            return Err(MoveError {
+                err_type: MoveErrorType::CannotCaptureKing(*piece),
+                from,
+                to,
+            });
+        }
+
         Ok(())
     }
 
-    pub fn move_piece(&mut self, from: Coord, to: Coord) -> Result<(), MoveError> {
+    /// play a move after checking it's legal, giving back the piece it
+    /// captured if there was one
+    pub fn move_piece(&mut self, from: Coord, to: Coord) -> Result<Option<Piece>, MoveError> {
         self.can_move(from, to)?;
+        Ok(self.make(Move::new(from, to)).captured)
+    }
+}
 
-        self.teleport(from, to);
+/// what a board is saved as - just the state `PartialEq` looks at, with the
+/// caches worked out again on loading
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct BoardData {
+    radius: i32,
+    pieces: Vec<(Coord, Piece)>,
+    unmoved_pawns: Vec<Coord>,
+    en_passant: Option<Coord>,
+}
 
-        self.update_checkers();
-        Ok(())
+#[cfg(feature = "serde")]
+impl From<HexBoard> for BoardData {
+    fn from(board: HexBoard) -> Self {
+        let mut unmoved_pawns: Vec<Coord> = board.unmoved_pawns.iter().copied().collect();
+        unmoved_pawns.sort_unstable();
+        BoardData {
+            radius: board.radius(),
+            pieces: board.iter().collect(),
+            unmoved_pawns,
+            en_passant: board.en_passant,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<BoardData> for HexBoard {
+    type Error = PlacementError;
+
+    fn try_from(data: BoardData) -> Result<Self, Self::Error> {
+        if data.radius < 0 {
+            return Err(PlacementError::InvalidRadius(data.radius));
+        }
+        let mut board = HexBoard::with_radius(data.radius);
+        for (c, piece) in data.pieces {
+            if !board.in_bounds(c) {
+                return Err(PlacementError::OutOfBounds(c));
+            }
+            if board.pieces.insert(c, piece).is_some() {
+                return Err(PlacementError::Occupied(c));
+            }
+        }
+        board.unmoved_pawns = data
+            .unmoved_pawns
+            .into_iter()
+            .filter(|&c| matches!(board.pieces.get(c), Some(p) if p.name == Name::Pawn))
+            .collect();
+        board.en_passant = data.en_passant;
+        board.update_checkers();
+        Ok(board)
+    }
+}
+
+/// boards are equal when they have the same pieces in the same places and the
+/// same pawns able to double step or be taken en passant - the kings and
+/// checkers are only caches of what's on the board so aren't compared
+impl PartialEq for HexBoard {
+    fn eq(&self, other: &Self) -> bool {
+        self.pieces.radius == other.pieces.radius
+            && self.pieces.slots == other.pieces.slots
+            && self.unmoved_pawns == other.unmoved_pawns
+            && self.en_passant == other.en_passant
     }
+}
+
+impl Eq for HexBoard {}
 
-    fn teleport(&mut self, from: Coord, to: Coord) {
-        let piece = self.pieces.remove(&from).unwrap();
-        self.pieces.insert(to, piece);
+// consistent with `Eq` since equal boards have the same pieces, unmoved pawns
+// and en passant hex, which is all the key is made from
+impl Hash for HexBoard {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.transposition_key(Team::White).hash(state);
     }
 }
 
@@ -300,34 +1062,45 @@ impl Default for HexBoard {
     }
 }
 
-fn write_border(f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(f, "{:1$}", "", (HexBoard::N + 1) as usize,)?;
-    for _ in 0..(HexBoard::N + 2) {
+pub(crate) fn write_border(f: &mut fmt::Formatter<'_>, n: i32) -> fmt::Result {
+    write!(f, "{:1$}", "", (n + 1) as usize,)?;
+    for _ in 0..(n + 2) {
         write!(f, "# ")?;
     }
     Ok(())
 }
 
 impl fmt::Display for HexBoard {
+    /// the alternate flag (`{:#}`) renders pieces as letters
+    ///
+    /// a king in check is wrapped in markers like `>♔<`, which take the place
+    /// of the spaces either side of it
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write_border(f)?;
+        let n = self.radius();
+        write_border(f, n)?;
         writeln!(f)?;
-        for row in 0..(2 * Self::N + 1) {
-            write!(f, "{:1$}#", "", Self::N.abs_diff(row) as usize)?;
-            for col in 0..(2 * Self::N + 1 - Self::N.abs_diff(row) as i32) {
-                // convert cartesian to axial by adding when offset for initial rows
-                // then subtract radius to put (0, 0) in the center
-                let x = col + 0.max(Self::N - row) - Self::N;
-                let y = row - Self::N;
-
-                match self.pieces.get(&(x, y).into()) {
-                    Some(p) => write!(f, " {}", p),
-                    None => write!(f, " ."),
+        for row in 0..(2 * n + 1) {
+            write!(f, "{:1$}#", "", n.abs_diff(row) as usize)?;
+            let mut after_check = false;
+            for col in 0..(2 * n + 1 - n.abs_diff(row) as i32) {
+                let piece = self.pieces.get(Coord::from_offset(row, col, n));
+                let checked =
+                    matches!(piece, Some(p) if p.name == Name::King && self.is_in_check(p.team));
+                let gap = match (checked, after_check) {
+                    (true, _) => '>',
+                    (false, true) => '<',
+                    (false, false) => ' ',
+                };
+                after_check = checked;
+                match piece {
+                    Some(p) if f.alternate() => write!(f, "{}{:#}", gap, p),
+                    Some(p) => write!(f, "{}{}", gap, p),
+                    None => write!(f, "{}.", gap),
                 }?
             }
-            writeln!(f, " #")?;
+            writeln!(f, "{}#", if after_check { '<' } else { ' ' })?;
         }
-        write_border(f)?;
+        write_border(f, n)?;
         Ok(())
     }
 }
@@ -344,7 +1117,7 @@ mod tests {
     // check that a move is valid and that the piece has the state expected
     fn check_move(board: &mut HexBoard, f: Coord, t: Coord, start_piece: Piece, end_piece: Piece) {
         assert_eq!(board.get(f), Ok(&start_piece), "state:\n{}", board);
-        assert_eq!(board.move_piece(f, t), Ok(()));
+        assert!(board.move_piece(f, t).is_ok(), "state:\n{}", board);
         assert_eq!(board.get(t), Ok(&end_piece), "state:\n{}", board);
     }
 
@@ -382,11 +1155,158 @@ mod tests {
         );
     }
 
+    #[test]
+    fn variants_are_symmetric() {
+        for (variant, count) in [(Variant::Glinski, 36), (Variant::McCooey, 32)] {
+            let board = HexBoard::new_variant(variant);
+            assert_eq!(board.iter().count(), count, "{:?}", variant);
+
+            for (c, piece) in board.iter().filter(|(_c, p)| p.team == Team::White) {
+                assert_eq!(
+                    board.get(c.reflect_q()),
+                    Ok(&piece.flip_team()),
+                    "{:?}\n{}",
+                    variant,
+                    board
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn from_pieces_endgame() {
+        let board = HexBoard::from_pieces([
+            ((1, -5).into(), Piece::new(Name::King, Team::White)),
+            ((0, 0).into(), Piece::new(Name::Rook, Team::White)),
+            ((0, 4).into(), Piece::new(Name::King, Team::Black)),
+        ])
+        .unwrap();
+
+        assert_eq!(board.iter().count(), 3);
+        assert_eq!(
+            board.get((0, 0).into()),
+            Ok(&Piece::new(Name::Rook, Team::White))
+        );
+        // the rook is already checking the black king
+        assert!(board.is_in_check(Team::Black));
+    }
+
+    #[test]
+    fn from_pieces_rejects_invalid() {
+        let king = Piece::new(Name::King, Team::White);
+        assert_eq!(
+            HexBoard::from_pieces([((3, 3).into(), king)]).unwrap_err(),
+            PlacementError::OutOfBounds((3, 3).into())
+        );
+        assert_eq!(
+            HexBoard::from_pieces([
+                ((0, 0).into(), king),
+                ((0, 0).into(), Piece::new(Name::Pawn, Team::Black)),
+            ])
+            .unwrap_err(),
+            PlacementError::Occupied((0, 0).into())
+        );
+        assert_eq!(
+            HexBoard::from_pieces([((0, 0).into(), king), ((1, 0).into(), king)]).unwrap_err(),
+            PlacementError::MultipleKings(Team::White)
+        );
+    }
+
+    #[test]
+    fn pieces_zobrist_tracks_pieces() {
+        let mut board = HexBoard::new_initialize();
+        let start = board.pieces_zobrist();
+        assert_eq!(start, HexBoard::new_initialize().pieces_zobrist());
+
+        board.move_piece((-2, -3).into(), (-3, 0).into()).unwrap();
+        assert_ne!(board.pieces_zobrist(), start);
+        board.move_piece((-3, 0).into(), (-2, -3).into()).unwrap();
+        assert_eq!(board.pieces_zobrist(), start);
+    }
+
+    #[test]
+    fn render_ascii_start() {
+        let expected = concat!(
+            "      # # # # # # # \n",
+            "     # B K N R P . #\n",
+            "    # Q B . . P . . #\n",
+            "   # N . B . P . . . #\n",
+            "  # R . . . P . . . . #\n",
+            " # P P P P P . . . . . #\n",
+            "# . . . . . . . . . . . #\n",
+            " # . . . . . p p p p p #\n",
+            "  # . . . . p . . . r #\n",
+            "   # . . . p . b . n #\n",
+            "    # . . p . . b k #\n",
+            "     # . p r n q b #\n",
+            "      # # # # # # # ",
+        );
+        assert_eq!(HexBoard::new_initialize().render_ascii(), expected);
+    }
+
+    #[test]
+    fn render_labeled_aligns() {
+        let rendered = HexBoard::new_initialize().render_labeled();
+        let lines: Vec<Vec<char>> = rendered.lines().map(|l| l.chars().collect()).collect();
+        let column = |line: &[char], label: char| line.iter().position(|&c| c == label).unwrap();
+
+        // the white king is at (1, -5) - the first row of hexes
+        let header = &lines[0];
+        assert_eq!(lines[2][column(header, '1')], '♔', "{}", rendered);
+        assert_eq!(lines[2][..3], ['-', '5', ' ']);
+
+        // the black queen is at (-1, 5) - the last row of hexes
+        let footer = &lines[lines.len() - 1];
+        let row = &lines[lines.len() - 3];
+        assert_eq!(row[column(footer, '1')], '♛', "{}", rendered);
+        assert_eq!(row[..3], [' ', '5', ' ']);
+    }
+
+    #[test]
+    fn move_rook_small_board() {
+        let mut board = HexBoard::with_radius(3);
+        let rook = Piece::new(Name::Rook, Team::White);
+        board.place((0, 0).into(), rook);
+
+        check_move_sym(&mut board, (0, 0).into(), (0, 3).into(), rook);
+        check_move_sym(&mut board, (0, 3).into(), (-3, 3).into(), rook);
+
+        // the edge of a radius 3 board is closer than the default
+        check_move_fails(
+            &mut board,
+            (-3, 3).into(),
+            (-4, 3).into(),
+            Some(rook),
+            MoveError {
+                err_type: MoveErrorType::InvalidMove(rook),
+                from: (-3, 3).into(),
+                to: (-4, 3).into(),
+            },
+        );
+    }
+
+    #[test]
+    fn queen_on_diagonal_gives_check() {
+        let mut board = HexBoard::new();
+        board.place((0, 0).into(), Piece::new(Name::King, Team::White));
+        assert!(!board.is_in_check(Team::White));
+
+        board.place((2, 2).into(), Piece::new(Name::Queen, Team::Black));
+        assert!(board.is_in_check(Team::White));
+        assert_eq!(board.checkers(Team::White), &[(2, 2).into()]);
+        assert!(!board.is_in_check(Team::Black));
+
+        // blocking the diagonal stops the check
+        board.place((1, 1).into(), Piece::new(Name::Pawn, Team::White));
+        assert!(!board.is_in_check(Team::White));
+        assert!(board.checkers(Team::White).is_empty());
+    }
+
     #[test]
     fn move_pawn() {
         let mut board = HexBoard::new();
         let pawn = Piece::new(Name::Pawn, Team::White);
-        board.place((0, -2).into(), pawn.clone());
+        board.place((0, -2).into(), pawn);
         board.place((-1, -1).into(), pawn);
         board.place((1, 1).into(), Piece::new(Name::Pawn, Team::Black));
 
@@ -453,6 +1373,343 @@ mod tests {
         )
     }
 
+    #[test]
+    fn cells_index_every_hex_once() {
+        for n in [1, 3, HexBoard::DEFAULT_RADIUS] {
+            let cells = Cells::new(n);
+            let indices: Vec<_> = cells.coords().map(|c| cells.index(c).unwrap()).collect();
+            assert_eq!(indices, (0..cells.slots.len()).collect::<Vec<_>>());
+            assert_eq!(cells.index(Coord::new(n + 1, 0)), None);
+            assert_eq!(cells.index(Coord::new(n, 1)), None);
+        }
+    }
+
+    #[test]
+    fn extreme_coords_are_off_the_board() {
+        let board = HexBoard::new_initialize();
+        for c in [
+            Coord::new(i32::MIN, 0),
+            Coord::new(0, i32::MIN),
+            Coord::new(i32::MAX, i32::MAX),
+            Coord::new(i32::MIN, i32::MAX),
+        ] {
+            assert_eq!(board.get(c), Err(GetError::NoPiece(c)));
+            assert!(board.legal_moves(c).is_empty());
+            assert!(board.can_move(c, (0, 0).into()).is_err());
+            assert!(board.can_move((0, -1).into(), c).is_err());
+        }
+    }
+
+    fn perft(board: &HexBoard, team: Team, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        board
+            .all_legal_moves(team)
+            .into_iter()
+            .map(|(from, to)| {
+                let mut next = board.clone();
+                next.move_piece(from, to).unwrap();
+                perft(&next, team.flip(), depth - 1)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn perft_matches_hashmap_board() {
+        // counted with the board stored in a HashMap, before the dense slots,
+        // plus the six en passant captures three plies in
+        let glinski = HexBoard::new_variant(Variant::Glinski);
+        assert_eq!(perft(&glinski, Team::White, 1), 51);
+        assert_eq!(perft(&glinski, Team::White, 2), 2586);
+        assert_eq!(perft(&glinski, Team::White, 3), 137858);
+
+        let mccooey = HexBoard::new_variant(Variant::McCooey);
+        assert_eq!(perft(&mccooey, Team::White, 3), 36193);
+    }
+
+    #[test]
+    fn transpositions_share_a_key() {
+        let knights = [
+            Move::new((-2, -3).into(), (-3, 0).into()),
+            Move::new((2, 3).into(), (3, 0).into()),
+            Move::new((2, -5).into(), (3, -3).into()),
+            Move::new((-2, 5).into(), (-3, 3).into()),
+        ];
+        let mut a = HexBoard::new_initialize();
+        let mut b = HexBoard::new_initialize();
+        for i in [0, 1, 2, 3] {
+            a.move_piece(knights[i].from, knights[i].to).unwrap();
+        }
+        for i in [2, 3, 0, 1] {
+            b.move_piece(knights[i].from, knights[i].to).unwrap();
+        }
+        assert_eq!(
+            a.transposition_key(Team::White),
+            b.transposition_key(Team::White)
+        );
+        assert_ne!(
+            a.transposition_key(Team::White),
+            a.transposition_key(Team::Black)
+        );
+
+        // a pawn that could still double step makes for a different position
+        let mut c = HexBoard::new();
+        c.place((0, -1).into(), Piece::new(Name::Pawn, Team::White));
+        let mut d = c.clone();
+        d.unmoved_pawns.clear();
+        assert_eq!(c.pieces_zobrist(), d.pieces_zobrist());
+        assert_ne!(
+            c.transposition_key(Team::White),
+            d.transposition_key(Team::White)
+        );
+    }
+
+    #[test]
+    fn bishop_pinned_by_rook() {
+        let mut board = HexBoard::new();
+        board.place((0, -4).into(), Piece::new(Name::King, Team::White));
+        board.place((0, -1).into(), Piece::new(Name::Bishop, Team::White));
+        board.place((0, 3).into(), Piece::new(Name::Rook, Team::Black));
+        // a bishop on the king's diagonal isn't pinned by a rook
+        board.place((1, -3).into(), Piece::new(Name::Bishop, Team::White));
+        board.place((3, -1).into(), Piece::new(Name::Rook, Team::Black));
+        assert_eq!(
+            board.pinned_pieces(Team::White),
+            vec![((0, -1).into(), (0, 3).into())]
+        );
+        assert!(board.pinned_pieces(Team::Black).is_empty());
+    }
+
+    #[test]
+    fn starting_material() {
+        let board = HexBoard::new_initialize();
+        assert_eq!(board.material_balance(), 0);
+        for team in [Team::White, Team::Black] {
+            assert_eq!(board.count(Name::Pawn, team), 9);
+            assert_eq!(board.count(Name::Bishop, team), 3);
+            assert_eq!(board.piece_count(team), 18);
+        }
+
+        let mut board = HexBoard::new();
+        board.place((0, 0).into(), Piece::new(Name::Queen, Team::White));
+        board.place((1, 1).into(), Piece::new(Name::Rook, Team::Black));
+        assert_eq!(board.material_balance(), 4);
+        assert_eq!(
+            board.move_piece((0, 0).into(), (1, 1).into()),
+            Ok(Some(Piece::new(Name::Rook, Team::Black)))
+        );
+        assert_eq!(board.material_balance(), 9);
+    }
+
+    #[test]
+    fn cannot_capture_king() {
+        let mut board = HexBoard::new();
+        let rook = Piece::new(Name::Rook, Team::White);
+        board.place((0, -5).into(), Piece::new(Name::King, Team::White));
+        board.place((2, 2).into(), rook);
+        board.place((2, -2).into(), Piece::new(Name::King, Team::Black));
+        assert_eq!(
+            board.move_piece((2, 2).into(), (2, -2).into()),
+            Err(MoveError {
+                err_type: MoveErrorType::CannotCaptureKing(rook),
+                from: (2, 2).into(),
+                to: (2, -2).into(),
+            })
+        );
+    }
+
+    #[test]
+    fn display_marks_checked_king() {
+        let mut board = HexBoard::new();
+        board.place((0, -4).into(), Piece::new(Name::King, Team::White));
+        board.place((2, 2).into(), Piece::new(Name::Rook, Team::Black));
+        assert!(!board.to_string().contains('>'));
+        assert!(!format!("{:#}", board).contains(">K<"));
+
+        board.place((0, 2).into(), Piece::new(Name::Rook, Team::Black));
+        assert!(board.to_string().contains(">♔<"), "{}", board);
+        assert!(format!("{:#}", board).contains(">K<"));
+    }
+
+    #[test]
+    fn legal_moves_are_sorted() {
+        let board = HexBoard::new_initialize();
+        let moves = board.all_legal_moves(Team::White);
+        assert_eq!(moves, board.all_legal_moves(Team::White));
+        assert!(moves.windows(2).all(|pair| pair[0] < pair[1]));
+        let knight = board.legal_moves((-2, -3).into());
+        assert!(!knight.is_empty());
+        assert!(knight.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn hexes_cover_the_board() {
+        let board = HexBoard::new();
+        let hexes: HashSet<Coord> = board.hexes().collect();
+        assert_eq!(board.hexes().count(), 91);
+        assert_eq!(hexes.len(), 91);
+        assert!(hexes.iter().all(|c| c.length() <= 5));
+        assert_eq!(board_hexes(1).count(), 7);
+    }
+
+    #[test]
+    fn place_then_remove() {
+        let mut board = HexBoard::new();
+        let c = Coord::new(0, 2);
+        let rook = Piece::new(Name::Rook, Team::Black);
+        board.place((0, -4).into(), Piece::new(Name::King, Team::White));
+        assert!(board.is_empty(c));
+        board.place(c, rook);
+        assert!(!board.is_empty(c));
+        assert!(board.is_in_check(Team::White));
+
+        assert_eq!(board.remove(c), Some(rook));
+        assert!(board.is_empty(c));
+        assert_eq!(board.get(c), Err(GetError::NoPiece(c)));
+        assert!(!board.is_in_check(Team::White));
+        assert_eq!(board.remove(c), None);
+    }
+
+    #[test]
+    fn king_position_tracks_kings() {
+        let mut board = HexBoard::new_initialize();
+        assert_eq!(board.king_position(Team::White), Some((1, -5).into()));
+        assert_eq!(board.king_position(Team::Black), Some((1, 4).into()));
+
+        // moving the king along and back keeps track of it
+        let undo = board.make(Move::new((1, -5).into(), (1, -2).into()));
+        assert_eq!(board.king_position(Team::White), Some((1, -2).into()));
+        board.unmake(undo);
+        assert_eq!(board.king_position(Team::White), Some((1, -5).into()));
+
+        assert_eq!(
+            board.remove((1, 4).into()),
+            Some(Piece::new(Name::King, Team::Black))
+        );
+        assert_eq!(board.king_position(Team::Black), None);
+        assert_eq!(board.king_position(Team::White), Some((1, -5).into()));
+    }
+
+    #[test]
+    fn make_then_unmake_restores_board() {
+        let mut board = HexBoard::new_initialize();
+        board.place((1, -1).into(), Piece::new(Name::Knight, Team::Black));
+        board.place((2, -4).into(), Piece::new(Name::Rook, Team::Black));
+        let start = board.clone();
+
+        for (from, to) in [
+            // quiet knight move
+            ((-2, -3), (-3, 0)),
+            // pawn double step
+            ((-1, -1), (-1, 1)),
+            // pawn capture
+            ((0, -1), (1, -1)),
+            // bishop capturing a rook
+            ((0, -3), (2, -4)),
+            // king step
+            ((1, -5), (1, -4)),
+        ] {
+            let undo = board.make(Move::new(from.into(), to.into()));
+            assert_ne!(board, start);
+            board.unmake(undo);
+            assert_eq!(board, start);
+        }
+    }
+
+    #[test]
+    fn lone_king_legal_moves() {
+        let mut board = HexBoard::new();
+        board.place((0, 0).into(), Piece::new(Name::King, Team::White));
+        assert_eq!(board.legal_moves((0, 0).into()).len(), 12);
+        assert!(board.legal_moves((1, 0).into()).is_empty());
+
+        // the enemy rook covers the one diagonal landing on its file
+        board.place((2, -5).into(), Piece::new(Name::Rook, Team::Black));
+        let moves = board.all_legal_moves(Team::White);
+        assert_eq!(moves.len(), 11);
+        assert!(moves
+            .iter()
+            .all(|&(from, to)| from == (0, 0).into() && to.q != 2));
+    }
+
+    #[test]
+    fn pawn_attacks_diagonally() {
+        let mut board = HexBoard::new();
+        board.place((0, 0).into(), Piece::new(Name::Pawn, Team::White));
+
+        assert!(board.attacked_by((1, 0).into(), Team::White));
+        assert!(board.attacked_by((-1, 1).into(), Team::White));
+        assert!(!board.attacked_by((0, 1).into(), Team::White));
+        assert!(!board.attacked_by((0, 2).into(), Team::White));
+        assert!(!board.attacked_by((1, 0).into(), Team::Black));
+    }
+
+    #[test]
+    fn blocker_shields_from_rook() {
+        let mut board = HexBoard::new();
+        board.place((0, -3).into(), Piece::new(Name::Rook, Team::Black));
+        assert!(board.attacked_by((0, 3).into(), Team::Black));
+
+        // a piece of either team in the way blocks the attack, but the
+        // blocker itself is attacked
+        board.place((0, 1).into(), Piece::new(Name::Knight, Team::Black));
+        assert!(!board.attacked_by((0, 3).into(), Team::Black));
+        assert!(board.attacked_by((0, 1).into(), Team::Black));
+    }
+
+    #[test]
+    fn pawn_double_step_only_from_start() {
+        let mut board = HexBoard::new_initialize();
+        let pawn = Piece::new(Name::Pawn, Team::White);
+
+        // every starting pawn can double step, including the right flank
+        check_move_sym(&mut board, (3, -4).into(), (3, -2).into(), pawn);
+
+        check_move_sym(&mut board, (-2, -1).into(), (-2, 0).into(), pawn);
+        check_move_fails(
+            &mut board,
+            (-2, 0).into(),
+            (-2, 2).into(),
+            Some(pawn),
+            MoveError {
+                err_type: MoveErrorType::InvalidMove(pawn),
+                from: (-2, 0).into(),
+                to: (-2, 2).into(),
+            },
+        );
+    }
+
+    #[test]
+    fn black_pawn_captures() {
+        let mut board = HexBoard::new();
+        let pawn = Piece::new(Name::Pawn, Team::Black);
+        let knight = Piece::new(Name::Knight, Team::White);
+        board.place((0, 0).into(), pawn);
+        for c in [(0, -1), (0, 1), (1, 0), (-1, 1), (1, -1), (-1, 0)] {
+            board.place(c.into(), knight);
+        }
+
+        // straight ahead, backward, and white's capture hexes are all off limits
+        for c in [(0, -1), (0, 1), (1, 0), (-1, 1)] {
+            check_move_fails(
+                &mut board,
+                (0, 0).into(),
+                c.into(),
+                Some(pawn),
+                MoveError {
+                    err_type: MoveErrorType::InvalidMove(pawn),
+                    from: (0, 0).into(),
+                    to: c.into(),
+                },
+            );
+        }
+
+        // capture right, then left
+        check_move_sym(&mut board, (0, 0).into(), (1, -1).into(), pawn);
+        check_move_sym(&mut board, (1, -1).into(), (0, -1).into(), pawn);
+    }
+
     #[test]
     fn move_bishop() {
         let mut board = HexBoard::new();
@@ -524,4 +1781,332 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn equality() {
+        let mut a = HexBoard::new_initialize();
+        let b = HexBoard::from_pieces(a.iter()).unwrap();
+        assert_eq!(a, b);
+
+        a.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        assert_ne!(a, b);
+
+        // the same pieces, but a placed pawn may still double step
+        let mut placed = b.clone();
+        placed.remove((0, -1).into());
+        placed.place((0, 0).into(), Piece::new(Name::Pawn, Team::White));
+        assert_eq!(placed.to_string(), a.to_string());
+        assert_ne!(placed, a);
+    }
+
+    #[test]
+    fn king_cannot_walk_into_check() {
+        let mut board = HexBoard::new();
+        let king = Piece::new(Name::King, Team::White);
+        board.place((0, -5).into(), king);
+        board.place((1, 0).into(), Piece::new(Name::Rook, Team::Black));
+        board.place((0, 5).into(), Piece::new(Name::King, Team::Black));
+        assert!(!board.is_in_check(Team::White));
+        assert_eq!(
+            board.can_move((0, -5).into(), (1, -5).into()),
+            Err(MoveError {
+                err_type: MoveErrorType::MovesIntoCheck(king),
+                from: (0, -5).into(),
+                to: (1, -5).into(),
+            })
+        );
+        assert!(board.can_move((0, -5).into(), (-1, -4).into()).is_ok());
+
+        // and once in check, only moves that escape it are allowed
+        board.move_piece((1, 0).into(), (0, 0).into()).unwrap();
+        assert!(board.is_in_check(Team::White));
+        assert_eq!(board.checkers(Team::White), &[Coord::new(0, 0)]);
+        assert!(matches!(
+            board.can_move((0, -5).into(), (0, -4).into()),
+            Err(MoveError {
+                err_type: MoveErrorType::InCheck(_),
+                ..
+            })
+        ));
+        assert!(board.can_move((0, -5).into(), (1, -5).into()).is_ok());
+    }
+
+    #[test]
+    fn blocked_check_is_not_mate() {
+        // the king is boxed in by its own pieces, but the rook can block
+        let mut board = HexBoard::new();
+        board.place((0, -5).into(), Piece::new(Name::King, Team::White));
+        for c in [(-1, -4), (1, -5), (-1, -3), (1, -4)] {
+            board.place(c.into(), Piece::new(Name::Pawn, Team::White));
+        }
+        board.place((3, -2).into(), Piece::new(Name::Rook, Team::White));
+        board.place((0, 4).into(), Piece::new(Name::Queen, Team::Black));
+        board.place((5, 0).into(), Piece::new(Name::King, Team::Black));
+        assert!(board.is_in_check(Team::White));
+        assert!(!board.is_checkmated(Team::White));
+        assert_eq!(
+            board.all_legal_moves(Team::White),
+            vec![
+                ((3, -2).into(), (0, -2).into()),
+                ((3, -2).into(), (0, 1).into())
+            ]
+        );
+
+        // without the rook nothing can help
+        board.remove((3, -2).into());
+        assert!(board.is_checkmated(Team::White));
+        assert!(!board.is_stalemated(Team::White));
+    }
+
+    #[test]
+    fn stalemate() {
+        let mut board = HexBoard::new();
+        board.place((3, -2).into(), Piece::new(Name::King, Team::White));
+        board.place((2, -4).into(), Piece::new(Name::Queen, Team::White));
+        board.place((5, -5).into(), Piece::new(Name::King, Team::Black));
+        assert!(board.is_stalemated(Team::Black));
+        assert!(!board.is_checkmated(Team::Black));
+        assert!(!board.is_stalemated(Team::White));
+    }
+
+    #[test]
+    fn en_passant() {
+        let white = Piece::new(Name::Pawn, Team::White);
+        let black = Piece::new(Name::Pawn, Team::Black);
+        let mut board = HexBoard::new();
+        board.place((0, -5).into(), Piece::new(Name::King, Team::White));
+        board.place((0, 5).into(), Piece::new(Name::King, Team::Black));
+        board.place((1, -1).into(), white);
+        board.place((-1, 2).into(), black);
+        let start = board.clone();
+
+        // white takes a black pawn that double stepped past it
+        board.move_piece((-1, 2).into(), (-1, 0).into()).unwrap();
+        assert_eq!(board.en_passant(), Some((-1, 1).into()));
+        assert_eq!(board.capture_square((1, -1).into(), (0, 0).into()), None);
+        assert_eq!(board.capture_square((0, 0).into(), (-1, 1).into()), None);
+        let mut taken = board.clone();
+        let undo = taken.make(Move::new((-1, 0).into(), (-1, -1).into()));
+        taken.unmake(undo);
+        assert_eq!(taken, board);
+
+        board.place((0, 0).into(), white);
+        assert_eq!(
+            board.capture_square((0, 0).into(), (-1, 1).into()),
+            Some((-1, 0).into())
+        );
+        let undo = board.make(Move::new((0, 0).into(), (-1, 1).into()));
+        assert_eq!(board.get((-1, 1).into()), Ok(&white));
+        assert!(board.is_empty((-1, 0).into()));
+        assert_eq!(board.en_passant(), None);
+        board.unmake(undo);
+        assert_eq!(board.get((-1, 0).into()), Ok(&black));
+        assert_eq!(board.en_passant(), Some((-1, 1).into()));
+
+        // the chance is gone after any other move
+        board.move_piece((0, 5).into(), (1, 4).into()).unwrap();
+        assert!(board.move_piece((0, 0).into(), (-1, 1).into()).is_err());
+
+        // and the same for black, whose pawns head the other way
+        let mut board = start;
+        board.remove((-1, 2).into());
+        board.place((1, 0).into(), black);
+        board.place((0, -1).into(), white);
+        board.move_piece((0, -1).into(), (0, 1).into()).unwrap();
+        assert_eq!(board.en_passant(), Some((0, 0).into()));
+        // only pawns get to take en passant
+        let mut knight = board.clone();
+        knight.place((-1, -2).into(), Piece::new(Name::Knight, Team::Black));
+        knight.move_piece((-1, -2).into(), (0, 0).into()).unwrap();
+        assert_eq!(knight.get((0, 1).into()), Ok(&white));
+        board.move_piece((1, 0).into(), (0, 0).into()).unwrap();
+        assert_eq!(board.get((0, 0).into()), Ok(&black));
+        assert!(board.is_empty((0, 1).into()));
+    }
+
+    #[test]
+    fn en_passant_cannot_expose_king() {
+        // taking en passant would clear the rank between the rook and king
+        let mut board = HexBoard::new();
+        let white = Piece::new(Name::Pawn, Team::White);
+        board.place((-4, 0).into(), Piece::new(Name::King, Team::White));
+        board.place((4, 0).into(), Piece::new(Name::Rook, Team::Black));
+        board.place((0, 5).into(), Piece::new(Name::King, Team::Black));
+        board.place((1, 0).into(), white);
+        board.place((0, 2).into(), Piece::new(Name::Pawn, Team::Black));
+        board.move_piece((0, 2).into(), (0, 0).into()).unwrap();
+        assert_eq!(
+            board.move_piece((1, 0).into(), (0, 1).into()),
+            Err(MoveError {
+                err_type: MoveErrorType::MovesIntoCheck(white),
+                from: (1, 0).into(),
+                to: (0, 1).into(),
+            })
+        );
+    }
+
+    #[test]
+    fn pawns_promote_on_last_rank() {
+        let mut board = HexBoard::new();
+        board.place((1, -5).into(), Piece::new(Name::King, Team::White));
+        board.place((4, 0).into(), Piece::new(Name::King, Team::Black));
+        board.place((0, 4).into(), Piece::new(Name::Pawn, Team::White));
+        board.place((-3, -1).into(), Piece::new(Name::Pawn, Team::Black));
+        let before = board.clone();
+
+        let undo = board.make(Move::promoting((0, 4).into(), (0, 5).into(), Name::Knight));
+        assert_eq!(
+            board.get((0, 5).into()),
+            Ok(&Piece::new(Name::Knight, Team::White))
+        );
+        board.unmake(undo);
+        assert_eq!(board, before);
+
+        // a queen unless told otherwise
+        board.move_piece((0, 4).into(), (0, 5).into()).unwrap();
+        assert_eq!(
+            board.get((0, 5).into()),
+            Ok(&Piece::new(Name::Queen, Team::White))
+        );
+        board.move_piece((-3, -1).into(), (-3, -2).into()).unwrap();
+        assert!(board.is_last_rank((-3, -2).into(), Team::Black));
+        assert_eq!(
+            board.get((-3, -2).into()),
+            Ok(&Piece::new(Name::Queen, Team::Black))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut board = HexBoard::new_initialize();
+        board.move_piece((1, -2).into(), (1, 0).into()).unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        let read: HexBoard = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, board);
+        assert_eq!(read.en_passant(), Some((1, -1).into()));
+
+        let doubled = r#"{"radius":5,"pieces":[[{"q":0,"r":0},{"name":"King","team":"White"}],[{"q":0,"r":0},{"name":"Pawn","team":"Black"}]],"unmoved_pawns":[],"en_passant":null}"#;
+        assert!(serde_json::from_str::<HexBoard>(doubled)
+            .unwrap_err()
+            .to_string()
+            .contains("more than one piece"));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let mut board = HexBoard::new();
+        board.place((0, -5).into(), Piece::new(Name::King, Team::White));
+        board.place((0, 5).into(), Piece::new(Name::King, Team::Black));
+        assert!(board.insufficient_material());
+        board.place((0, 0).into(), Piece::new(Name::Bishop, Team::Black));
+        assert!(board.insufficient_material());
+        board.place((1, 0).into(), Piece::new(Name::Knight, Team::White));
+        assert!(!board.insufficient_material());
+        board.remove((0, 0).into());
+        assert!(board.insufficient_material());
+        board.place((1, 0).into(), Piece::new(Name::Pawn, Team::White));
+        assert!(!board.insufficient_material());
+        assert!(!HexBoard::new_initialize().insufficient_material());
+    }
+
+    #[test]
+    fn attack_maps() {
+        let mut board = HexBoard::new_initialize();
+        board.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        for team in [Team::White, Team::Black] {
+            let attacked = board.attacked_hexes(team);
+            let expected: HashSet<Coord> = board
+                .hexes()
+                .filter(|&c| board.attacked_by(c, team))
+                .collect();
+            assert_eq!(attacked, expected);
+        }
+        // the pawn attacks forward and to the sides, not straight ahead
+        assert!(!board.attacked_hexes(Team::White).contains(&(0, 1).into()));
+        assert_eq!(
+            board.attackers_of((1, 0).into(), Team::White),
+            vec![(0, 0).into()]
+        );
+        board.place((1, -1).into(), Piece::new(Name::Rook, Team::White));
+        assert_eq!(
+            board.attackers_of((1, 0).into(), Team::White),
+            vec![(0, 0).into(), (1, -1).into()]
+        );
+        // a piece doesn't attack the hex it stands on
+        assert!(!board.attacked_by((-3, -2).into(), Team::White));
+    }
+
+    #[test]
+    fn collides_matches_blocker_scan() {
+        // xorshift, to scatter pieces the same way every run
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..8 {
+            let mut board = HexBoard::new();
+            for c in board.hexes().collect::<Vec<_>>() {
+                if next() % 3 == 0 {
+                    board.place(c, Piece::new(Name::Pawn, Team::White));
+                }
+            }
+            for f in board.hexes() {
+                for t in board.hexes().filter(|&t| t != f) {
+                    let v = t - f;
+                    // a piece is in the way if its center is on the segment,
+                    // which a linear map like axial coords preserves
+                    let blocked = board.hexes().any(|c| {
+                        let w = c - f;
+                        let along = w.q * v.q + w.r * v.r;
+                        board.get(c).is_ok()
+                            && w.q * v.r == w.r * v.q
+                            && 0 < along
+                            && along < v.q * v.q + v.r * v.r
+                    });
+                    assert_eq!(board.collides(f, t), blocked, "{} to {}", f, t);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn layouts_on_other_radii() {
+        assert_eq!(Variant::Glinski.min_radius(), 5);
+        assert_eq!(Variant::McCooey.min_radius(), 4);
+        assert_eq!(
+            HexBoard::new_variant_with_radius(Variant::Glinski, 4),
+            Err(PlacementError::LayoutDoesNotFit(4))
+        );
+        assert_eq!(
+            HexBoard::new_variant_with_radius(Variant::Glinski, 5),
+            Ok(HexBoard::new_initialize())
+        );
+
+        assert!(HexBoard::new_variant_with_radius(Variant::McCooey, 4).is_ok());
+
+        for (variant, n, pawns) in [(Variant::Glinski, 7, 9), (Variant::McCooey, 6, 7)] {
+            let board = HexBoard::new_variant_with_radius(variant, n).unwrap();
+            assert_eq!(board.radius(), n);
+            assert_eq!(board.to_string().lines().count() as i32, 2 * n + 3);
+            // the back rank sits against the edge
+            let king = |team| Piece::new(Name::King, team);
+            assert_eq!(board.get(Coord::new(1, -n)), Ok(&king(Team::White)));
+            assert_eq!(board.get(Coord::new(1, n - 1)), Ok(&king(Team::Black)));
+            // and every pawn may still double step
+            for (c, piece) in board.iter() {
+                if piece.name == Name::Pawn && piece.team == Team::White {
+                    assert!(
+                        board.legal_moves(c).contains(&(c + Coord::new(0, 2))),
+                        "{}",
+                        c
+                    );
+                }
+            }
+            assert_eq!(board.count(Name::Pawn, Team::White), pawns);
+        }
+    }
 }
@@ -1,9 +1,16 @@
-use std::{
+//! hex coordinates and their arithmetic, which only need `core` so they
+//! build without std
+
+use core::{
     fmt,
-    ops::{Add, Div, Mul, Sub},
+    num::ParseIntError,
+    ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
+    str::FromStr,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// coords are ordered by `q` and then `r`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Coord {
     pub q: i32,
     pub r: i32,
@@ -12,6 +19,28 @@ pub struct Coord {
 impl Coord {
     pub const ZERO: Coord = Coord::new(0, 0);
 
+    /// the steps to each of the six hexes sharing an edge with a hex, going
+    /// around it in order
+    pub const ADJACENTS: [Coord; 6] = [
+        Coord::new(1, 0),
+        Coord::new(1, -1),
+        Coord::new(0, -1),
+        Coord::new(-1, 0),
+        Coord::new(-1, 1),
+        Coord::new(0, 1),
+    ];
+
+    /// the steps a bishop takes, each crossing between two hexes that share
+    /// an edge
+    pub const DIAGONALS: [Coord; 6] = [
+        Coord::new(1, -2),
+        Coord::new(2, -1),
+        Coord::new(1, 1),
+        Coord::new(-1, 2),
+        Coord::new(-2, 1),
+        Coord::new(-1, -1),
+    ];
+
     pub const fn new(q: i32, r: i32) -> Self {
         Self { q, r }
     }
@@ -32,6 +61,68 @@ impl Coord {
         q * q + r * r + q * r
     }
 
+    /// gives the euclidean norm, in units of the distance between hex centers
+    /// (with the bevy frontend's `LAYOUT_FLAT`, that distance is `sqrt(3) * size`)
+    #[cfg(feature = "std")]
+    pub fn norm(&self) -> f32 {
+        (self.norm_squared() as f32).sqrt()
+    }
+
+    /// which of the board's three tile colors this hex is, with 0 for the
+    /// center hex's color. no two adjacent hexes share a color
+    pub fn color_index(&self) -> u8 {
+        // the same as `norm_squared` modulo 3 picking out the center's color
+        (self.q - self.r).rem_euclid(3) as u8
+    }
+
+    /// the cube coordinates `(x, y, z)`, which are just `(q, r, s)`
+    pub fn to_cube(&self) -> (i32, i32, i32) {
+        (self.q, self.r, self.s())
+    }
+
+    /// build a coord from cube coordinates, which must sum to zero
+    pub fn from_cube(x: i32, y: i32, z: i32) -> Result<Self, CoordError> {
+        if x + y + z == 0 {
+            Ok(Self::new(x, y))
+        } else {
+            Err(CoordError::NotOnPlane(x, y, z))
+        }
+    }
+
+    /// the `(row, col)` of this hex when a board of radius `n` is drawn as
+    /// staggered rows of text, with `r = -n` as row 0 and each row's columns
+    /// counting from its leftmost hex
+    pub fn to_offset(&self, n: i32) -> (i32, i32) {
+        (self.r + n, self.q + n - 0.max(-self.r))
+    }
+
+    /// the hex at `(row, col)` of a board of radius `n` drawn as in
+    /// `to_offset`
+    pub fn from_offset(row: i32, col: i32, n: i32) -> Self {
+        // shift the rows above the middle right then put (0, 0) in the center
+        Self::new(col + 0.max(n - row) - n, row - n)
+    }
+
+    /// build a coord that must lie on a board with `n` hexes between the
+    /// center and each edge
+    ///
+    /// this can't be `TryFrom<(i32, i32)>` as the infallible `From` already
+    /// provides one through the blanket impl
+    pub fn try_new(q: i32, r: i32, n: i32) -> Result<Self, CoordError> {
+        let c = Self::new(q, r);
+        if c.in_bounds(n) {
+            Ok(c)
+        } else {
+            Err(CoordError::OutOfBounds(c, n))
+        }
+    }
+
+    /// whether `|q|`, `|r|` and `|s|` are all at most `n`
+    pub fn in_bounds(&self, n: i32) -> bool {
+        // checking `q` and `r` first keeps `s` from overflowing
+        (-n..=n).contains(&self.q) && (-n..=n).contains(&self.r) && self.length() <= n
+    }
+
     pub fn reflect_q(self) -> Self {
         Self::new(self.q, self.s())
     }
@@ -41,6 +132,106 @@ impl Coord {
             || (self.r == 0 && self.q != 0 && self.s() != 0)
             || (self.s() == 0 && self.q != 0 && self.r != 0)
     }
+
+    /// whether this is a non-zero multiple of one of the `DIAGONALS`, which
+    /// have two of `q`, `r` and `s` equal
+    pub fn is_diagonal(&self) -> bool {
+        *self != Self::ZERO && (self.q == self.r || self.r == self.s() || self.s() == self.q)
+    }
+
+    /// the six hexes sharing an edge with this one, in the order of
+    /// `ADJACENTS`
+    pub fn neighbors(self) -> impl Iterator<Item = Coord> {
+        Self::ADJACENTS.into_iter().map(move |step| self + step)
+    }
+
+    /// the hexes exactly `radius` steps from this one, going once around the
+    /// ring. a radius of 0 gives just this hex and a negative one nothing
+    pub fn ring(self, radius: i32) -> impl Iterator<Item = Coord> {
+        let center = (radius == 0).then_some(self);
+        // walk each side from its corner, where the side before it ended
+        let sides = (0..6).flat_map(move |side| {
+            let corner = self + Self::ADJACENTS[(side + 4) % 6] * radius;
+            (0..radius).map(move |step| corner + Self::ADJACENTS[side] * step)
+        });
+        center.into_iter().chain(sides)
+    }
+
+    /// the hexes at most `radius` steps from this one, going through them by
+    /// `q` and then `r`
+    pub fn range(self, radius: i32) -> impl Iterator<Item = Coord> {
+        (-radius..=radius).flat_map(move |q| {
+            ((-radius).max(-q - radius)..=radius.min(-q + radius))
+                .map(move |r| self + Coord::new(q, r))
+        })
+    }
+
+    /// the hexes whose centers lie on the straight line from this one to
+    /// `other`, in order and including both ends
+    ///
+    /// along an axis that's every hex in between, along a diagonal every other
+    /// one, and for a knight's jump only the two ends
+    pub fn line_to(self, other: Coord) -> impl Iterator<Item = Coord> {
+        let (step, steps) = self.delta_to(other).split();
+        (0..=steps).map(move |n| self + step * n)
+    }
+
+    /// the move from this hex to `other`
+    pub fn delta_to(self, other: Coord) -> Delta {
+        Delta(other - self)
+    }
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// one of the twelve directions a rook, bishop or queen can slide in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Dir(Coord);
+
+impl Dir {
+    /// the step to the next hex in this direction, one of `Coord::ADJACENTS`
+    /// or `Coord::DIAGONALS`
+    pub fn step(self) -> Coord {
+        self.0
+    }
+
+    /// whether this is a bishop's direction rather than a rook's
+    pub fn is_diagonal(self) -> bool {
+        self.0.is_diagonal()
+    }
+}
+
+/// the difference between two hexes, kept apart from `Coord` so that it can
+/// only be split up exactly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Delta(pub Coord);
+
+impl Delta {
+    /// the shortest whole step this is a multiple of and how many of them it
+    /// takes, with `(ZERO, 0)` for no move at all
+    pub fn split(self) -> (Coord, i32) {
+        let Delta(v) = self;
+        let steps = gcd(v.q, v.r);
+        if steps == 0 {
+            (Coord::ZERO, 0)
+        } else {
+            (Coord::new(v.q / steps, v.r / steps), steps)
+        }
+    }
+
+    /// the direction and number of steps of a straight slide, or `None` if
+    /// this isn't one
+    pub fn decompose(self) -> Option<(Dir, i32)> {
+        let (step, steps) = self.split();
+        (step.length() == 1 || (step.length() == 2 && step.is_diagonal()))
+            .then_some((Dir(step), steps))
+    }
 }
 
 impl From<(i32, i32)> for Coord {
@@ -49,6 +240,76 @@ impl From<(i32, i32)> for Coord {
     }
 }
 
+// the errors here are written out by hand as thiserror needs std
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordError {
+    NotOnPlane(i32, i32, i32),
+    /// the coord and the radius of the board it is off
+    OutOfBounds(Coord, i32),
+}
+
+impl fmt::Display for CoordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoordError::NotOnPlane(x, y, z) => {
+                write!(
+                    f,
+                    "cube coordinate ({}, {}, {}) does not sum to zero",
+                    x, y, z
+                )
+            }
+            CoordError::OutOfBounds(c, n) => {
+                write!(f, "{} is off a board of radius {}", c, n)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CoordError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseCoordError {
+    Format,
+    Int(ParseIntError),
+}
+
+impl From<ParseIntError> for ParseCoordError {
+    fn from(e: ParseIntError) -> Self {
+        ParseCoordError::Int(e)
+    }
+}
+
+impl fmt::Display for ParseCoordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseCoordError::Format => write!(f, "expected a coordinate of the form `q, r`"),
+            ParseCoordError::Int(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseCoordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseCoordError::Format => None,
+            ParseCoordError::Int(e) => Some(e),
+        }
+    }
+}
+
+/// parses the `q, r` form used on the command line
+impl FromStr for Coord {
+    type Err = ParseCoordError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (q, r) = s.split_once(',').ok_or(ParseCoordError::Format)?;
+        Ok(Self::new(q.trim().parse()?, r.trim().parse()?))
+    }
+}
+
 impl Add for Coord {
     type Output = Self;
 
@@ -65,19 +326,23 @@ impl Sub for Coord {
     }
 }
 
-impl Div for Coord {
-    type Output = Self;
+impl AddAssign for Coord {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
 
-    fn div(self, rhs: Self) -> Self::Output {
-        Self::new(self.q / rhs.q, self.r / rhs.r)
+impl SubAssign for Coord {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
     }
 }
 
-impl Div<i32> for Coord {
+impl Neg for Coord {
     type Output = Self;
 
-    fn div(self, rhs: i32) -> Self::Output {
-        Self::new(self.q / rhs, self.r / rhs)
+    fn neg(self) -> Self::Output {
+        Self::new(-self.q, -self.r)
     }
 }
 
@@ -102,3 +367,212 @@ impl fmt::Display for Coord {
         write!(f, "({}, {}, {})", self.q, self.r, self.s())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        assert_eq!("1, -2".parse(), Ok(Coord::new(1, -2)));
+        assert_eq!(" 0,3 ".parse(), Ok(Coord::new(0, 3)));
+        assert_eq!("1 -2".parse::<Coord>(), Err(ParseCoordError::Format));
+        assert!(matches!(
+            "a, 2".parse::<Coord>(),
+            Err(ParseCoordError::Int(_))
+        ));
+    }
+
+    #[test]
+    fn assign_ops_and_neg() {
+        let c = Coord::new(2, -3);
+        assert_eq!(-c, Coord::ZERO - c);
+        assert_eq!((-c).s(), -c.s());
+
+        let mut acc = Coord::new(1, 1);
+        acc += c;
+        assert_eq!(acc, Coord::new(1, 1) + c);
+        acc -= c;
+        assert_eq!(acc, Coord::new(1, 1));
+    }
+
+    #[test]
+    fn cube_round_trip() {
+        for c in [
+            Coord::ZERO,
+            Coord::new(1, -2),
+            Coord::new(-5, 3),
+            Coord::new(4, 1),
+        ] {
+            let (x, y, z) = c.to_cube();
+            assert_eq!(x + y + z, 0);
+            assert_eq!(Coord::from_cube(x, y, z), Ok(c));
+        }
+        assert_eq!(
+            Coord::from_cube(1, 1, 1),
+            Err(CoordError::NotOnPlane(1, 1, 1))
+        );
+    }
+
+    #[test]
+    fn arithmetic_without_std() {
+        let a = Coord::new(3, -1);
+        let b = Coord::new(-1, 2);
+        assert_eq!(a + b, Coord::new(2, 1));
+        assert_eq!(a - b, Coord::new(4, -3));
+        assert_eq!(a * 2, Coord::new(6, -2));
+        assert_eq!(a.length(), 3);
+        assert_eq!(a.reflect_q(), Coord::new(3, -2));
+        assert!(Coord::new(0, 2).is_axis());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn unit_hex_norm() {
+        for c in [Coord::new(1, 0), Coord::new(0, -1), Coord::new(-1, 1)] {
+            assert!((c.norm() - 1.0).abs() < f32::EPSILON);
+        }
+        assert!((Coord::new(1, 1).norm() - 3f32.sqrt()).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn bounds_checked() {
+        assert_eq!(Coord::try_new(2, 3, 5), Ok(Coord::new(2, 3)));
+        assert_eq!(Coord::try_new(-5, 5, 5), Ok(Coord::new(-5, 5)));
+        // |s| is 6 even though q and r are in range
+        assert_eq!(
+            Coord::try_new(3, 3, 5),
+            Err(CoordError::OutOfBounds(Coord::new(3, 3), 5))
+        );
+        assert!(!Coord::new(0, 6).in_bounds(5));
+        assert!(!Coord::new(i32::MAX, i32::MAX).in_bounds(5));
+        assert!(!Coord::new(i32::MIN, 0).in_bounds(5));
+    }
+
+    #[test]
+    fn adjacent_colors_differ() {
+        for q in -5..=5 {
+            for r in -5..=5 {
+                let c = Coord::new(q, r);
+                assert!(c.color_index() < 3);
+                assert_eq!(c.color_index() == 0, c.norm_squared() % 3 == 0);
+                for neighbor in c.neighbors() {
+                    assert_ne!(c.color_index(), neighbor.color_index());
+                }
+            }
+        }
+        assert_eq!(Coord::ZERO.color_index(), 0);
+    }
+
+    #[test]
+    fn offset_round_trip() {
+        for c in [
+            Coord::ZERO,
+            Coord::new(0, -5),
+            Coord::new(5, -5),
+            Coord::new(-5, 5),
+            Coord::new(-3, 1),
+            Coord::new(2, 2),
+        ] {
+            let (row, col) = c.to_offset(5);
+            assert_eq!(Coord::from_offset(row, col, 5), c);
+        }
+        // the top left corner and the last hex of the middle row
+        assert_eq!(Coord::new(0, -5).to_offset(5), (0, 0));
+        assert_eq!(Coord::new(5, 0).to_offset(5), (5, 10));
+    }
+
+    #[test]
+    fn diagonals() {
+        for d in Coord::DIAGONALS {
+            assert!(d.is_diagonal(), "{}", d);
+            assert!((d * 3).is_diagonal(), "{}", d);
+            assert_eq!(d.length(), 2);
+        }
+        for c in [
+            Coord::ZERO,
+            Coord::new(1, 0),
+            Coord::new(0, -3),
+            Coord::new(2, -2),
+            Coord::new(1, 2),
+            Coord::new(3, -1),
+            Coord::new(2, -3),
+        ] {
+            assert!(!c.is_diagonal(), "{}", c);
+        }
+    }
+
+    #[test]
+    fn neighbors() {
+        let c = Coord::new(2, -3);
+        assert_eq!(c.neighbors().count(), 6);
+        assert!(c.neighbors().all(|n| (n - c).length() == 1));
+        assert_eq!(c.ring(1).count(), 6);
+        assert!(c.ring(1).all(|h| c.neighbors().any(|n| n == h)));
+    }
+
+    #[test]
+    fn rings() {
+        let c = Coord::new(-1, 3);
+        assert!(c.ring(0).eq([c]));
+        assert_eq!(c.ring(-1).count(), 0);
+        for radius in 1..=6 {
+            assert_eq!(c.ring(radius).count(), 6 * radius as usize);
+            assert!(c.ring(radius).all(|h| (h - c).length() == radius));
+            // each hex follows on from the last, wrapping back to the start
+            let next = c.ring(radius).skip(1).chain(c.ring(radius).take(1));
+            assert!(c.ring(radius).zip(next).all(|(a, b)| (b - a).length() == 1));
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let c = Coord::new(3, -2);
+        assert_eq!(c.range(-1).count(), 0);
+        for radius in 0..=5 {
+            assert_eq!(
+                c.range(radius).count(),
+                (3 * radius * (radius + 1) + 1) as usize
+            );
+            assert!(c.range(radius).all(|h| (h - c).length() <= radius));
+            assert!(c
+                .range(radius)
+                .zip(c.range(radius).skip(1))
+                .all(|(a, b)| a < b));
+            assert!((0..=radius).all(|k| c.ring(k).all(|h| c.range(radius).any(|g| g == h))));
+        }
+    }
+
+    #[test]
+    fn lines() {
+        let c = Coord::new(1, -1);
+        assert!(c.line_to(c).eq([c]));
+        assert!(c
+            .line_to(Coord::new(1, 2))
+            .eq([(1, -1), (1, 0), (1, 1), (1, 2)].map(Coord::from)));
+        assert!(c
+            .line_to(Coord::new(-3, 1))
+            .eq([(1, -1), (-1, 0), (-3, 1)].map(Coord::from)));
+        // no hex centers lie between the ends of a knight's jump
+        assert!(c
+            .line_to(Coord::new(4, -2))
+            .eq([(1, -1), (4, -2)].map(Coord::from)));
+    }
+
+    #[test]
+    fn decompose() {
+        let slide = |q, r| Coord::ZERO.delta_to(Coord::new(q, r)).decompose();
+        let (dir, steps) = slide(0, -4).unwrap();
+        assert_eq!((dir.step(), steps), (Coord::new(0, -1), 4));
+        assert!(!dir.is_diagonal());
+        let (dir, steps) = slide(-6, 3).unwrap();
+        assert_eq!((dir.step(), steps), (Coord::new(-2, 1), 3));
+        assert!(dir.is_diagonal());
+        // neither of these lie along a line of hexes, however they're rounded
+        assert_eq!(slide(5, -1), None);
+        assert_eq!(slide(3, -1), None);
+        assert_eq!(slide(4, 2), None);
+        assert_eq!(slide(0, 0), None);
+        assert_eq!(Delta(Coord::new(6, -9)).split(), (Coord::new(2, -3), 3));
+    }
+}
@@ -0,0 +1,315 @@
+//! exhaustive solving of small endgames by retrograde analysis
+//!
+//! starting from every checkmate the solver works backwards through the moves
+//! that could have led there, so each position is only looked at a handful of
+//! times rather than searched forwards
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    board::{board_hexes, BoardKey, HexBoard},
+    coord::Coord,
+    piece::{Name, Piece, Team},
+    tables, zobrist,
+};
+
+const HEXES: usize = 91;
+/// marks a position that isn't known to be won (yet)
+const UNKNOWN: i32 = -1;
+
+/// the geometry of the default board, indexed by the hexes in `board_hexes`
+/// order
+struct Geometry {
+    coords: Vec<Coord>,
+    king_moves: Vec<Vec<usize>>,
+    /// the hexes along each rook or bishop line out from a hex
+    rays: Vec<Vec<Vec<usize>>>,
+    /// the hexes strictly between two hexes on a line, if they share one
+    between: Vec<Option<Vec<usize>>>,
+    adjacent: Vec<bool>,
+}
+
+impl Geometry {
+    fn new() -> Self {
+        let coords: Vec<Coord> = board_hexes(HexBoard::DEFAULT_RADIUS).collect();
+        let index: HashMap<Coord, usize> =
+            coords.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+        let king_moves: Vec<Vec<usize>> = coords
+            .iter()
+            .map(|&c| {
+                tables::targets(Name::King, c)
+                    .iter()
+                    .map(|t| index[t])
+                    .collect()
+            })
+            .collect();
+        let rays: Vec<Vec<Vec<usize>>> = coords
+            .iter()
+            .map(|&c| {
+                HexBoard::ADJACENTS
+                    .iter()
+                    .chain(HexBoard::DIAGONALS)
+                    .map(|&step| {
+                        (1..)
+                            .map_while(|n| index.get(&(c + step * n)).copied())
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut between = vec![None; HEXES * HEXES];
+        for (from, rays) in rays.iter().enumerate() {
+            for ray in rays {
+                for (k, &to) in ray.iter().enumerate() {
+                    between[from * HEXES + to] = Some(ray[..k].to_vec());
+                }
+            }
+        }
+        let mut adjacent = vec![false; HEXES * HEXES];
+        for (from, targets) in king_moves.iter().enumerate() {
+            for &to in targets {
+                adjacent[from * HEXES + to] = true;
+            }
+        }
+        Self {
+            coords,
+            king_moves,
+            rays,
+            between,
+            adjacent,
+        }
+    }
+
+    fn adjacent(&self, a: usize, b: usize) -> bool {
+        self.adjacent[a * HEXES + b]
+    }
+
+    /// whether a queen on `queen` hits `target` with only `blocker` able to get
+    /// in the way
+    fn queen_attacks(&self, queen: usize, target: usize, blocker: usize) -> bool {
+        match &self.between[queen * HEXES + target] {
+            Some(between) => !between.contains(&blocker),
+            None => false,
+        }
+    }
+}
+
+/// a king and queen against a lone king, with white holding the queen
+#[derive(Clone, Copy)]
+struct Position {
+    king: usize,
+    queen: usize,
+    enemy: usize,
+    black_to_move: bool,
+}
+
+impl Position {
+    fn index(&self) -> usize {
+        ((self.king * HEXES + self.queen) * HEXES + self.enemy) * 2 + self.black_to_move as usize
+    }
+
+    fn from_index(i: usize) -> Self {
+        Self {
+            king: i / (2 * HEXES * HEXES),
+            queen: i / (2 * HEXES) % HEXES,
+            enemy: i / 2 % HEXES,
+            black_to_move: i % 2 == 1,
+        }
+    }
+
+    /// whether the position could come up in a game
+    fn legal(&self, geometry: &Geometry) -> bool {
+        self.king != self.queen
+            && self.king != self.enemy
+            && self.queen != self.enemy
+            && !geometry.adjacent(self.king, self.enemy)
+            // black can't be left in check on white's turn
+            && (self.black_to_move || !self.in_check(geometry))
+    }
+
+    fn in_check(&self, geometry: &Geometry) -> bool {
+        geometry.queen_attacks(self.queen, self.enemy, self.king)
+    }
+
+    fn key(&self, geometry: &Geometry) -> BoardKey {
+        let mut key = zobrist::piece(
+            geometry.coords[self.king],
+            Piece::new(Name::King, Team::White),
+        ) ^ zobrist::piece(
+            geometry.coords[self.queen],
+            Piece::new(Name::Queen, Team::White),
+        ) ^ zobrist::piece(
+            geometry.coords[self.enemy],
+            Piece::new(Name::King, Team::Black),
+        );
+        if self.black_to_move {
+            key ^= zobrist::BLACK_TO_MOVE;
+        }
+        BoardKey(key)
+    }
+
+    /// the number of moves black has, counting taking an undefended queen
+    fn black_moves(&self, geometry: &Geometry) -> u8 {
+        let mut moves = 0;
+        for &to in &geometry.king_moves[self.enemy] {
+            let legal = if to == self.queen {
+                !geometry.adjacent(self.king, to)
+            } else {
+                !geometry.adjacent(self.king, to)
+                    && !geometry.queen_attacks(self.queen, to, self.king)
+            };
+            moves += legal as u8;
+        }
+        moves
+    }
+}
+
+/// solve the king and queen against king endgame on glinski's board
+///
+/// the map goes from the `HexBoard::transposition_key` of a position with a
+/// white king and queen against the black king to the number of plies until
+/// black is checkmated with best play from both sides, so a position where
+/// black is already mated scores 0. positions missing from the map are draws -
+/// stalemates or those where black can take the queen - as stalemate is scored
+/// as a draw here
+///
+/// there are about 1.5 million positions to go through, which takes around a
+/// second and some tens of megabytes even without optimizations
+pub fn solve_kqk() -> HashMap<BoardKey, i32> {
+    let geometry = Geometry::new();
+    let positions = HEXES * HEXES * HEXES * 2;
+    let mut plies = vec![UNKNOWN; positions];
+    // black moves from each position not yet known to lose
+    let mut remaining = vec![0u8; positions];
+    let mut queue = VecDeque::new();
+
+    for i in 0..positions {
+        let position = Position::from_index(i);
+        if !position.black_to_move || !position.legal(&geometry) {
+            continue;
+        }
+        remaining[i] = position.black_moves(&geometry);
+        if remaining[i] == 0 && position.in_check(&geometry) {
+            plies[i] = 0;
+            queue.push_back(position);
+        }
+    }
+
+    while let Some(position) = queue.pop_front() {
+        let next = plies[position.index()] + 1;
+        if position.black_to_move {
+            // white has mated or is mating, so every white move that got here wins
+            for previous in white_unmoves(&geometry, position) {
+                if plies[previous.index()] == UNKNOWN {
+                    plies[previous.index()] = next;
+                    queue.push_back(previous);
+                }
+            }
+        } else {
+            // black loses once every move it has leads to a lost position
+            for previous in black_unmoves(&geometry, position) {
+                let i = previous.index();
+                if plies[i] == UNKNOWN {
+                    remaining[i] -= 1;
+                    if remaining[i] == 0 {
+                        plies[i] = next;
+                        queue.push_back(previous);
+                    }
+                }
+            }
+        }
+    }
+
+    plies
+        .iter()
+        .enumerate()
+        .filter(|&(_i, &plies)| plies != UNKNOWN)
+        .map(|(i, &plies)| (Position::from_index(i).key(&geometry), plies))
+        .collect()
+}
+
+/// the positions with white to move that lead to `position` in one move
+fn white_unmoves(geometry: &Geometry, position: Position) -> Vec<Position> {
+    let mut previous = Vec::new();
+    for ray in &geometry.rays[position.queen] {
+        for &from in ray {
+            if from == position.king || from == position.enemy {
+                break;
+            }
+            previous.push(Position {
+                queen: from,
+                black_to_move: false,
+                ..position
+            });
+        }
+    }
+    for &from in &geometry.king_moves[position.king] {
+        previous.push(Position {
+            king: from,
+            black_to_move: false,
+            ..position
+        });
+    }
+    previous.retain(|p| p.legal(geometry));
+    previous
+}
+
+/// the positions with black to move that lead to `position` in one move
+fn black_unmoves(geometry: &Geometry, position: Position) -> Vec<Position> {
+    geometry.king_moves[position.enemy]
+        .iter()
+        .map(|&from| Position {
+            enemy: from,
+            black_to_move: true,
+            ..position
+        })
+        .filter(|p| p.legal(geometry))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(king: (i32, i32), queen: (i32, i32), enemy: (i32, i32)) -> HexBoard {
+        HexBoard::from_pieces([
+            (king.into(), Piece::new(Name::King, Team::White)),
+            (queen.into(), Piece::new(Name::Queen, Team::White)),
+            (enemy.into(), Piece::new(Name::King, Team::Black)),
+        ])
+        .unwrap()
+    }
+
+    fn key(king: (i32, i32), queen: (i32, i32), enemy: (i32, i32), turn: Team) -> BoardKey {
+        board(king, queen, enemy).transposition_key(turn)
+    }
+
+    #[test]
+    fn kqk() {
+        let solved = solve_kqk();
+
+        // already mated
+        assert_eq!(
+            solved.get(&key((3, -3), (4, -4), (5, -5), Team::Black)),
+            Some(&0)
+        );
+        // mate in one, which the board agrees with
+        assert_eq!(
+            solved.get(&key((3, -3), (4, 0), (5, -5), Team::White)),
+            Some(&1)
+        );
+        let mut mated = board((3, -3), (4, 0), (5, -5));
+        mated.move_piece((4, 0).into(), (4, -4).into()).unwrap();
+        assert!(mated.is_in_check(Team::Black));
+        assert!(mated.all_legal_moves(Team::Black).is_empty());
+        // with the kings apart white still mates, after an odd number of plies
+        let plies = solved[&key((0, -5), (1, 0), (0, 5), Team::White)];
+        assert!(plies > 0 && plies % 2 == 1);
+
+        // a stalemate is a draw
+        let stalemate = key((3, -2), (2, -4), (5, -5), Team::Black);
+        assert!(!solved.contains_key(&stalemate));
+        // as is being able to take an undefended queen
+        assert!(!solved.contains_key(&key((0, -5), (4, -4), (5, -5), Team::Black)));
+    }
+}
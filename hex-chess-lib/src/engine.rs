@@ -0,0 +1,258 @@
+//! a small negamax search for picking moves
+//!
+//! the evaluation only counts material, so this is mostly useful as a
+//! sparring partner that won't hang pieces or miss a short mate
+
+use std::time::Duration;
+
+// std's clock panics in the browser
+#[cfg(target_arch = "wasm32")]
+use instant::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use crate::{board::HexBoard, moves::Move, piece::Team};
+
+const MATE: i32 = 1_000_000;
+
+/// the material balance in pawns from `team`'s point of view
+pub fn evaluate(board: &HexBoard, team: Team) -> i32 {
+    match team {
+        Team::White => board.material_balance(),
+        Team::Black => -board.material_balance(),
+    }
+}
+
+/// the deepest a search goes when it isn't given a depth
+pub const MAX_DEPTH: u32 = 64;
+
+/// how often, in nodes, the clock is looked at
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// when to give up searching - whichever limit is reached first ends the
+/// search, and a limit left as `None` doesn't apply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// how many plies ahead to look, up to `MAX_DEPTH`
+    pub depth: Option<u32>,
+    /// how long to think for
+    pub time: Option<Duration>,
+    /// how many positions to visit
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    /// search exactly `depth` plies ahead
+    pub fn depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    /// search as deep as possible within `time`
+    pub fn time(time: Duration) -> Self {
+        Self {
+            time: Some(time),
+            ..Self::default()
+        }
+    }
+}
+
+/// the best move for `team` looking `depth` plies ahead, or `None` if `team`
+/// has no legal moves
+pub fn best_move(board: &HexBoard, team: Team, depth: u32) -> Option<Move> {
+    search(board, team, SearchLimits::depth(depth.max(1)))
+}
+
+/// the best move for `team` found within `limits`, or `None` if `team` has no
+/// legal moves
+///
+/// the search deepens one ply at a time, so stopping early still gives the
+/// best move from the deepest search that finished. it always manages at
+/// least a move, even if a limit is hit before the first ply is done
+pub fn search(board: &HexBoard, team: Team, limits: SearchLimits) -> Option<Move> {
+    let mut moves: Vec<Move> = board
+        .all_legal_moves(team)
+        .into_iter()
+        .map(Move::from)
+        .collect();
+    let mut best = *moves.first()?;
+    let mut searcher = Searcher {
+        board: board.clone(),
+        limits,
+        start: Instant::now(),
+        nodes: 0,
+        stopped: false,
+    };
+    for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH).min(MAX_DEPTH) {
+        // look at the best move so far first, so it is kept if the search is
+        // cut short before anything better turns up
+        if let Some(i) = moves.iter().position(|&mv| mv == best) {
+            moves[..=i].rotate_right(1);
+        }
+        let found = searcher.root(&moves, team, depth);
+        if searcher.stopped {
+            // a move that beat the previous best is worth taking even from a
+            // partial search
+            if let Some((mv, _score)) = found {
+                best = mv;
+            }
+            break;
+        }
+        let (mv, score) = found.expect("a complete search has a move");
+        best = mv;
+        // nothing deeper will do better than a forced mate
+        if score.abs() >= MATE {
+            break;
+        }
+    }
+    Some(best)
+}
+
+struct Searcher {
+    board: HexBoard,
+    limits: SearchLimits,
+    start: Instant,
+    nodes: u64,
+    /// set once a limit has been hit, after which scores mean nothing
+    stopped: bool,
+}
+
+impl Searcher {
+    /// the best of `moves` with its score, searching `depth` plies ahead -
+    /// if the search is stopped partway this is the best of the moves it got
+    /// all the way through, if any
+    fn root(&mut self, moves: &[Move], team: Team, depth: u32) -> Option<(Move, i32)> {
+        let mut best = None;
+        let mut alpha = -MATE * 2;
+        for &mv in moves {
+            let undo = self.board.make(mv);
+            let score = -self.negamax(team.flip(), depth - 1, -MATE * 2, -alpha);
+            self.board.unmake(undo);
+            if self.stopped {
+                return best;
+            }
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some((mv, score));
+            }
+        }
+        best
+    }
+
+    fn negamax(&mut self, team: Team, depth: u32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.out_of_budget() {
+            self.stopped = true;
+            return 0;
+        }
+
+        let moves = self.board.all_legal_moves(team);
+        if moves.is_empty() {
+            // being mated with more depth left means it came sooner, which is worse
+            return if self.board.is_in_check(team) {
+                -MATE - depth as i32
+            } else {
+                0
+            };
+        }
+        if depth == 0 {
+            return evaluate(&self.board, team);
+        }
+
+        for (from, to) in moves {
+            let undo = self.board.make(Move::new(from, to));
+            let score = -self.negamax(team.flip(), depth - 1, -beta, -alpha);
+            self.board.unmake(undo);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    fn out_of_budget(&self) -> bool {
+        self.limits.nodes.is_some_and(|nodes| self.nodes > nodes)
+            || (self.nodes.is_multiple_of(TIME_CHECK_INTERVAL)
+                && self
+                    .limits
+                    .time
+                    .is_some_and(|time| self.start.elapsed() >= time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Game, GameResult, Name, Piece};
+
+    #[test]
+    fn takes_hanging_queen() {
+        let board = HexBoard::from_pieces([
+            ((0, -5).into(), Piece::new(Name::King, Team::White)),
+            ((-3, 0).into(), Piece::new(Name::Rook, Team::White)),
+            ((0, 5).into(), Piece::new(Name::King, Team::Black)),
+            ((2, 0).into(), Piece::new(Name::Queen, Team::Black)),
+        ])
+        .unwrap();
+        assert_eq!(evaluate(&board, Team::White), -4);
+        assert_eq!(
+            best_move(&board, Team::White, 2),
+            Some(Move::new((-3, 0).into(), (2, 0).into()))
+        );
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let mut game = Game::new();
+        game.move_piece((0, -3).into(), (2, -4).into()).unwrap();
+        game.move_piece((1, 4).into(), (2, 2).into()).unwrap();
+        game.move_piece((-1, -4).into(), (-4, 2).into()).unwrap();
+        game.move_piece((2, 2).into(), (3, 0).into()).unwrap();
+
+        let mv = best_move(&game.board, Team::White, 1).unwrap();
+        game.move_piece(mv.from, mv.to).unwrap();
+        assert_eq!(game.result(), GameResult::Checkmate(Team::White));
+    }
+
+    #[test]
+    fn stops_within_limits() {
+        let board = HexBoard::new_initialize();
+        let start = Instant::now();
+        let mv = search(
+            &board,
+            Team::White,
+            SearchLimits::time(Duration::from_millis(200)),
+        )
+        .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(board.can_move(mv.from, mv.to).is_ok());
+
+        // even a search cut off straight away has a move to give
+        let limits = SearchLimits {
+            nodes: Some(1),
+            ..SearchLimits::default()
+        };
+        let mv = search(&board, Team::White, limits).unwrap();
+        assert!(board.can_move(mv.from, mv.to).is_ok());
+    }
+
+    #[test]
+    fn deepens_to_a_mate() {
+        let mut game = Game::new();
+        game.move_piece((0, -3).into(), (2, -4).into()).unwrap();
+        game.move_piece((1, 4).into(), (2, 2).into()).unwrap();
+        game.move_piece((-1, -4).into(), (-4, 2).into()).unwrap();
+        game.move_piece((2, 2).into(), (3, 0).into()).unwrap();
+
+        let limits = SearchLimits::time(Duration::from_secs(10));
+        let mv = search(&game.board, Team::White, limits).unwrap();
+        game.move_piece(mv.from, mv.to).unwrap();
+        assert_eq!(game.result(), GameResult::Checkmate(Team::White));
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{
-    board::{GetError, HexBoard, MoveError},
+    board::{GetError, HexBoard, MoveError, MoveErrorType, PlacementError, Variant},
     coord::Coord,
-    piece::Team,
+    moves::Move,
+    notation::{parse_square, square_name, FenError, SanError},
+    piece::{Name, Piece, Team},
 };
-use std::fmt;
+use std::{collections::HashMap, fmt, time::Duration};
 
 #[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
 pub enum GameError {
@@ -11,41 +13,863 @@ pub enum GameError {
     PieceError(#[from] GetError),
     #[error("wrong turn - expected {real} but was given {given}")]
     TurnError { given: Team, real: Team },
+    #[error("in check - {0}")]
+    InCheck(MoveError),
+    #[error("moves into check - {0}")]
+    MovesIntoCheck(MoveError),
     #[error("{0}")]
-    MoveError(#[from] MoveError),
+    MoveError(MoveError),
+    #[error("{0}")]
+    SanError(#[from] SanError),
+    #[error("ply {ply}: {source}")]
+    TranscriptError { ply: usize, source: Box<GameError> },
+    #[error("the game is already over - {0}")]
+    GameOver(GameResult),
+    #[error("{0} has no draw offer to accept")]
+    NoDrawOffer(Team),
+    #[error("{0}")]
+    PlacementError(#[from] PlacementError),
+    #[error("{0} has no king")]
+    MissingKing(Team),
+    #[error("{0} is in check but it isn't their turn")]
+    WaitingInCheck(Team),
+    #[error("the pawn on {0} has no hex left ahead of it")]
+    PawnOnLastRank(Coord),
+    #[error("{0} isn't a pawn promoting to a queen, rook, bishop or knight")]
+    InvalidPromotion(Move),
+    #[error("{0}")]
+    FenError(#[from] FenError),
+}
+
+impl From<MoveError> for GameError {
+    fn from(e: MoveError) -> Self {
+        match e.err_type {
+            MoveErrorType::InCheck(_) => GameError::InCheck(e),
+            MoveErrorType::MovesIntoCheck(_) => GameError::MovesIntoCheck(e),
+            _ => GameError::MoveError(e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawReason {
+    /// 50 moves by each side without a pawn move or capture
+    FiftyMove,
+    /// the same position with the same player to move three times
+    Repetition,
+    /// both players agreed to a draw
+    Agreement,
+    /// neither player has the pieces left to checkmate
+    InsufficientMaterial,
+    /// the player to move has no legal move but isn't in check
+    Stalemate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameResult {
+    Ongoing,
+    /// won by checkmate by the given team
+    Checkmate(Team),
+    /// won by the given team after the other resigned
+    Resignation(Team),
+    /// won by the given team after the other ran out of time
+    Timeout(Team),
+    Draw(DrawReason),
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameResult::Ongoing => write!(f, "game in progress"),
+            GameResult::Checkmate(team) => write!(f, "{} wins by checkmate", team),
+            GameResult::Resignation(team) => write!(f, "{} wins by resignation", team),
+            GameResult::Timeout(team) => write!(f, "{} wins on time", team),
+            GameResult::Draw(DrawReason::FiftyMove) => write!(f, "draw by the fifty move rule"),
+            GameResult::Draw(DrawReason::Repetition) => write!(f, "draw by threefold repetition"),
+            GameResult::Draw(DrawReason::Agreement) => write!(f, "draw by agreement"),
+            GameResult::Draw(DrawReason::InsufficientMaterial) => {
+                write!(f, "draw by insufficient material")
+            }
+            GameResult::Draw(DrawReason::Stalemate) => write!(f, "draw by stalemate"),
+        }
+    }
+}
+
+/// what happened in a move played by `Game::move_piece`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveRecord {
+    pub mv: Move,
+    /// the piece that moved
+    pub piece: Piece,
+    /// the enemy piece taken by the move, if any
+    pub captured: Option<Piece>,
+    /// whether a pawn was taken en passant, from the hex behind `mv.to`
+    pub en_passant: bool,
+    /// whether the move put the other team in check
+    pub check: bool,
+    /// whether the move checkmated the other team
+    pub checkmate: bool,
+    /// whether the move left the other team with no legal move while not in
+    /// check
+    pub stalemate: bool,
+}
+
+impl MoveRecord {
+    /// the hex the captured piece stood on, if there was one
+    pub fn captured_at(&self) -> Option<Coord> {
+        self.captured.map(|_| {
+            if self.en_passant {
+                self.mv.to - HexBoard::pawn_forward(self.piece.team)
+            } else {
+                self.mv.to
+            }
+        })
+    }
 }
 
+/// how much time each team gets for the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeControl {
+    /// the time on each clock at the start
+    pub initial: Duration,
+    /// the time added to a team's clock after each of its moves
+    pub increment: Duration,
+}
+
+/// what is needed to take back a move
+#[derive(Debug, Clone)]
+struct Ply {
+    record: MoveRecord,
+    board: HexBoard,
+    halfmove_clock: u32,
+    /// the mover's clock before the move, without its increment
+    clock: Duration,
+}
+
+#[derive(Clone)]
 pub struct Game {
     pub turn: Team,
     pub board: HexBoard,
-    finished: bool,
+    /// set once the game is decided by checkmate, resignation, agreement or time
+    ended: Option<GameResult>,
+    /// the team with a draw offer standing
+    draw_offer: Option<Team>,
+    history: Vec<Ply>,
+    /// moves taken back by `undo` that `redo` can play again, the most
+    /// recently undone last
+    undone: Vec<Move>,
+    captured: [Vec<Piece>; 2],
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    /// how many times each position has been reached
+    repetitions: HashMap<u64, u32>,
+    time_control: Option<TimeControl>,
+    /// the time each team has left, if the game is timed
+    clocks: [Duration; 2],
 }
 
 impl Game {
     pub fn new() -> Self {
-        Self {
-            turn: Team::White,
-            board: HexBoard::new_initialize(),
-            finished: false,
+        Self::from_board(HexBoard::new_initialize(), Team::White)
+    }
+
+    /// a new game starting from the layout of `variant`
+    ///
+    /// every variant is played with glinski's board and pawn rules, so only
+    /// the layout changes. shafran's game isn't one of them, see `Variant`
+    pub fn new_variant(variant: Variant) -> Self {
+        Self::from_board(HexBoard::new_variant(variant), Team::White)
+    }
+
+    /// a new game where each team has to make its moves within `time_control`
+    pub fn with_time_control(time_control: TimeControl) -> Self {
+        let mut game = Self::new();
+        game.set_time_control(time_control);
+        game
+    }
+
+    pub(crate) fn set_time_control(&mut self, time_control: TimeControl) {
+        self.time_control = Some(time_control);
+        self.clocks = [time_control.initial; 2];
+    }
+
+    fn from_board(board: HexBoard, turn: Team) -> Self {
+        let mut game = Self {
+            turn,
+            board,
+            ended: None,
+            draw_offer: None,
+            history: Vec::new(),
+            undone: Vec::new(),
+            captured: Default::default(),
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            repetitions: HashMap::new(),
+            time_control: None,
+            clocks: Default::default(),
+        };
+        game.repetitions.insert(game.position_hash(), 1);
+        game
+    }
+
+    /// start a game from a custom position, checking that each team has a
+    /// king and the team that just moved isn't left in check
+    fn from_position(board: HexBoard, turn: Team) -> Result<Self, GameError> {
+        for team in [Team::White, Team::Black] {
+            if board.king_position(team).is_none() {
+                return Err(GameError::MissingKing(team));
+            }
         }
+        if board.is_in_check(turn.flip()) {
+            return Err(GameError::WaitingInCheck(turn.flip()));
+        }
+        let mut game = Game::from_board(board, turn);
+        if game.board.is_checkmated(game.turn) {
+            game.ended = Some(GameResult::Checkmate(game.turn.flip()));
+        } else if game.board.is_stalemated(game.turn) {
+            game.ended = Some(GameResult::Draw(DrawReason::Stalemate));
+        }
+        Ok(game)
+    }
+
+    /// the hash repetitions are counted with, which covers everything that
+    /// decides the moves available
+    fn position_hash(&self) -> u64 {
+        self.board.transposition_key(self.turn).0
     }
 
-    pub fn move_piece(&mut self, from: Coord, to: Coord) -> Result<(), GameError> {
-        let piece = self.board.get(from)?;
+    /// the piece at `from`, as long as it belongs to the team whose turn it is
+    fn own_piece(&self, from: Coord) -> Result<Piece, GameError> {
+        let piece = *self.board.get(from)?;
         if piece.team != self.turn {
             return Err(GameError::TurnError {
                 given: piece.team,
                 real: self.turn,
             });
         }
-        self.board.move_piece(from, to)?;
-        self.finished = self.board.is_checkmated(self.turn.flip());
+        Ok(piece)
+    }
+
+    /// the board as it would be after `mv`, without playing it
+    ///
+    /// fails with the same error `move_piece` would give
+    pub fn preview(&self, mv: Move) -> Result<HexBoard, GameError> {
+        self.ensure_ongoing()?;
+        self.own_piece(mv.from)?;
+        let mv = self.promotion_filled_in(mv)?;
+        let mut board = self.board.clone();
+        board.can_move(mv.from, mv.to)?;
+        board.make(mv);
+        Ok(board)
+    }
+
+    /// move the piece on `from` to `to`, with a pawn reaching the last rank
+    /// becoming a queen
+    pub fn move_piece(&mut self, from: Coord, to: Coord) -> Result<MoveRecord, GameError> {
+        self.play(Move::new(from, to))
+    }
+
+    /// move a pawn from `from` to the last rank on `to`, where it becomes
+    /// `name`
+    ///
+    /// fails with `InvalidPromotion` if the move doesn't promote or `name`
+    /// isn't something a pawn can become
+    pub fn move_piece_promoting(
+        &mut self,
+        from: Coord,
+        to: Coord,
+        name: Name,
+    ) -> Result<MoveRecord, GameError> {
+        let mv = Move::promoting(from, to, name);
+        if !Name::PROMOTIONS.contains(&name) || !self.promotes(mv) {
+            return Err(GameError::InvalidPromotion(mv));
+        }
+        self.play(mv)
+    }
+
+    /// play `mv`, with a promoting pawn becoming the piece it names or a
+    /// queen if it doesn't name one
+    pub fn make_move(&mut self, mv: Move) -> Result<MoveRecord, GameError> {
+        match mv.promotion {
+            Some(name) => self.move_piece_promoting(mv.from, mv.to, name),
+            None => self.play(mv),
+        }
+    }
+
+    /// whether `mv` takes a pawn to its last rank, legal or not
+    pub fn promotes(&self, mv: Move) -> bool {
+        match self.board.get(mv.from) {
+            Ok(piece) => piece.name == Name::Pawn && self.board.is_last_rank(mv.to, piece.team),
+            Err(_) => false,
+        }
+    }
+
+    /// `mv` with what a promoting pawn becomes, a queen unless it says
+    /// otherwise, and no promotion for any other move
+    ///
+    /// fails with `InvalidPromotion` if a pawn can't become what it names
+    fn promotion_filled_in(&self, mut mv: Move) -> Result<Move, GameError> {
+        if let Some(name) = mv.promotion {
+            if !Name::PROMOTIONS.contains(&name) {
+                return Err(GameError::InvalidPromotion(mv));
+            }
+        }
+        mv.promotion = self
+            .promotes(mv)
+            .then(|| mv.promotion.unwrap_or(Name::Queen));
+        Ok(mv)
+    }
+
+    /// play `mv`, recording what a promoting pawn became in the record's move
+    fn play(&mut self, mv: Move) -> Result<MoveRecord, GameError> {
+        let (from, to) = (mv.from, mv.to);
+        self.ensure_ongoing()?;
+        let piece = self.own_piece(from)?;
+        let mv = self.promotion_filled_in(mv)?;
+        let previous = self.board.clone();
+        self.board.can_move(from, to)?;
+        self.board.make(mv);
+        let captured_at = previous.capture_square(from, to);
+        let captured = captured_at.and_then(|c| previous.get(c).ok().copied());
+        if let Some(piece) = captured {
+            self.captured[self.turn as usize].push(piece);
+        }
+        let halfmove_clock = self.halfmove_clock;
+        let clock = self.clocks[self.turn as usize];
+
+        if piece.name == Name::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.turn == Team::Black {
+            self.fullmove_number += 1;
+        }
+        if let Some(time_control) = self.time_control {
+            self.clocks[self.turn as usize] += time_control.increment;
+        }
+        let checkmate = self.board.is_checkmated(self.turn.flip());
+        let stalemate = !checkmate && self.board.is_stalemated(self.turn.flip());
+        if checkmate {
+            self.ended = Some(GameResult::Checkmate(self.turn));
+        } else if stalemate {
+            self.ended = Some(GameResult::Draw(DrawReason::Stalemate));
+        }
+        // moving instead of accepting declines the opponent's offer
+        if self.draw_offer != Some(self.turn) {
+            self.draw_offer = None;
+        }
         self.turn = self.turn.flip();
+        *self.repetitions.entry(self.position_hash()).or_insert(0) += 1;
+        let record = MoveRecord {
+            mv,
+            piece,
+            captured,
+            en_passant: captured.is_some() && captured_at != Some(to),
+            check: self.board.is_in_check(self.turn),
+            checkmate,
+            stalemate,
+        };
+        self.history.push(Ply {
+            record,
+            board: previous,
+            halfmove_clock,
+            clock,
+        });
+        // a new move branches off, so what was undone can't be redone
+        self.undone.clear();
+        Ok(record)
+    }
+
+    /// a sentence describing the last move, like `White knight from (-2, -3, 5)
+    /// captures black pawn on (0, -1, 1), check.`, or `None` if nothing has been
+    /// played
+    pub fn describe_last_move(&self) -> Option<String> {
+        let record = self.history.last()?.record;
+        let MoveRecord {
+            mv,
+            piece,
+            captured,
+            en_passant,
+            check,
+            checkmate,
+            stalemate,
+        } = record;
+        let team = match piece.team {
+            Team::White => "White",
+            Team::Black => "Black",
+        };
+        let mut description = match (captured, record.captured_at()) {
+            (Some(taken), Some(at)) if en_passant => format!(
+                "{} {} from {} to {} captures {} {} on {} en passant",
+                team, piece.name, mv.from, mv.to, taken.team, taken.name, at
+            ),
+            (Some(taken), _) => format!(
+                "{} {} from {} captures {} {} on {}",
+                team, piece.name, mv.from, taken.team, taken.name, mv.to
+            ),
+            (None, _) => format!("{} {} from {} to {}", team, piece.name, mv.from, mv.to),
+        };
+        if let Some(name) = mv.promotion {
+            description.push_str(&format!(" and promotes to a {}", name));
+        }
+        if checkmate {
+            description.push_str(", checkmate");
+        } else if check {
+            description.push_str(", check");
+        } else if stalemate {
+            description.push_str(", stalemate");
+        }
+        description.push('.');
+        Some(description)
+    }
+
+    /// every legal move for the team whose turn it is along with its algebraic
+    /// notation, or nothing once the game is over
+    pub fn legal_moves_for_turn(&self) -> Vec<(Move, String)> {
+        if self.finished() {
+            return Vec::new();
+        }
+        self.board
+            .all_legal_moves(self.turn)
+            .into_iter()
+            .map(|mv| {
+                let mv = Move::from(mv);
+                (mv, mv.to_san(&self.board))
+            })
+            .collect()
+    }
+
+    /// take back the last move made, returning false if there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(ply) => {
+                let hash = self.position_hash();
+                if let Some(count) = self.repetitions.get_mut(&hash) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.repetitions.remove(&hash);
+                    }
+                }
+                self.board = ply.board;
+                self.turn = self.turn.flip();
+                if ply.record.captured.is_some() {
+                    self.captured[self.turn as usize].pop();
+                }
+                self.halfmove_clock = ply.halfmove_clock;
+                self.clocks[self.turn as usize] = ply.clock;
+                if self.turn == Team::Black {
+                    self.fullmove_number -= 1;
+                }
+                self.ended = None;
+                self.draw_offer = None;
+                self.undone.push(ply.record.mv);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// play the last move taken back by `undo` again, returning false if there
+    /// was nothing to redo or the game has since ended by resignation,
+    /// agreement or time
+    ///
+    /// playing any other move forgets what was undone
+    pub fn redo(&mut self) -> bool {
+        let mv = match self.undone.pop() {
+            Some(mv) => mv,
+            None => return false,
+        };
+        let mut undone = std::mem::take(&mut self.undone);
+        let replayed = self.play(mv).is_ok();
+        if !replayed {
+            undone.push(mv);
+        }
+        self.undone = undone;
+        replayed
+    }
+
+    /// the moves played so far, oldest first
+    pub fn history(&self) -> impl Iterator<Item = MoveRecord> + '_ {
+        self.history.iter().map(|ply| ply.record)
+    }
+
+    /// take `elapsed` off the clock of `team`, which loses on time if its
+    /// clock runs out
+    ///
+    /// does nothing if the game isn't timed or is already over
+    pub fn record_elapsed(&mut self, team: Team, elapsed: Duration) {
+        if self.time_control.is_none() || self.finished() {
+            return;
+        }
+        let clock = &mut self.clocks[team as usize];
+        *clock = clock.saturating_sub(elapsed);
+        if clock.is_zero() {
+            self.ended = Some(GameResult::Timeout(team.flip()));
+        }
+    }
+
+    /// run the clock of the team to move for `elapsed`, for a frontend to
+    /// call as time passes - each move then adds the increment to the clock
+    /// of the team that made it
+    pub fn tick(&mut self, elapsed: Duration) {
+        self.record_elapsed(self.turn, elapsed);
+    }
+
+    /// the clocks the game is played on, or `None` if it isn't timed
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.time_control
+    }
+
+    /// the time `team` has left, or `None` if the game isn't timed
+    pub fn remaining(&self, team: Team) -> Option<Duration> {
+        self.time_control.map(|_| self.clocks[team as usize])
+    }
+
+    /// the enemy pieces `team` has captured, in the order they were taken
+    pub fn captured(&self, team: Team) -> &[Piece] {
+        &self.captured[team as usize]
+    }
+
+    /// whether the king of `team` is attacked
+    pub fn in_check(&self, team: Team) -> bool {
+        self.board.is_in_check(team)
+    }
+
+    /// white's material minus black's, in pawns
+    pub fn material_balance(&self) -> i32 {
+        self.board.material_balance()
+    }
+
+    /// the number of halfmoves since the last pawn move or capture
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// the current move number, starting at 1 and incremented after black moves
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    fn ensure_ongoing(&self) -> Result<(), GameError> {
+        match self.result() {
+            GameResult::Ongoing => Ok(()),
+            result => Err(GameError::GameOver(result)),
+        }
+    }
+
+    /// concede the game, giving the win to the other team
+    pub fn resign(&mut self, team: Team) -> Result<(), GameError> {
+        self.ensure_ongoing()?;
+        self.ended = Some(GameResult::Resignation(team.flip()));
         Ok(())
     }
 
+    /// offer a draw that stands until the opponent accepts it or makes a move
+    pub fn offer_draw(&mut self, team: Team) -> Result<(), GameError> {
+        self.ensure_ongoing()?;
+        self.draw_offer = Some(team);
+        Ok(())
+    }
+
+    /// accept the opponent's standing draw offer, ending the game
+    pub fn accept_draw(&mut self, team: Team) -> Result<(), GameError> {
+        self.ensure_ongoing()?;
+        if self.draw_offer != Some(team.flip()) {
+            return Err(GameError::NoDrawOffer(team));
+        }
+        self.draw_offer = None;
+        self.ended = Some(GameResult::Draw(DrawReason::Agreement));
+        Ok(())
+    }
+
+    pub fn result(&self) -> GameResult {
+        if let Some(result) = self.ended {
+            result
+        } else if self.halfmove_clock >= 100 {
+            GameResult::Draw(DrawReason::FiftyMove)
+        } else if self.repetitions.values().any(|&count| count >= 3) {
+            GameResult::Draw(DrawReason::Repetition)
+        } else if self.board.insufficient_material() {
+            GameResult::Draw(DrawReason::InsufficientMaterial)
+        } else {
+            GameResult::Ongoing
+        }
+    }
+
     pub fn finished(&self) -> bool {
-        self.finished
+        self.result() != GameResult::Ongoing
+    }
+
+    /// the board at the start of the game and after each move since, ending
+    /// with the current board
+    pub fn replay(&self) -> impl Iterator<Item = HexBoard> + '_ {
+        self.history
+            .iter()
+            .map(|ply| ply.board.clone())
+            .chain(std::iter::once(self.board.clone()))
+    }
+
+    /// the moves played so far in algebraic notation, one entry per move
+    /// number like `1. Nc3 Ni6`, or `1... Ni6` if black moved first
+    pub fn move_list(&self) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        let mut number = 1;
+        for ply in &self.history {
+            let san = ply.record.mv.to_san(&ply.board);
+            match (ply.record.piece.team, lines.last_mut()) {
+                (Team::White, _) => lines.push(format!("{}. {}", number, san)),
+                (Team::Black, Some(line)) => {
+                    line.push(' ');
+                    line.push_str(&san);
+                }
+                (Team::Black, None) => lines.push(format!("{}... {}", number, san)),
+            }
+            if ply.record.piece.team == Team::Black {
+                number += 1;
+            }
+        }
+        lines
+    }
+
+    /// write out the moves played so far in algebraic notation, numbered like
+    /// `1. Nc3 Ni6 2. ...`
+    pub fn to_transcript(&self) -> String {
+        self.move_list().join(" ")
+    }
+
+    /// replay a transcript written by `to_transcript` from the starting
+    /// position
+    ///
+    /// an unreadable or illegal move gives a `TranscriptError` with the index
+    /// of the offending ply, counting from 0
+    pub fn from_transcript(s: &str) -> Result<Game, GameError> {
+        let mut game = Game::new();
+        let moves = s.split_whitespace().filter(|token| !token.ends_with('.'));
+        for (ply, san) in moves.enumerate() {
+            let error = |e: GameError| GameError::TranscriptError {
+                ply,
+                source: Box::new(e),
+            };
+            let mv = Move::from_san(san, &game.board, game.turn).map_err(|e| error(e.into()))?;
+            game.play(mv).map_err(error)?;
+        }
+        Ok(game)
+    }
+
+    /// save the position as fen - the board, `w` or `b` for whose turn it is,
+    /// the hex a pawn just skipped with a double step or `-`, then the halfmove
+    /// clock and fullmove number, e.g. `... b g5 0 1` after `g4 -> g6`
+    ///
+    /// the history, clocks and any draw offer aren't kept
+    pub fn to_fen(&self) -> String {
+        let turn = match self.turn {
+            Team::White => 'w',
+            Team::Black => 'b',
+        };
+        let en_passant = match self.board.en_passant() {
+            Some(c) => square_name(c, self.board.radius()),
+            None => "-".to_string(),
+        };
+        format!(
+            "{} {} {} {} {}",
+            self.board.to_fen(),
+            turn,
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    /// carry on a game saved by `to_fen`
+    pub fn from_fen(s: &str) -> Result<Game, GameError> {
+        let mut fields = s.split_whitespace();
+        let mut field = |name| fields.next().ok_or(FenError::MissingField(name));
+        let mut board = HexBoard::from_fen(field("board")?)?;
+        let turn = match field("turn")? {
+            "w" => Team::White,
+            "b" => Team::Black,
+            other => return Err(FenError::InvalidField("turn", other.to_string()).into()),
+        };
+        let en_passant = field("en passant")?;
+        if en_passant != "-" {
+            // the pawn that double stepped belongs to the team that just moved
+            let valid = parse_square(en_passant, board.radius())
+                .is_some_and(|c| board.set_en_passant(c, turn.flip()));
+            if !valid {
+                return Err(FenError::InvalidField("en passant", en_passant.to_string()).into());
+            }
+        }
+        let halfmove = field("halfmove clock")?;
+        let halfmove_clock = halfmove
+            .parse()
+            .map_err(|_| FenError::InvalidField("halfmove clock", halfmove.to_string()))?;
+        let fullmove = field("fullmove number")?;
+        let fullmove_number = fullmove
+            .parse()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| FenError::InvalidField("fullmove number", fullmove.to_string()))?;
+
+        let mut game = Game::from_position(board, turn)?;
+        game.halfmove_clock = halfmove_clock;
+        game.fullmove_number = fullmove_number;
+        Ok(game)
+    }
+}
+
+/// what a game is saved as - the position it started from and the moves
+/// since, which are replayed on loading to rebuild the rest
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GameData {
+    start: HexBoard,
+    first_turn: Team,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    moves: Vec<Move>,
+    undone: Vec<Move>,
+    ended: Option<GameResult>,
+    draw_offer: Option<Team>,
+    time_control: Option<TimeControl>,
+    clocks: [Duration; 2],
+    /// the mover's clock before each of `moves`, for undo to put back
+    #[serde(default)]
+    move_clocks: Vec<Duration>,
+}
+
+#[cfg(feature = "serde")]
+impl From<&Game> for GameData {
+    fn from(game: &Game) -> Self {
+        let (start, first_turn, halfmove_clock) = match game.history.first() {
+            Some(ply) => (ply.board.clone(), ply.record.piece.team, ply.halfmove_clock),
+            None => (game.board.clone(), game.turn, game.halfmove_clock),
+        };
+        let black_moves = game
+            .history
+            .iter()
+            .filter(|ply| ply.record.piece.team == Team::Black)
+            .count() as u32;
+        GameData {
+            start,
+            first_turn,
+            halfmove_clock,
+            fullmove_number: game.fullmove_number - black_moves,
+            moves: game.history().map(|record| record.mv).collect(),
+            undone: game.undone.clone(),
+            ended: game.ended,
+            draw_offer: game.draw_offer,
+            time_control: game.time_control,
+            clocks: game.clocks,
+            move_clocks: game.history.iter().map(|ply| ply.clock).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<GameData> for Game {
+    type Error = GameError;
+
+    fn try_from(data: GameData) -> Result<Self, Self::Error> {
+        let mut game = Game::from_position(data.start, data.first_turn)?;
+        game.halfmove_clock = data.halfmove_clock;
+        game.fullmove_number = data.fullmove_number;
+        for (ply, mv) in data.moves.into_iter().enumerate() {
+            game.play(mv).map_err(|e| GameError::TranscriptError {
+                ply,
+                source: Box::new(e),
+            })?;
+        }
+        game.undone = data.undone;
+        game.ended = data.ended;
+        game.draw_offer = data.draw_offer;
+        game.time_control = data.time_control;
+        game.clocks = data.clocks;
+        for (ply, clock) in game.history.iter_mut().zip(data.move_clocks) {
+            ply.clock = clock;
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Game {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GameData::from(self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Game {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = GameData::deserialize(deserializer)?;
+        Game::try_from(data).map_err(serde::de::Error::custom)
+    }
+}
+
+/// sets up a game from a custom position
+#[derive(Debug, Clone)]
+pub struct GameBuilder {
+    pieces: Vec<(Coord, Piece)>,
+    turn: Team,
+    time_control: Option<TimeControl>,
+}
+
+impl GameBuilder {
+    /// an empty board with white to move
+    pub fn new() -> Self {
+        Self {
+            pieces: Vec::new(),
+            turn: Team::White,
+            time_control: None,
+        }
+    }
+
+    /// put `piece` on `coord`
+    ///
+    /// pawns are treated as not having moved yet
+    pub fn place(mut self, coord: Coord, piece: Piece) -> Self {
+        self.pieces.push((coord, piece));
+        self
+    }
+
+    /// set the team to move first
+    pub fn turn(mut self, team: Team) -> Self {
+        self.turn = team;
+        self
+    }
+
+    /// give each team a clock
+    pub fn time_control(mut self, time_control: TimeControl) -> Self {
+        self.time_control = Some(time_control);
+        self
+    }
+
+    /// create the game, checking that the pieces fit on the board, each team
+    /// has exactly one king, no pawn is stuck on its last rank, and the team
+    /// that just moved isn't left in check
+    pub fn build(self) -> Result<Game, GameError> {
+        let board = HexBoard::from_pieces(self.pieces)?;
+        let stuck = board
+            .iter()
+            .find(|&(c, p)| p.name == Name::Pawn && board.is_last_rank(c, p.team));
+        if let Some((c, _pawn)) = stuck {
+            return Err(GameError::PawnOnLastRank(c));
+        }
+        let mut game = Game::from_position(board, self.turn)?;
+        if let Some(time_control) = self.time_control {
+            game.set_time_control(time_control);
+        }
+        Ok(game)
+    }
+}
+
+impl Default for GameBuilder {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -70,8 +894,9 @@ mod tests {
     fn moving_works() {
         let mut game = Game::new();
         assert_eq!(
-            game.move_piece((0, -1).into(), (0, 0).into()),
-            Ok(()),
+            game.move_piece((0, -1).into(), (0, 0).into())
+                .map(|record| record.mv),
+            Ok(Move::new((0, -1).into(), (0, 0).into())),
             "{}",
             game
         );
@@ -88,4 +913,799 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn moving_pinned_piece_is_moves_into_check() {
+        let mut game = Game::new();
+        game.board = HexBoard::new();
+        let rook = Piece::new(Name::Rook, Team::White);
+        game.board
+            .place((0, -4).into(), Piece::new(Name::King, Team::White));
+        game.board.place((0, -2).into(), rook);
+        game.board
+            .place((0, 2).into(), Piece::new(Name::Rook, Team::Black));
+
+        // stepping off the file exposes the king
+        assert_eq!(
+            game.move_piece((0, -2).into(), (2, -2).into()),
+            Err(GameError::MovesIntoCheck(MoveError {
+                err_type: MoveErrorType::MovesIntoCheck(rook),
+                from: (0, -2).into(),
+                to: (2, -2).into(),
+            }))
+        );
+
+        // moving along the pin is fine
+        assert!(game.move_piece((0, -2).into(), (0, 1).into()).is_ok());
+    }
+
+    #[test]
+    fn ignoring_check_is_in_check() {
+        let mut game = Game::new();
+        game.board = HexBoard::new();
+        let knight = Piece::new(Name::Knight, Team::White);
+        game.board
+            .place((0, -4).into(), Piece::new(Name::King, Team::White));
+        game.board.place((-3, 0).into(), knight);
+        game.board
+            .place((0, 2).into(), Piece::new(Name::Rook, Team::Black));
+
+        assert_eq!(
+            game.move_piece((-3, 0).into(), (-2, 2).into()),
+            Err(GameError::InCheck(MoveError {
+                err_type: MoveErrorType::InCheck(knight),
+                from: (-3, 0).into(),
+                to: (-2, 2).into(),
+            }))
+        );
+
+        // blocking resolves the check
+        assert!(game.move_piece((-3, 0).into(), (0, -1).into()).is_ok());
+    }
+
+    #[test]
+    fn invalid_move_is_still_move_error() {
+        let mut game = Game::new();
+        assert!(matches!(
+            game.move_piece((0, -1).into(), (0, 3).into()),
+            Err(GameError::MoveError(_))
+        ));
+    }
+
+    #[test]
+    fn move_record_has_capture() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        let record = game.move_piece((1, 1).into(), (1, 0).into()).unwrap();
+        assert_eq!(record.captured, None);
+        let record = game.move_piece((0, 0).into(), (1, 0).into()).unwrap();
+        assert_eq!(record.piece, Piece::new(Name::Pawn, Team::White));
+        assert_eq!(record.captured, Some(Piece::new(Name::Pawn, Team::Black)));
+        assert_eq!(record.captured_at(), Some((1, 0).into()));
+        assert!(!record.en_passant && !record.check && !record.checkmate);
+    }
+
+    #[test]
+    fn captures_are_tracked() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.move_piece((1, 1).into(), (1, 0).into()).unwrap();
+        assert!(game.captured(Team::White).is_empty());
+
+        game.move_piece((0, 0).into(), (1, 0).into()).unwrap();
+        assert_eq!(
+            game.captured(Team::White),
+            &[Piece::new(Name::Pawn, Team::Black)]
+        );
+        assert!(game.captured(Team::Black).is_empty());
+
+        game.undo();
+        assert!(game.captured(Team::White).is_empty());
+    }
+
+    // shuffle both queenside knights out and back again
+    fn shuffle_knights(game: &mut Game) {
+        game.move_piece((-2, -3).into(), (-3, 0).into()).unwrap();
+        game.move_piece((-2, 5).into(), (-3, 3).into()).unwrap();
+        game.move_piece((-3, 0).into(), (-2, -3).into()).unwrap();
+        game.move_piece((-3, 3).into(), (-2, 5).into()).unwrap();
+    }
+
+    #[test]
+    fn move_counters() {
+        let mut game = Game::new();
+        shuffle_knights(&mut game);
+        assert_eq!(game.halfmove_clock(), 4);
+        assert_eq!(game.fullmove_number(), 3);
+
+        // a pawn move resets the clock
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        assert_eq!(game.halfmove_clock(), 0);
+        assert_eq!(game.fullmove_number(), 3);
+
+        game.undo();
+        assert_eq!(game.halfmove_clock(), 4);
+        assert_eq!(game.fullmove_number(), 3);
+        game.undo();
+        assert_eq!(game.halfmove_clock(), 3);
+        assert_eq!(game.fullmove_number(), 2);
+    }
+
+    #[test]
+    fn fifty_move_draw() {
+        let mut game = Game::new();
+        // skip ahead rather than making 50 moves
+        game.halfmove_clock = 96;
+        game.move_piece((-2, -3).into(), (-3, 0).into()).unwrap();
+        game.move_piece((-2, 5).into(), (-3, 3).into()).unwrap();
+        game.move_piece((2, -5).into(), (3, -3).into()).unwrap();
+        assert_eq!(game.result(), GameResult::Ongoing);
+        game.move_piece((2, 3).into(), (3, 0).into()).unwrap();
+        assert_eq!(game.halfmove_clock(), 100);
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::FiftyMove));
+        assert!(game.finished());
+
+        game.undo();
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn threefold_repetition_draw() {
+        let mut game = Game::new();
+        shuffle_knights(&mut game);
+        assert_eq!(game.result(), GameResult::Ongoing);
+
+        // the starting position is reached for the third time
+        shuffle_knights(&mut game);
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::Repetition));
+
+        game.undo();
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn repetition_counts_en_passant() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.move_piece((1, 1).into(), (1, -1).into()).unwrap();
+        assert_eq!(game.board.en_passant(), Some((1, 0).into()));
+
+        // the same pieces come back twice more, but without the en passant
+        // capture the first time had, so it isn't a repetition
+        shuffle_knights(&mut game);
+        shuffle_knights(&mut game);
+        assert_eq!(game.board.en_passant(), None);
+        assert_eq!(game.result(), GameResult::Ongoing);
+
+        // while the position after the knight's first move has had the same
+        // moves available each time
+        game.move_piece((-2, -3).into(), (-3, 0).into()).unwrap();
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::Repetition));
+    }
+
+    #[test]
+    fn transcript_round_trip() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.move_piece((1, 1).into(), (1, 0).into()).unwrap();
+        game.move_piece((0, 0).into(), (1, 0).into()).unwrap();
+        game.move_piece((2, 3).into(), (3, 0).into()).unwrap();
+        game.move_piece((-2, -3).into(), (-3, 0).into()).unwrap();
+
+        let transcript = game.to_transcript();
+        assert_eq!(transcript, "1. f6 g6 2. fxg6 Ni6 3. Nc3");
+        assert_eq!(game.move_list(), ["1. f6 g6", "2. fxg6 Ni6", "3. Nc3"]);
+
+        let replayed = Game::from_transcript(&transcript).unwrap();
+        assert_eq!(replayed.board, game.board);
+        assert_eq!(replayed.turn, game.turn);
+        assert_eq!(replayed.captured(Team::White), game.captured(Team::White));
+
+        // a game can start with black to move
+        let start = HexBoard::new_initialize().to_fen();
+        let mut game = Game::from_fen(&format!("{} b - 0 1", start)).unwrap();
+        game.move_piece((1, 1).into(), (1, 0).into()).unwrap();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        assert_eq!(game.move_list(), ["1... g6", "2. f6"]);
+    }
+
+    #[test]
+    fn transcript_errors_give_ply() {
+        assert_eq!(
+            Game::from_transcript("1. f6 g6 2. Nf6").err(),
+            Some(GameError::TranscriptError {
+                ply: 2,
+                source: Box::new(SanError::NoMove("Nf6".to_string()).into()),
+            })
+        );
+        assert!(matches!(
+            Game::from_transcript("1. f6 ?").err(),
+            Some(GameError::TranscriptError { ply: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn undo_restores_position() {
+        let mut game = Game::new();
+        let start = game.board.to_string();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+
+        assert!(game.undo());
+        assert_eq!(game.turn, Team::White);
+        assert_eq!(game.board.to_string(), start);
+
+        // nothing left to undo
+        assert!(!game.undo());
+        assert_eq!(game.turn, Team::White);
+    }
+
+    #[test]
+    fn white_resigns() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.resign(Team::White).unwrap();
+        assert_eq!(game.result(), GameResult::Resignation(Team::Black));
+        assert!(game.finished());
+
+        assert_eq!(
+            game.move_piece((1, 1).into(), (1, 0).into()),
+            Err(GameError::GameOver(GameResult::Resignation(Team::Black)))
+        );
+        assert!(game.resign(Team::Black).is_err());
+    }
+
+    #[test]
+    fn agreed_draw() {
+        let mut game = Game::new();
+        assert_eq!(
+            game.accept_draw(Team::Black),
+            Err(GameError::NoDrawOffer(Team::Black))
+        );
+
+        // moving instead of accepting declines the offer
+        game.offer_draw(Team::White).unwrap();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.move_piece((1, 1).into(), (1, 0).into()).unwrap();
+        assert_eq!(
+            game.accept_draw(Team::Black),
+            Err(GameError::NoDrawOffer(Team::Black))
+        );
+
+        game.offer_draw(Team::White).unwrap();
+        assert!(game.accept_draw(Team::White).is_err());
+        game.accept_draw(Team::Black).unwrap();
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::Agreement));
+        assert!(matches!(
+            game.move_piece((0, 0).into(), (1, 0).into()),
+            Err(GameError::GameOver(_))
+        ));
+    }
+
+    #[test]
+    fn preview_leaves_game_unchanged() {
+        let mut game = Game::new();
+        let start = game.board.to_string();
+
+        let mv = Move::new((0, -1).into(), (0, 0).into());
+        let previewed = game.preview(mv).unwrap();
+        assert_eq!(game.board.to_string(), start);
+        assert_eq!(game.turn, Team::White);
+        assert_eq!(game.to_transcript(), "");
+
+        game.move_piece(mv.from, mv.to).unwrap();
+        assert_eq!(previewed, game.board);
+    }
+
+    #[test]
+    fn illegal_preview_matches_move_piece() {
+        let mut game = Game::new();
+        for mv in [
+            Move::new((0, 1).into(), (0, 0).into()),
+            Move::new((0, -1).into(), (0, 2).into()),
+            Move::new((0, 0).into(), (0, 1).into()),
+        ] {
+            let err = game.preview(mv).unwrap_err();
+            assert_eq!(game.move_piece(mv.from, mv.to), Err(err));
+        }
+    }
+
+    #[test]
+    fn builds_queen_mate() {
+        let game = GameBuilder::new()
+            .place((3, -3).into(), Piece::new(Name::King, Team::White))
+            .place((4, -4).into(), Piece::new(Name::Queen, Team::White))
+            .place((5, -5).into(), Piece::new(Name::King, Team::Black))
+            .turn(Team::Black)
+            .build()
+            .unwrap();
+        assert_eq!(game.turn, Team::Black);
+        assert!(game.board.is_in_check(Team::Black));
+        assert_eq!(game.result(), GameResult::Checkmate(Team::White));
+    }
+
+    #[test]
+    fn promotion() {
+        let mut game = GameBuilder::new()
+            .place((1, -5).into(), Piece::new(Name::King, Team::White))
+            .place((4, 0).into(), Piece::new(Name::King, Team::Black))
+            .place((0, 4).into(), Piece::new(Name::Pawn, Team::White))
+            .place((-1, 5).into(), Piece::new(Name::Rook, Team::Black))
+            .build()
+            .unwrap();
+        let (from, to) = ((0, 4).into(), (0, 5).into());
+        assert_eq!(
+            game.move_piece_promoting(from, to, Name::King),
+            Err(GameError::InvalidPromotion(Move::promoting(
+                from,
+                to,
+                Name::King
+            )))
+        );
+        // every way of playing a move checks what the pawn becomes
+        let king = Move::promoting(from, to, Name::King);
+        assert_eq!(game.preview(king), Err(GameError::InvalidPromotion(king)));
+        let pawn = Move::promoting(from, to, Name::Pawn);
+        assert_eq!(game.make_move(pawn), Err(GameError::InvalidPromotion(pawn)));
+        let sideways = Move::promoting((1, -5).into(), (1, -4).into(), Name::Rook);
+        assert_eq!(
+            game.move_piece_promoting(sideways.from, sideways.to, Name::Rook),
+            Err(GameError::InvalidPromotion(sideways))
+        );
+
+        let record = game.move_piece_promoting(from, to, Name::Rook).unwrap();
+        assert_eq!(record.mv, Move::promoting(from, to, Name::Rook));
+        assert_eq!(record.piece, Piece::new(Name::Pawn, Team::White));
+        assert_eq!(game.board.get(to), Ok(&Piece::new(Name::Rook, Team::White)));
+        assert!(game
+            .describe_last_move()
+            .unwrap()
+            .ends_with("and promotes to a rook."));
+        assert!(game.move_list()[0].starts_with("1. f11=R"));
+
+        assert!(game.undo());
+        assert_eq!(
+            game.board.get(from),
+            Ok(&Piece::new(Name::Pawn, Team::White))
+        );
+        assert!(game.redo());
+        assert_eq!(game.board.get(to), Ok(&Piece::new(Name::Rook, Team::White)));
+
+        // capturing onto the last rank promotes too, to a queen by default
+        assert!(game.undo());
+        let record = game.move_piece(from, (-1, 5).into()).unwrap();
+        assert_eq!(record.mv.promotion, Some(Name::Queen));
+        assert_eq!(
+            game.board.get((-1, 5).into()),
+            Ok(&Piece::new(Name::Queen, Team::White))
+        );
+    }
+
+    #[test]
+    fn builder_rejects_bad_positions() {
+        let white_king = Piece::new(Name::King, Team::White);
+        let black_king = Piece::new(Name::King, Team::Black);
+        assert_eq!(
+            GameBuilder::new()
+                .place((0, -5).into(), white_king)
+                .build()
+                .err(),
+            Some(GameError::MissingKing(Team::Black))
+        );
+        assert_eq!(
+            GameBuilder::new()
+                .place((0, -5).into(), white_king)
+                .place((0, 5).into(), black_king)
+                .place((0, 7).into(), black_king)
+                .build()
+                .err(),
+            Some(GameError::PlacementError(PlacementError::OutOfBounds(
+                (0, 7).into()
+            )))
+        );
+        // black can't be left in check with white to move
+        assert_eq!(
+            GameBuilder::new()
+                .place((0, -5).into(), white_king)
+                .place((0, 5).into(), black_king)
+                .place((0, 0).into(), Piece::new(Name::Rook, Team::White))
+                .build()
+                .err(),
+            Some(GameError::WaitingInCheck(Team::Black))
+        );
+        // pawns can't start where they have nowhere to go
+        for (c, team) in [((2, 3), Team::White), ((-2, -3), Team::Black)] {
+            assert_eq!(
+                GameBuilder::new()
+                    .place((0, -5).into(), white_king)
+                    .place((0, 5).into(), black_king)
+                    .place(c.into(), Piece::new(Name::Pawn, team))
+                    .build()
+                    .err(),
+                Some(GameError::PawnOnLastRank(c.into()))
+            );
+        }
+        // but a black pawn on white's last rank is fine
+        assert!(GameBuilder::new()
+            .place((0, -5).into(), white_king)
+            .place((0, 5).into(), black_king)
+            .place((2, 3).into(), Piece::new(Name::Pawn, Team::Black))
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn opening_moves_with_san() {
+        let mut game = Game::new();
+        let moves = game.legal_moves_for_turn();
+        assert_eq!(moves.len(), game.board.all_legal_moves(Team::White).len());
+        assert!(moves.contains(&(
+            Move::new((-2, -3).into(), (-3, 0).into()),
+            "Nc3".to_string()
+        )));
+
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        let moves = game.legal_moves_for_turn();
+        assert!(moves.contains(&(Move::new((2, 3).into(), (3, 0).into()), "Ni6".to_string())));
+    }
+
+    #[test]
+    fn describes_capturing_check() {
+        let mut game = GameBuilder::new()
+            .place((0, -5).into(), Piece::new(Name::King, Team::White))
+            .place((-2, -3).into(), Piece::new(Name::Knight, Team::White))
+            .place((-3, 0).into(), Piece::new(Name::Pawn, Team::Black))
+            .place((-2, 2).into(), Piece::new(Name::King, Team::Black))
+            .build()
+            .unwrap();
+        assert_eq!(game.describe_last_move(), None);
+        game.move_piece((-2, -3).into(), (-3, 0).into()).unwrap();
+        let description = game.describe_last_move().unwrap();
+        assert!(description.starts_with("White knight"), "{}", description);
+        assert!(
+            description.contains("captures black pawn"),
+            "{}",
+            description
+        );
+        assert!(description.ends_with(", check."), "{}", description);
+    }
+
+    #[test]
+    fn flag_fall() {
+        let mut game = Game::with_time_control(TimeControl {
+            initial: Duration::from_secs(60),
+            increment: Duration::from_secs(2),
+        });
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.record_elapsed(Team::White, Duration::from_secs(10));
+        assert_eq!(game.remaining(Team::White), Some(Duration::from_secs(52)));
+        assert_eq!(game.result(), GameResult::Ongoing);
+
+        game.record_elapsed(Team::Black, Duration::from_secs(61));
+        assert_eq!(game.remaining(Team::Black), Some(Duration::ZERO));
+        assert_eq!(game.result(), GameResult::Timeout(Team::White));
+        assert!(game.move_piece((1, 1).into(), (1, 0).into()).is_err());
+    }
+
+    #[test]
+    fn running_clock() {
+        let mut game = Game::with_time_control(TimeControl {
+            initial: Duration::from_secs(60),
+            increment: Duration::from_secs(3),
+        });
+        game.tick(Duration::from_secs(5));
+        assert_eq!(game.remaining(Team::White), Some(Duration::from_secs(55)));
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        assert_eq!(game.remaining(Team::White), Some(Duration::from_secs(58)));
+
+        // now it's black's clock that runs
+        game.tick(Duration::from_secs(20));
+        assert_eq!(game.remaining(Team::White), Some(Duration::from_secs(58)));
+        assert_eq!(game.remaining(Team::Black), Some(Duration::from_secs(40)));
+        game.tick(Duration::from_secs(40));
+        assert_eq!(game.result(), GameResult::Timeout(Team::White));
+        game.tick(Duration::from_secs(1));
+        assert_eq!(game.remaining(Team::White), Some(Duration::from_secs(58)));
+    }
+
+    #[test]
+    fn undo_takes_back_the_increment() {
+        let mut game = Game::with_time_control(TimeControl {
+            initial: Duration::from_secs(60),
+            increment: Duration::from_secs(5),
+        });
+        game.tick(Duration::from_secs(4));
+        for _ in 0..10 {
+            game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+            assert!(game.undo());
+        }
+        assert_eq!(game.remaining(Team::White), Some(Duration::from_secs(56)));
+
+        assert!(game.redo());
+        assert_eq!(game.remaining(Team::White), Some(Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn untimed_game_has_no_clock() {
+        let mut game = Game::new();
+        game.record_elapsed(Team::White, Duration::from_secs(1_000_000));
+        assert_eq!(game.remaining(Team::White), None);
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn replay_ends_on_current_board() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.move_piece((1, 1).into(), (1, 0).into()).unwrap();
+        game.move_piece((0, 0).into(), (1, 0).into()).unwrap();
+
+        let boards: Vec<HexBoard> = game.replay().collect();
+        assert_eq!(boards.len(), 4);
+        assert_eq!(boards[0], HexBoard::new_initialize());
+        assert_eq!(boards.last(), Some(&game.board));
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        let mut game = Game::new();
+        for (from, to) in [((-1, -1), (-1, 0)), ((1, 1), (1, 0)), ((-2, -3), (-1, -1))] {
+            game.move_piece(from.into(), to.into()).unwrap();
+        }
+        let fen = game.to_fen();
+        assert!(fen.ends_with(" b - 1 2"));
+
+        let restored = Game::from_fen(&fen).unwrap();
+        assert_eq!(restored.turn, Team::Black);
+        assert_eq!(restored.board, game.board);
+        assert_eq!(restored.halfmove_clock(), 1);
+        assert_eq!(restored.fullmove_number(), 2);
+        assert_eq!(restored.to_fen(), fen);
+    }
+
+    #[test]
+    fn bad_fen() {
+        let empty = HexBoard::new().to_fen();
+        assert_eq!(
+            Game::from_fen(&format!("{} w - 0 1", empty)).err(),
+            Some(GameError::MissingKing(Team::White))
+        );
+        let start = HexBoard::new_initialize().to_fen();
+        assert_eq!(
+            Game::from_fen(&format!("{} x - 0 1", start)).err(),
+            Some(FenError::InvalidField("turn", "x".to_string()).into())
+        );
+        assert_eq!(
+            Game::from_fen(&format!("{} w - 0", start)).err(),
+            Some(FenError::MissingField("fullmove number").into())
+        );
+    }
+
+    #[test]
+    fn stalemate_ends_the_game() {
+        let mut game = GameBuilder::new()
+            .place((3, -2).into(), Piece::new(Name::King, Team::White))
+            .place((2, -3).into(), Piece::new(Name::Queen, Team::White))
+            .place((5, -5).into(), Piece::new(Name::King, Team::Black))
+            .build()
+            .unwrap();
+        let record = game.move_piece((2, -3).into(), (2, -4).into()).unwrap();
+        assert!(!record.check && !record.checkmate && record.stalemate);
+        assert!(!game.in_check(Team::Black));
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::Stalemate));
+        assert_eq!(game.result().to_string(), "draw by stalemate");
+        assert!(game.move_piece((5, -5).into(), (4, -4).into()).is_err());
+
+        game.undo();
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn reports_check() {
+        let mut game = GameBuilder::new()
+            .place((0, -5).into(), Piece::new(Name::King, Team::White))
+            .place((0, 5).into(), Piece::new(Name::King, Team::Black))
+            .place((3, -2).into(), Piece::new(Name::Rook, Team::White))
+            .build()
+            .unwrap();
+        assert!(!game.in_check(Team::Black));
+        let record = game.move_piece((3, -2).into(), (0, 1).into()).unwrap();
+        assert!(record.check && !record.checkmate && !record.stalemate);
+        assert!(game.in_check(Team::Black) && !game.in_check(Team::White));
+        assert!(game.describe_last_move().unwrap().ends_with(", check."));
+    }
+
+    #[test]
+    fn records_en_passant_capture() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.move_piece((1, 1).into(), (1, -1).into()).unwrap();
+        let record = game.move_piece((0, 0).into(), (1, 0).into()).unwrap();
+        assert_eq!(record.captured, Some(Piece::new(Name::Pawn, Team::Black)));
+        assert!(game.board.is_empty((1, -1).into()));
+        assert_eq!(game.halfmove_clock(), 0);
+    }
+
+    #[test]
+    fn redo_replays_undone_moves() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.move_piece((1, 1).into(), (1, 0).into()).unwrap();
+        game.move_piece((0, 0).into(), (1, 0).into()).unwrap();
+        let history: Vec<MoveRecord> = game.history().collect();
+        assert_eq!(history.len(), 3);
+        assert_eq!(
+            history[2].captured,
+            Some(Piece::new(Name::Pawn, Team::Black))
+        );
+        let end = game.board.clone();
+
+        assert!(game.undo());
+        assert!(game.undo());
+        assert_eq!(game.history().count(), 1);
+        assert!(game.redo());
+        assert!(game.redo());
+        assert!(!game.redo());
+        assert_eq!(game.board, end);
+        assert_eq!(game.turn, Team::Black);
+        assert_eq!(game.history().collect::<Vec<_>>(), history);
+        assert_eq!(game.captured(Team::White).len(), 1);
+
+        // a different move drops the rest of the undone moves
+        assert!(game.undo());
+        assert!(game.undo());
+        game.move_piece((-1, 2).into(), (-1, 1).into()).unwrap();
+        assert!(!game.redo());
+    }
+
+    #[test]
+    fn moves_off_the_board() {
+        let mut game = Game::new();
+        let far = Coord::new(i32::MIN, 0);
+        assert!(game.make_move(Move::new(far, (0, 0).into())).is_err());
+        assert!(game.make_move(Move::new((0, -1).into(), far)).is_err());
+        assert!(game.preview(Move::new(far, far)).is_err());
+    }
+
+    #[test]
+    fn redo_after_game_ends() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        assert!(game.undo());
+        game.resign(Team::White).unwrap();
+        assert!(!game.redo());
+        assert_eq!(game.result(), GameResult::Resignation(Team::Black));
+
+        // and the same when a flag falls
+        let mut game = Game::with_time_control(TimeControl {
+            initial: Duration::from_secs(60),
+            increment: Duration::ZERO,
+        });
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        assert!(game.undo());
+        game.tick(Duration::from_secs(60));
+        assert!(!game.redo());
+        assert_eq!(game.result(), GameResult::Timeout(Team::Black));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut game = Game::with_time_control(TimeControl {
+            initial: Duration::from_secs(300),
+            increment: Duration::from_secs(2),
+        });
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.record_elapsed(Team::White, Duration::from_secs(10));
+        game.move_piece((1, 1).into(), (1, 0).into()).unwrap();
+        game.move_piece((0, 0).into(), (1, 0).into()).unwrap();
+        game.move_piece((2, 1).into(), (2, 0).into()).unwrap();
+        game.undo();
+        game.offer_draw(Team::White).unwrap();
+
+        let json = serde_json::to_string(&game).unwrap();
+        let mut read: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.turn, Team::Black);
+        assert_eq!(read.board, game.board);
+        assert_eq!(
+            read.history().collect::<Vec<_>>(),
+            game.history().collect::<Vec<_>>()
+        );
+        assert_eq!(read.captured(Team::White), game.captured(Team::White));
+        assert_eq!(read.remaining(Team::White), game.remaining(Team::White));
+        assert_eq!(read.to_fen(), game.to_fen());
+        let mut taken_back: Game = serde_json::from_str(&json).unwrap();
+        assert!(taken_back.undo() && taken_back.undo());
+        assert_eq!(
+            taken_back.remaining(Team::White),
+            Some(Duration::from_secs(292))
+        );
+        // a saved move can't promote to something a pawn can't become
+        let mut promoting = GameBuilder::new()
+            .place((1, -5).into(), Piece::new(Name::King, Team::White))
+            .place((4, 0).into(), Piece::new(Name::King, Team::Black))
+            .place((0, 4).into(), Piece::new(Name::Pawn, Team::White))
+            .build()
+            .unwrap();
+        promoting
+            .move_piece_promoting((0, 4).into(), (0, 5).into(), Name::Rook)
+            .unwrap();
+        let saved = serde_json::to_string(&promoting).unwrap();
+        assert!(serde_json::from_str::<Game>(&saved).is_ok());
+        assert_eq!(saved.matches("\"Rook\"").count(), 1);
+        let tampered = saved.replace("\"Rook\"", "\"King\"");
+        assert!(serde_json::from_str::<Game>(&tampered).is_err());
+
+        read.accept_draw(Team::Black).unwrap();
+        assert!(read.undo() && read.redo() && read.redo());
+
+        // a game started from its own position keeps its counters
+        let game =
+            Game::from_fen(&format!("{} b - 7 12", HexBoard::new_initialize().to_fen())).unwrap();
+        let read: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+        assert_eq!(read.to_fen(), game.to_fen());
+    }
+
+    #[test]
+    fn fen_en_passant() {
+        let mut game = Game::new();
+        game.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        game.move_piece((1, 1).into(), (1, -1).into()).unwrap();
+        let fen = game.to_fen();
+        assert!(fen.ends_with(" w g6 0 2"), "{}", fen);
+
+        // the capture is still there after loading
+        let mut restored = Game::from_fen(&fen).unwrap();
+        assert_eq!(restored.board, game.board);
+        let record = restored.move_piece((0, 0).into(), (1, 0).into()).unwrap();
+        assert_eq!(record.captured, Some(Piece::new(Name::Pawn, Team::Black)));
+        assert!(record.en_passant);
+        assert_eq!(record.captured_at(), Some((1, -1).into()));
+        assert_eq!(
+            restored.describe_last_move().unwrap(),
+            "White pawn from (0, 0, 0) to (1, 0, -1) captures black pawn on (1, -1, 0) en passant."
+        );
+
+        // with no pawn past the hex there's nothing to take
+        let start = HexBoard::new_initialize().to_fen();
+        assert_eq!(
+            Game::from_fen(&format!("{} w f6 0 1", start)).err(),
+            Some(FenError::InvalidField("en passant", "f6".to_string()).into())
+        );
+    }
+
+    #[test]
+    fn insufficient_material_draw() {
+        let mut game = GameBuilder::new()
+            .place((0, -5).into(), Piece::new(Name::King, Team::White))
+            .place((0, 0).into(), Piece::new(Name::Rook, Team::White))
+            .place((1, 0).into(), Piece::new(Name::Knight, Team::Black))
+            .place((4, 1).into(), Piece::new(Name::King, Team::Black))
+            .build()
+            .unwrap();
+        assert_eq!(game.result(), GameResult::Ongoing);
+        game.move_piece((0, 0).into(), (0, -2).into()).unwrap();
+        // the knight takes the rook, leaving only itself with the kings
+        game.move_piece((1, 0).into(), (0, -2).into()).unwrap();
+        assert_eq!(
+            game.result(),
+            GameResult::Draw(DrawReason::InsufficientMaterial)
+        );
+        assert_eq!(game.result().to_string(), "draw by insufficient material");
+
+        game.undo();
+        assert_eq!(game.result(), GameResult::Ongoing);
+    }
+
+    #[test]
+    fn mccooey_game() {
+        let mut game = Game::new_variant(Variant::McCooey);
+        assert_eq!(game.board, HexBoard::new_variant(Variant::McCooey));
+        assert_eq!(game.turn, Team::White);
+        // the centre pawn double steps from its own starting hex
+        game.move_piece((0, -2).into(), (0, 0).into()).unwrap();
+        assert_eq!(game.turn, Team::Black);
+        // and black's is its reflection, which is now blocked
+        assert!(game.move_piece((0, 2).into(), (0, 0).into()).is_err());
+        game.move_piece((0, 2).into(), (0, 1).into()).unwrap();
+    }
 }
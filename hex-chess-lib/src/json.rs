@@ -0,0 +1,90 @@
+//! a line based json protocol for driving a game from scripts and other
+//! programs, turned on with `--json`
+//!
+//! every line of input is one request and gets exactly one line of output.
+//! coordinates are `[q, r]` arrays
+//!
+//! requests:
+//! - `{"move":{"from":[0,-1],"to":[0,0]}}` plays a move for the team whose
+//!   turn it is
+//! - `{"legal":{"from":[0,-1]}}` asks where the piece on `from` can move
+//!
+//! responses:
+//! - `{"state":{...}}` after a move, holding the `turn`, whether that team is
+//!   `in_check`, the `result` of the game and the `pieces` on the board, each
+//!   as `{"at":[q,r],"piece":"P"}` with white pieces in uppercase and black in
+//!   lowercase
+//! - `{"legal":[[0,0],[0,1]]}` with the hexes the piece can move to
+//! - `{"error":"..."}` if the request couldn't be read or carried out
+
+use std::io::{self, BufRead, Write};
+
+use hex_chess_lib::{Coord, Game};
+use serde_json::{json, Value};
+
+pub fn run(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut game = Game::new();
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = respond(&mut game, &line).unwrap_or_else(|e| json!({ "error": e }));
+        writeln!(output, "{}", response)?;
+    }
+    Ok(())
+}
+
+fn respond(game: &mut Game, line: &str) -> Result<Value, String> {
+    let request: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    if let Some(mv) = request.get("move") {
+        let from = field(mv, "from")?;
+        let to = field(mv, "to")?;
+        game.move_piece(from, to).map_err(|e| e.to_string())?;
+        Ok(json!({ "state": state(game) }))
+    } else if let Some(legal) = request.get("legal") {
+        let from = field(legal, "from")?;
+        game.board.get(from).map_err(|e| e.to_string())?;
+        let moves: Vec<Value> = game
+            .board
+            .legal_moves(from)
+            .into_iter()
+            .map(coord_to_json)
+            .collect();
+        Ok(json!({ "legal": moves }))
+    } else {
+        Err("expected a `move` or `legal` request".to_string())
+    }
+}
+
+/// read the `[q, r]` coordinate in `name`
+fn field(value: &Value, name: &str) -> Result<Coord, String> {
+    let (q, r) = value
+        .get(name)
+        .and_then(Value::as_array)
+        .filter(|pair| pair.len() == 2)
+        .and_then(|pair| Some((pair[0].as_i64()?, pair[1].as_i64()?)))
+        .ok_or_else(|| format!("expected `{}` to be a [q, r] pair", name))?;
+    match (i32::try_from(q), i32::try_from(r)) {
+        (Ok(q), Ok(r)) => Ok(Coord::new(q, r)),
+        _ => Err(format!("`{}` is too far off the board", name)),
+    }
+}
+
+fn coord_to_json(c: Coord) -> Value {
+    json!([c.q, c.r])
+}
+
+fn state(game: &Game) -> Value {
+    let pieces: Vec<Value> = game
+        .board
+        .iter()
+        .map(|(c, piece)| json!({ "at": coord_to_json(c), "piece": piece.fen_char().to_string() }))
+        .collect();
+    json!({
+        "turn": game.turn.to_string(),
+        "in_check": game.in_check(game.turn),
+        "result": game.result().to_string(),
+        "pieces": pieces,
+    })
+}
@@ -1,10 +1,42 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
 mod game;
+#[cfg(feature = "std")]
+mod svg;
+#[cfg(feature = "std")]
+mod tables;
+#[cfg(feature = "std")]
+mod term;
+#[cfg(feature = "std")]
+mod zobrist;
 
+#[cfg(feature = "std")]
 pub mod board;
 pub mod coord;
+#[cfg(feature = "std")]
+pub mod endgame;
+#[cfg(feature = "std")]
+pub mod engine;
+#[cfg(feature = "std")]
+pub mod moves;
+#[cfg(feature = "std")]
+pub mod net;
+#[cfg(feature = "std")]
+pub mod notation;
+#[cfg(feature = "std")]
 pub mod piece;
+#[cfg(feature = "ws")]
+pub mod ws;
 
-pub use board::HexBoard;
-pub use coord::Coord;
-pub use game::Game;
+#[cfg(feature = "std")]
+pub use board::{BoardKey, HexBoard, Variant};
+pub use coord::{Coord, CoordError, Delta, Dir, ParseCoordError};
+#[cfg(feature = "std")]
+pub use game::{DrawReason, Game, GameBuilder, GameResult, MoveRecord, TimeControl};
+#[cfg(feature = "std")]
+pub use moves::Move;
+#[cfg(feature = "std")]
 pub use piece::*;
+#[cfg(feature = "std")]
+pub use term::TermRenderer;
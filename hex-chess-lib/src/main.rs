@@ -1,51 +1,353 @@
-use std::io::{self, BufRead, Write};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    process,
+};
 
-use hex_chess_lib::Game;
+use hex_chess_lib::{
+    engine,
+    notation::{self, square_name, ParseError},
+    Coord, Game, Move, Team, TermRenderer,
+};
+
+mod json;
+
+const USAGE: &str = "usage: hex-chess-lib [play]
+       hex-chess-lib analyze <fen> [--depth N]
+       hex-chess-lib selfplay [--depth N]
+       hex-chess-lib --json";
+
+const HELP: &str = "commands:
+  <move> or move <move>   play a move like `Nc3`, `f5 -> f6` or `0, -1 -> 0, 0`
+  undo, redo              step back or forward through the moves played
+  show                    print the board again
+  legal <hex>             list the moves for the piece on a hex
+  resign                  concede the game for the team to move
+  save <file>             write the game to a file
+  load <file>             carry on a game saved to a file, or from a fen
+  help                    print this";
+
+/// how far ahead the engine looks when not told
+const DEFAULT_DEPTH: u32 = 3;
+
+/// what the program was asked to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    Play,
+    Json,
+    Analyze { fen: String, depth: u32 },
+    SelfPlay { depth: u32 },
+}
+
+/// read the command line, where each flag is only accepted by the commands
+/// it's for
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let args: Vec<String> = args.into_iter().collect();
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return Ok(Command::Play),
+    };
+    match command {
+        "play" if rest.is_empty() => Ok(Command::Play),
+        "--json" if rest.is_empty() => Ok(Command::Json),
+        "analyze" => {
+            let (words, depth) = with_depth(rest)?;
+            if words.is_empty() {
+                return Err("`analyze` needs a fen".to_string());
+            }
+            // a fen has spaces in it, so it may come unquoted
+            Ok(Command::Analyze {
+                fen: words.join(" "),
+                depth,
+            })
+        }
+        "selfplay" => match with_depth(rest)? {
+            (words, depth) if words.is_empty() => Ok(Command::SelfPlay { depth }),
+            (words, _) => Err(format!("unexpected argument `{}` to `selfplay`", words[0])),
+        },
+        "play" | "--json" => Err(format!("`{}` takes no arguments", command)),
+        flag if flag.starts_with("--") => Err(format!("unknown flag `{}`", flag)),
+        _ => Err(format!("unknown command `{}`", command)),
+    }
+}
+
+/// take `--depth N` out of the arguments to a command that searches, giving
+/// back the rest along with the depth
+fn with_depth(args: &[String]) -> Result<(Vec<&str>, u32), String> {
+    let mut args = args.iter();
+    let mut words = Vec::new();
+    let mut depth = DEFAULT_DEPTH;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--depth" => {
+                let n = args.next().ok_or("--depth needs a number")?;
+                depth = n.parse().map_err(|_| format!("`{}` isn't a depth", n))?;
+            }
+            flag if flag.starts_with("--") => return Err(format!("unknown flag `{}`", flag)),
+            word => words.push(word),
+        }
+    }
+    Ok((words, depth))
+}
 
 pub fn main() {
+    let command = match parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+    match command {
+        Command::Play => play(),
+        Command::Json => {
+            json::run(io::stdin().lock(), io::stdout().lock()).expect("unable to talk over stdio")
+        }
+        Command::Analyze { fen, depth } => {
+            if let Err(e) = analyze(&fen, depth) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        Command::SelfPlay { depth } => selfplay(depth),
+    }
+}
+
+/// play a game typed in line by line
+fn play() {
     let mut game = Game::new();
+    // whether the game's history goes back to the starting position, so it
+    // can be saved as a transcript
+    let mut from_start = true;
 
     let mut lines = io::stdin().lock().lines();
+    let mut redraw = true;
 
     loop {
-        println!("{}", game);
+        if redraw {
+            draw(&game);
+
+            if game.finished() {
+                println!("{}", game.result());
+                break;
+            }
+
+            if game.in_check(game.turn) {
+                println!("{} is in check", game.turn);
+            }
+        }
+        redraw = true;
 
         print!("Move: ");
         io::stdout().flush().expect("unable to flush output");
 
-        let _move = match lines.next() {
-            Some(_move) => _move,
+        let line = match lines.next() {
+            Some(line) => line.expect("unable to read input"),
             None => break,
         };
+        let line = line.trim();
+        let (command, arg) = match line.split_once(char::is_whitespace) {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (line, ""),
+        };
 
-        let _move = _move.expect("unable to read input");
-        let _move: Result<Vec<_>, _> = _move
-            .splitn(2, "->")
-            .map(|c| {
-                c.trim()
-                    .splitn(2, ",")
-                    .map(|n| n.trim().parse::<i32>())
-                    .collect::<Result<Vec<_>, _>>()
-            })
-            .collect();
-        let _move = match _move {
-            Ok(_move) => _move,
-            Err(e) => {
-                eprintln!("failed to parse move: {}", e);
-                continue;
+        match command {
+            "undo" => {
+                if !game.undo() {
+                    eprintln!("nothing to undo");
+                }
             }
-        };
+            "redo" => {
+                if !game.redo() {
+                    eprintln!("nothing to redo");
+                }
+            }
+            "show" => {}
+            "legal" => {
+                redraw = false;
+                match notation::parse_hex(arg, game.board.radius()) {
+                    Ok(from) => println!("{}", legal_moves(&game, from)),
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+            "resign" => {
+                if let Err(e) = game.resign(game.turn) {
+                    eprintln!("{}", e);
+                }
+            }
+            "save" => {
+                redraw = false;
+                let saved = if from_start {
+                    game.to_transcript()
+                } else {
+                    game.to_fen()
+                };
+                match fs::write(arg, saved + "\n") {
+                    Ok(()) => println!("saved to {}", arg),
+                    Err(e) => eprintln!("unable to save to `{}` - {}", arg, e),
+                }
+            }
+            "load" => match load(arg) {
+                Ok((loaded, loaded_from_start)) => {
+                    game = loaded;
+                    from_start = loaded_from_start;
+                }
+                Err(e) => eprintln!("{}", e),
+            },
+            "help" => {
+                redraw = false;
+                println!("{}", HELP);
+            }
+            "move" => play_move(&mut game, arg),
+            _ => play_move(&mut game, line),
+        }
+    }
+}
 
-        let (f, t) = if let [f, t] = &_move[..] {
-            (f, t)
-        } else {
-            // can't get to this point as an invalid move due to parsing logic
-            unreachable!()
-        };
+/// try to play the move in `input`, saying what happened
+fn play_move(game: &mut Game, input: &str) {
+    let mv = match parse_move(input, game) {
+        Ok(mv) => mv,
+        Err(e) => {
+            eprintln!("failed to parse move: {}", e);
+            return;
+        }
+    };
 
-        if let Err(e) = game.move_piece((f[0], f[1]).into(), (t[0], t[1]).into()) {
-            eprintln!("{}", e);
-            continue;
+    match game.make_move(mv) {
+        Ok(record) => {
+            if let Some(captured) = record.captured {
+                println!("{} took a {}", record.piece.team, captured.name);
+            }
+            println!("{}", material(game));
+            if record.checkmate {
+                println!("Checkmate!");
+            } else if record.check {
+                println!("Check!");
+            } else if record.stalemate {
+                println!("Stalemate!");
+            }
         }
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+/// a game from a file written by `save`, or from a fen given in place of a
+/// file, along with whether it has its history from the starting position
+fn load(arg: &str) -> Result<(Game, bool), String> {
+    let saved = match fs::read_to_string(arg) {
+        Ok(saved) => saved,
+        Err(_) if Game::from_fen(arg).is_ok() => arg.to_string(),
+        Err(e) => return Err(format!("unable to load `{}` - {}", arg, e)),
+    };
+    match Game::from_transcript(&saved) {
+        Ok(game) => Ok((game, true)),
+        Err(transcript_error) => match Game::from_fen(saved.trim()) {
+            Ok(game) => Ok((game, false)),
+            Err(_) => Err(format!("unable to load `{}` - {}", arg, transcript_error)),
+        },
+    }
+}
+
+/// the moves the piece on `from` can make, in algebraic notation
+fn legal_moves(game: &Game, from: Coord) -> String {
+    let moves: Vec<String> = game
+        .board
+        .legal_moves(from)
+        .into_iter()
+        .map(|to| Move::new(from, to).to_san(&game.board))
+        .collect();
+    if moves.is_empty() {
+        format!("no moves from {}", square_name(from, game.board.radius()))
+    } else {
+        moves.join(" ")
+    }
+}
+
+/// print what the engine makes of the position in `fen`
+fn analyze(fen: &str, depth: u32) -> Result<(), String> {
+    let game = Game::from_fen(fen).map_err(|e| format!("unable to read fen - {}", e))?;
+    draw(&game);
+    if game.finished() {
+        println!("{}", game.result());
+        return Ok(());
     }
+    if game.in_check(game.turn) {
+        println!("{} is in check", game.turn);
+    }
+    println!("{}", material(&game));
+    println!(
+        "{} legal moves",
+        game.board.all_legal_moves(game.turn).len()
+    );
+    if let Some(mv) = engine::best_move(&game.board, game.turn, depth) {
+        println!("best move: {}", mv.to_san(&game.board));
+    }
+    Ok(())
+}
+
+/// let the engine play both sides, printing each move as it goes
+fn selfplay(depth: u32) {
+    let mut game = Game::new();
+    while !game.finished() {
+        let mv = match engine::best_move(&game.board, game.turn, depth) {
+            Some(mv) => mv,
+            None => break,
+        };
+        let record = game.make_move(mv).expect("the engine plays legal moves");
+        // a line per move number, once black has replied
+        if record.piece.team == Team::Black || game.finished() {
+            if let Some(line) = game.move_list().last() {
+                println!("{}", line);
+            }
+        }
+    }
+    draw(&game);
+    println!("{}", game.result());
+}
+
+/// print whose turn it is and the board, in colour when the terminal has it
+fn draw(game: &Game) {
+    println!("{}'s turn", game.turn);
+    println!(
+        "{}",
+        game.board
+            .display_colored()
+            .color(TermRenderer::color_supported())
+            .coordinates(true)
+            .last_move(
+                game.history()
+                    .last()
+                    .map(|record| (record.mv.from, record.mv.to))
+            )
+    );
+}
+
+/// who is ahead on material and by how many pawns
+fn material(game: &Game) -> String {
+    match game.material_balance() {
+        0 => "material is even".to_string(),
+        n if n > 0 => format!("white is up {}", n),
+        n => format!("black is up {}", -n),
+    }
+}
+
+/// read a move in algebraic notation like `Nc3`, or as a pair of hexes like
+/// `f5 -> f6` or `0, -1 -> 0, 0`
+fn parse_move(input: &str, game: &Game) -> Result<Move, String> {
+    let input = input.trim();
+    let san_error = match Move::from_san(input, &game.board, game.turn) {
+        Ok(mv) => return Ok(mv),
+        Err(e) => e,
+    };
+    notation::parse_move_with_radius(input, game.board.radius())
+        .map(|(from, to)| Move::new(from, to))
+        .map_err(|e| match e {
+            // an arrow means hexes were meant rather than algebraic notation
+            ParseError::InvalidHex(_) if input.contains("->") => e.to_string(),
+            _ => format!(
+                "{} - expected algebraic notation like `Nc3` or hexes like `f5 -> f6` or `q, r -> q, r`",
+                san_error
+            ),
+        })
 }
@@ -0,0 +1,51 @@
+use crate::{coord::Coord, piece::Name};
+use std::fmt;
+
+/// a piece moving from one hex to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move {
+    pub from: Coord,
+    pub to: Coord,
+    /// what a pawn reaching the last rank becomes - a queen if not given
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub promotion: Option<Name>,
+}
+
+impl Move {
+    pub const fn new(from: Coord, to: Coord) -> Self {
+        Self {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+
+    /// a pawn move to the last rank that promotes to `name`
+    pub const fn promoting(from: Coord, to: Coord, name: Name) -> Self {
+        Self {
+            from,
+            to,
+            promotion: Some(name),
+        }
+    }
+}
+
+impl From<(Coord, Coord)> for Move {
+    fn from((from, to): (Coord, Coord)) -> Self {
+        Self::new(from, to)
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.from, self.to)?;
+        match self.promotion {
+            Some(name) => write!(f, "={}", name.fen_char()),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,381 @@
+//! messages for playing a game between two machines
+//!
+//! each side keeps its own `Game` inside a `RemoteGame`, sends what its player
+//! does and checks what arrives against the local copy. moves carry a checksum
+//! of the position they lead to so the two copies can't drift apart quietly.
+//!
+//! games played through a server wrap these in `ClientMsg` and `ServerMsg`,
+//! which add lobbies with join codes, reconnecting and spectating.
+
+use crate::{
+    game::{Game, GameError, GameResult, MoveRecord, TimeControl},
+    moves::Move,
+    piece::Team,
+};
+
+/// a move along with enough to check both sides agree on the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MoveMsg {
+    pub mv: Move,
+    /// how many plies were played before this one
+    pub ply: usize,
+    /// `checksum` of the game's fen after the move
+    pub checksum: u64,
+}
+
+/// everything one side of a networked game says to the other
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Message {
+    /// start a game from `fen`, with the receiver playing `team`
+    NewGame {
+        fen: String,
+        team: Team,
+        time_control: Option<TimeControl>,
+    },
+    Move(MoveMsg),
+    Resign,
+    DrawOffer,
+    DrawAccept,
+    Chat(String),
+}
+
+/// what a client says to a game server
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClientMsg {
+    /// open a lobby for a new game with the sender playing `team`
+    Create {
+        team: Team,
+        time_control: Option<TimeControl>,
+    },
+    /// take the empty seat in the lobby with `code`
+    Join { code: String },
+    /// take back a seat after losing the connection, with the token the seat
+    /// was given
+    Rejoin { code: String, token: String },
+    /// watch the game in the lobby with `code`
+    Spectate { code: String },
+    /// something for the game the sender is seated in
+    Game(Message),
+}
+
+/// what a game server says to a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ServerMsg {
+    /// a lobby was opened, with the code to pass on to an opponent
+    Created {
+        code: String,
+        token: String,
+    },
+    /// the sender has a seat, and `token` gets it back after a disconnect
+    Seated {
+        token: String,
+    },
+    /// the sender is watching a game that is now at `fen`
+    Spectating {
+        fen: String,
+    },
+    /// something from the other player, or a `NewGame` to (re)start from
+    Game(Message),
+    /// the other player connected or dropped out
+    Opponent {
+        connected: bool,
+    },
+    GameOver(GameResult),
+    Error(String),
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum NetError {
+    #[error("{0}")]
+    GameError(#[from] GameError),
+    #[error("it's {0}'s turn to move")]
+    NotYourTurn(Team),
+    #[error("expected ply {expected} but was sent ply {given}")]
+    WrongPly { expected: usize, given: usize },
+    #[error("the position after {0} doesn't match the other side's")]
+    ChecksumMismatch(Move),
+    #[error("a game is already being played")]
+    GameInProgress,
+    #[error("expected a new game")]
+    NoGame,
+}
+
+/// a 64 bit FNV-1a hash of a fen string, which is the same on every platform
+pub fn checksum(fen: &str) -> u64 {
+    fen.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// how many plies into the game its position is, worked out from the fen
+/// counters so it survives picking a game back up from its fen
+pub fn ply(game: &Game) -> usize {
+    let black = usize::from(game.turn == Team::Black);
+    2 * (game.fullmove_number() as usize - 1) + black
+}
+
+/// one side of a game played over the network
+pub struct RemoteGame {
+    pub game: Game,
+    /// the team played on this machine
+    pub local: Team,
+}
+
+impl RemoteGame {
+    /// start hosting `game` from its current position, playing `local`, along
+    /// with the message that sets the game up on the other side
+    pub fn host(game: Game, local: Team) -> (Self, Message) {
+        let message = Message::NewGame {
+            fen: game.to_fen(),
+            team: local.flip(),
+            time_control: game.time_control(),
+        };
+        (Self { game, local }, message)
+    }
+
+    /// set up the game the host described in a `NewGame` message
+    pub fn join(message: &Message) -> Result<Self, NetError> {
+        match message {
+            Message::NewGame {
+                fen,
+                team,
+                time_control,
+            } => {
+                let mut game = Game::from_fen(fen)?;
+                if let Some(time_control) = *time_control {
+                    game.set_time_control(time_control);
+                }
+                Ok(Self { game, local: *team })
+            }
+            _ => Err(NetError::NoGame),
+        }
+    }
+
+    /// the team on the other machine
+    pub fn remote(&self) -> Team {
+        self.local.flip()
+    }
+
+    fn ply(&self) -> usize {
+        ply(&self.game)
+    }
+
+    /// play a move for the local team, giving the message to send for it
+    pub fn play(&mut self, mv: Move) -> Result<(MoveRecord, Message), NetError> {
+        if self.game.turn != self.local {
+            return Err(NetError::NotYourTurn(self.game.turn));
+        }
+        let ply = self.ply();
+        let record = self.game.make_move(mv)?;
+        let message = Message::Move(MoveMsg {
+            mv: record.mv,
+            ply,
+            checksum: checksum(&self.game.to_fen()),
+        });
+        Ok((record, message))
+    }
+
+    /// concede the game for the local team
+    pub fn resign(&mut self) -> Result<Message, NetError> {
+        self.game.resign(self.local)?;
+        Ok(Message::Resign)
+    }
+
+    /// offer the remote team a draw
+    pub fn offer_draw(&mut self) -> Result<Message, NetError> {
+        self.game.offer_draw(self.local)?;
+        Ok(Message::DrawOffer)
+    }
+
+    /// take the remote team's standing draw offer
+    pub fn accept_draw(&mut self) -> Result<Message, NetError> {
+        self.game.accept_draw(self.local)?;
+        Ok(Message::DrawAccept)
+    }
+
+    /// apply a message from the other side, giving the record of the move
+    /// if it was one
+    ///
+    /// a move that leaves the game in a different position to the sender's
+    /// is taken back and reported as a `ChecksumMismatch`
+    pub fn receive(&mut self, message: &Message) -> Result<Option<MoveRecord>, NetError> {
+        let remote = self.remote();
+        match message {
+            Message::NewGame { .. } => return Err(NetError::GameInProgress),
+            Message::Move(MoveMsg {
+                mv,
+                ply,
+                checksum: sent,
+            }) => {
+                if self.game.turn != remote {
+                    return Err(NetError::NotYourTurn(self.game.turn));
+                }
+                if *ply != self.ply() {
+                    return Err(NetError::WrongPly {
+                        expected: self.ply(),
+                        given: *ply,
+                    });
+                }
+                let record = self.game.make_move(*mv)?;
+                if checksum(&self.game.to_fen()) != *sent {
+                    self.game.undo();
+                    return Err(NetError::ChecksumMismatch(*mv));
+                }
+                return Ok(Some(record));
+            }
+            Message::Resign => self.game.resign(remote)?,
+            Message::DrawOffer => self.game.offer_draw(remote)?,
+            Message::DrawAccept => self.game.accept_draw(remote)?,
+            Message::Chat(_) => {}
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::{GameBuilder, GameResult},
+        piece::{Name, Piece},
+    };
+    use std::time::Duration;
+
+    fn pair() -> (RemoteGame, RemoteGame) {
+        let game = Game::with_time_control(TimeControl {
+            initial: Duration::from_secs(300),
+            increment: Duration::from_secs(2),
+        });
+        let (host, new_game) = RemoteGame::host(game, Team::White);
+        let guest = RemoteGame::join(&new_game).unwrap();
+        (host, guest)
+    }
+
+    #[test]
+    fn moves_keep_both_sides_in_step() {
+        let (mut host, mut guest) = pair();
+        assert_eq!(guest.local, Team::Black);
+        assert_eq!(
+            guest.game.remaining(Team::Black),
+            Some(Duration::from_secs(300))
+        );
+
+        let (_, message) = host.play(Move::new((0, -1).into(), (0, 0).into())).unwrap();
+        assert!(guest.receive(&message).unwrap().is_some());
+        let (_, message) = guest.play(Move::new((1, 1).into(), (1, 0).into())).unwrap();
+        let record = host.receive(&message).unwrap().unwrap();
+        assert_eq!(record.piece.team, Team::Black);
+        assert_eq!(host.game.to_fen(), guest.game.to_fen());
+
+        // the same move can't be delivered twice
+        assert_eq!(
+            host.receive(&message),
+            Err(NetError::NotYourTurn(Team::White))
+        );
+        assert_eq!(
+            guest.play(Move::new((2, 1).into(), (2, 0).into())).err(),
+            Some(NetError::NotYourTurn(Team::White))
+        );
+    }
+
+    #[test]
+    fn rejects_moves_out_of_step() {
+        let (mut host, mut guest) = pair();
+        let (_, message) = host.play(Move::new((0, -1).into(), (0, 0).into())).unwrap();
+        let mut stale = match message {
+            Message::Move(msg) => msg,
+            _ => unreachable!(),
+        };
+        stale.ply = 3;
+        assert_eq!(
+            guest.receive(&Message::Move(stale)),
+            Err(NetError::WrongPly {
+                expected: 0,
+                given: 3
+            })
+        );
+
+        stale.ply = 0;
+        stale.checksum ^= 1;
+        assert_eq!(
+            guest.receive(&Message::Move(stale)),
+            Err(NetError::ChecksumMismatch(stale.mv))
+        );
+        assert_eq!(guest.game.turn, Team::White);
+        assert_eq!(guest.game.history().count(), 0);
+        assert!(guest.receive(&message).is_ok());
+
+        // a side picked back up from the fen counts plies from the same place
+        let (_, message) = guest.play(Move::new((1, 1).into(), (1, 0).into())).unwrap();
+        host.receive(&message).unwrap();
+        let (rejoined, _) =
+            RemoteGame::host(Game::from_fen(&host.game.to_fen()).unwrap(), Team::White);
+        assert_eq!(rejoined.ply(), 2);
+    }
+
+    #[test]
+    fn rejects_impossible_promotions() {
+        let game = GameBuilder::new()
+            .place((1, -5).into(), Piece::new(Name::King, Team::White))
+            .place((4, 0).into(), Piece::new(Name::King, Team::Black))
+            .place((0, 4).into(), Piece::new(Name::Pawn, Team::White))
+            .build()
+            .unwrap();
+        let (mut host, _) = RemoteGame::host(game, Team::Black);
+        // a second white king, which the sender can't be allowed to make
+        let mv = Move::promoting((0, 4).into(), (0, 5).into(), Name::King);
+        let message = Message::Move(MoveMsg {
+            mv,
+            ply: 0,
+            checksum: 0,
+        });
+        assert_eq!(
+            host.receive(&message),
+            Err(NetError::GameError(GameError::InvalidPromotion(mv)))
+        );
+        assert_eq!(host.game.history().count(), 0);
+    }
+
+    #[test]
+    fn resigning_and_draws() {
+        let (mut host, mut guest) = pair();
+        assert!(guest
+            .receive(&Message::Chat("good luck".to_string()))
+            .is_ok());
+        assert!(matches!(
+            guest.receive(&Message::DrawAccept),
+            Err(NetError::GameError(GameError::NoDrawOffer(Team::White)))
+        ));
+
+        let offer = guest.offer_draw().unwrap();
+        host.receive(&offer).unwrap();
+        let accept = host.accept_draw().unwrap();
+        guest.receive(&accept).unwrap();
+        assert_eq!(host.game.result(), guest.game.result());
+        assert!(host.game.finished());
+
+        let (mut host, mut guest) = pair();
+        let resign = host.resign().unwrap();
+        guest.receive(&resign).unwrap();
+        assert_eq!(guest.game.result(), GameResult::Resignation(Team::Black));
+        assert!(matches!(
+            guest.receive(&resign),
+            Err(NetError::GameError(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn messages_round_trip_through_json() {
+        let (mut host, _) = pair();
+        let (_, message) = host.play(Move::new((0, -1).into(), (0, 0).into())).unwrap();
+        for message in [message, Message::Chat("gg".to_string()), Message::Resign] {
+            let json = serde_json::to_string(&message).unwrap();
+            assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+        }
+    }
+}
@@ -0,0 +1,588 @@
+//! algebraic notation for hex chess moves
+//!
+//! hexes are named like glinski's notation - a file letter followed by a rank
+//! number. files run `a` to `l` (skipping `j`) from `q = -radius` to
+//! `q = radius`, and ranks count up from 1 at the hex on each file closest to
+//! white, so `f1` is `(0, -5)` and `f11` is `(0, 5)` on the default board.
+//!
+//! moves are written like standard algebraic notation - the piece letter
+//! (omitted for pawns), the file and/or rank of the moving piece if another of
+//! the same type could also make the move, `x` for a capture, the destination
+//! and `+` or `#` for check or checkmate, e.g. `Nc3`, `fxg6`, `Rbf6+`
+//!
+//! positions are written like fen - the rows of the board as drawn by
+//! `HexBoard`'s `Display` separated by `/`, each listing its pieces by letter
+//! (uppercase for white) with runs of empty hexes as a count, e.g.
+//! `bbbkq1/...`
+
+use crate::{
+    board::{HexBoard, PlacementError},
+    coord::Coord,
+    moves::Move,
+    piece::{Name, Piece, Team},
+};
+
+const FILES: &[u8] = b"abcdefghiklmnopqrstuvwxyz";
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum SanError {
+    #[error("unable to parse move {0:?}")]
+    InvalidNotation(String),
+    #[error("{0:?} is not a hex on the board")]
+    InvalidSquare(String),
+    #[error("no legal move matches {0:?}")]
+    NoMove(String),
+    #[error("more than one legal move matches {0:?}")]
+    Ambiguous(String),
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("expected hexes like `f5 -> f6` or `q, r -> q, r` but found {0:?}")]
+    Format(String),
+    #[error("{0:?} is not a hex on the board")]
+    InvalidHex(String),
+}
+
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum FenError {
+    #[error("expected {expected} rows but found {found}")]
+    RowCount { expected: usize, found: usize },
+    #[error("row {0} doesn't have the right number of hexes")]
+    RowLength(usize),
+    #[error("{0:?} is not a piece")]
+    InvalidPiece(char),
+    #[error("{0}")]
+    PlacementError(#[from] PlacementError),
+    #[error("missing the {0} field")]
+    MissingField(&'static str),
+    #[error("unable to read the {0} field from {1:?}")]
+    InvalidField(&'static str, String),
+}
+
+/// the rank of the hex at the bottom (white's side) of file `q`
+fn first_rank(q: i32, radius: i32) -> i32 {
+    (-radius).max(-radius - q)
+}
+
+fn file_name(q: i32, radius: i32) -> char {
+    FILES[(q + radius) as usize] as char
+}
+
+fn rank_number(c: Coord, radius: i32) -> i32 {
+    c.r - first_rank(c.q, radius) + 1
+}
+
+/// the name of hex `c` on a board of `radius`, e.g. `f5`
+pub fn square_name(c: Coord, radius: i32) -> String {
+    format!("{}{}", file_name(c.q, radius), rank_number(c, radius))
+}
+
+fn parse_file(file: char, radius: i32) -> Option<i32> {
+    let q = FILES.iter().position(|&f| f as char == file)? as i32 - radius;
+    (q <= radius).then_some(q)
+}
+
+/// parse a hex name like `f5` into a coordinate on a board of `radius`
+pub fn parse_square(s: &str, radius: i32) -> Option<Coord> {
+    let mut chars = s.chars();
+    let q = parse_file(chars.next()?, radius)?;
+    let rank: i32 = chars.as_str().parse().ok()?;
+    if !(1..=2 * radius + 1).contains(&rank) {
+        return None;
+    }
+    let c = Coord::new(q, first_rank(q, radius) + rank - 1);
+    c.in_bounds(radius).then_some(c)
+}
+
+/// parse a hex on a board of `radius` given by name like `f5`, or in axial
+/// coordinates like `0, -1`
+pub fn parse_hex(s: &str, radius: i32) -> Result<Coord, ParseError> {
+    let s = s.trim();
+    parse_square(s, radius)
+        .or_else(|| s.parse::<Coord>().ok().filter(|c| c.in_bounds(radius)))
+        .ok_or_else(|| ParseError::InvalidHex(s.to_string()))
+}
+
+/// parse a move on the default board given as the hexes it's from and to, by
+/// name like `f5 -> f6` or in axial coordinates like `0, -1 -> 0, 0`
+///
+/// named hexes can also go without the arrow, as in `f5-f6`, `f5 f6` or
+/// `f5f6`. moves in algebraic notation like `Nc3` need the board to read, so
+/// they're left to `Move::from_san`
+pub fn parse_move(s: &str) -> Result<(Coord, Coord), ParseError> {
+    parse_move_with_radius(s, HexBoard::DEFAULT_RADIUS)
+}
+
+/// `parse_move` for a board of `radius`
+pub fn parse_move_with_radius(s: &str, radius: i32) -> Result<(Coord, Coord), ParseError> {
+    let (from, to) = match s.split_once("->") {
+        Some(hexes) => hexes,
+        // without an arrow the second hex starts at the next file letter
+        None => {
+            let split = s
+                .trim()
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| c.is_ascii_alphabetic())
+                .map(|(i, _)| i)
+                .ok_or_else(|| ParseError::Format(s.to_string()))?;
+            let (from, to) = s.trim().split_at(split);
+            (
+                from.trim_end_matches(|c: char| c == '-' || c.is_whitespace()),
+                to,
+            )
+        }
+    };
+    Ok((parse_hex(from, radius)?, parse_hex(to, radius)?))
+}
+
+fn parse_piece_letter(letter: char) -> Option<Name> {
+    // pawns go without a letter and the letters are always uppercase
+    Name::from_fen_char(letter).filter(|&name| name != Name::Pawn && letter.is_ascii_uppercase())
+}
+
+/// the number of hexes in `row` of a board of `radius` drawn as in
+/// `Coord::to_offset`
+fn row_length(row: i32, radius: i32) -> i32 {
+    2 * radius + 1 - radius.abs_diff(row) as i32
+}
+
+impl HexBoard {
+    /// write the pieces on the board in fen
+    ///
+    /// this only covers where the pieces stand - `Game::to_fen` adds whose
+    /// turn it is and the move counters
+    pub fn to_fen(&self) -> String {
+        let radius = self.radius();
+        let rows: Vec<String> = (0..2 * radius + 1)
+            .map(|row| {
+                let mut fen = String::new();
+                let mut empty = 0;
+                for col in 0..row_length(row, radius) {
+                    match self.get(Coord::from_offset(row, col, radius)) {
+                        Ok(piece) => {
+                            if empty > 0 {
+                                fen += &empty.to_string();
+                                empty = 0;
+                            }
+                            fen.push(piece.fen_char());
+                        }
+                        Err(_) => empty += 1,
+                    }
+                }
+                if empty > 0 {
+                    fen += &empty.to_string();
+                }
+                fen
+            })
+            .collect();
+        rows.join("/")
+    }
+
+    /// read pieces written by `to_fen` onto the default board
+    ///
+    /// fen doesn't say which pawns have moved, so only those on the hexes
+    /// pawns start on in glinski's layout may double step
+    pub fn from_fen(s: &str) -> Result<HexBoard, FenError> {
+        let radius = HexBoard::DEFAULT_RADIUS;
+        let rows: Vec<&str> = s.split('/').collect();
+        let expected = (2 * radius + 1) as usize;
+        if rows.len() != expected {
+            return Err(FenError::RowCount {
+                expected,
+                found: rows.len(),
+            });
+        }
+
+        let mut pieces = Vec::new();
+        for (row, fen) in rows.iter().enumerate() {
+            let mut col = 0;
+            let mut chars = fen.chars().peekable();
+            while let Some(c) = chars.next() {
+                if let Some(digit) = c.to_digit(10) {
+                    let mut empty = digit as i32;
+                    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                        empty = empty * 10 + digit as i32;
+                        // stop before a long run of digits overflows
+                        if empty > row_length(row as i32, radius) {
+                            return Err(FenError::RowLength(row));
+                        }
+                        chars.next();
+                    }
+                    col += empty;
+                } else {
+                    let name = Name::from_fen_char(c).ok_or(FenError::InvalidPiece(c))?;
+                    let team = if c.is_ascii_uppercase() {
+                        Team::White
+                    } else {
+                        Team::Black
+                    };
+                    pieces.push((
+                        Coord::from_offset(row as i32, col, radius),
+                        Piece::new(name, team),
+                    ));
+                    col += 1;
+                }
+                if col > row_length(row as i32, radius) {
+                    return Err(FenError::RowLength(row));
+                }
+            }
+            if col != row_length(row as i32, radius) {
+                return Err(FenError::RowLength(row));
+            }
+        }
+
+        let mut board = HexBoard::from_pieces(pieces)?;
+        board.reset_unmoved_pawns();
+        Ok(board)
+    }
+}
+
+impl Move {
+    /// write the move in algebraic notation - `self` should be a legal move on
+    /// `board`
+    pub fn to_san(&self, board: &HexBoard) -> String {
+        let radius = board.radius();
+        let piece = match board.get(self.from) {
+            Ok(&piece) => piece,
+            Err(_) => {
+                return format!(
+                    "{}-{}",
+                    square_name(self.from, radius),
+                    square_name(self.to, radius)
+                )
+            }
+        };
+        let capture = board.capture_square(self.from, self.to).is_some();
+
+        let mut san = String::new();
+        if piece.name == Name::Pawn {
+            if capture {
+                san.push(file_name(self.from.q, radius));
+            }
+        } else {
+            san.push(piece.name.fen_char());
+
+            // other pieces of the same type that could also make the move
+            let others: Vec<_> = board
+                .iter()
+                .filter(|&(c, p)| {
+                    c != self.from && p == piece && board.can_move(c, self.to).is_ok()
+                })
+                .map(|(c, _p)| c)
+                .collect();
+            if !others.is_empty() {
+                if others.iter().all(|c| c.q != self.from.q) {
+                    san.push(file_name(self.from.q, radius));
+                } else if others.iter().all(|c| c.r != self.from.r) {
+                    san += &rank_number(self.from, radius).to_string();
+                } else {
+                    san += &square_name(self.from, radius);
+                }
+            }
+        }
+        if capture {
+            san.push('x');
+        }
+        san += &square_name(self.to, radius);
+        if piece.name == Name::Pawn && board.is_last_rank(self.to, piece.team) {
+            san.push('=');
+            san.push(self.promotion.unwrap_or(Name::Queen).fen_char());
+        }
+
+        let mut projected = board.clone();
+        if projected.can_move(self.from, self.to).is_ok() {
+            projected.make(*self);
+            let enemy = piece.team.flip();
+            if projected.is_checkmated(enemy) {
+                san.push('#');
+            } else if projected.is_in_check(enemy) {
+                san.push('+');
+            }
+        }
+        san
+    }
+
+    /// parse a move by `team` written in algebraic notation
+    pub fn from_san(s: &str, board: &HexBoard, team: Team) -> Result<Move, SanError> {
+        let radius = board.radius();
+        let invalid = || SanError::InvalidNotation(s.to_string());
+        let san = s.trim().trim_end_matches(['+', '#']);
+        let (san, promotion) = match san.split_once('=') {
+            Some((san, promotion)) => {
+                let mut letters = promotion.chars();
+                match (letters.next().and_then(parse_piece_letter), letters.next()) {
+                    (Some(name), None) if Name::PROMOTIONS.contains(&name) => (san, Some(name)),
+                    _ => return Err(invalid()),
+                }
+            }
+            None => (san, None),
+        };
+
+        let mut chars = san.chars();
+        let name = match chars.clone().next().and_then(parse_piece_letter) {
+            Some(name) => {
+                chars.next();
+                name
+            }
+            None => Name::Pawn,
+        };
+        let rest: String = chars.filter(|&c| c != 'x').collect();
+
+        // the destination is the last file letter and the digits after it
+        let split = rest
+            .rfind(|c: char| c.is_ascii_alphabetic())
+            .ok_or_else(invalid)?;
+        let (disambiguation, destination) = rest.split_at(split);
+        let to = parse_square(destination, radius)
+            .ok_or_else(|| SanError::InvalidSquare(destination.to_string()))?;
+
+        // the file and/or rank of the moving piece
+        let mut disambiguation = disambiguation.chars().peekable();
+        let file = match disambiguation.peek() {
+            Some(&c) if c.is_ascii_alphabetic() => {
+                disambiguation.next();
+                Some(parse_file(c, radius).ok_or_else(invalid)?)
+            }
+            _ => None,
+        };
+        let rank: String = disambiguation.collect();
+        let rank = match rank.as_str() {
+            "" => None,
+            rank => Some(rank.parse::<i32>().map_err(|_| invalid())?),
+        };
+
+        let mut candidates = board.iter().filter(|&(c, p)| {
+            p == Piece::new(name, team)
+                && file.is_none_or(|q| c.q == q)
+                && rank.is_none_or(|rank| rank_number(c, radius) == rank)
+                && board.can_move(c, to).is_ok()
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some((from, _)), None) => match promotion {
+                // only a pawn reaching the last rank can say what it becomes
+                Some(_) if !(name == Name::Pawn && board.is_last_rank(to, team)) => Err(invalid()),
+                Some(promotion) => Ok(Move::promoting(from, to, promotion)),
+                None => Ok(Move::new(from, to)),
+            },
+            (None, _) => Err(SanError::NoMove(s.to_string())),
+            (Some(_), Some(_)) => Err(SanError::Ambiguous(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_as_hexes() {
+        let expected = Ok(((0, -1).into(), (0, 0).into()));
+        for input in [
+            "f5 -> f6",
+            "f5->f6",
+            "  f5-f6 ",
+            "f5 f6",
+            "f5f6",
+            "0, -1 -> 0, 0",
+            "0,-1->0,0",
+            "f5 -> 0, 0",
+        ] {
+            assert_eq!(parse_move(input), expected, "{:?}", input);
+        }
+        assert_eq!(
+            parse_move_with_radius("a1 -> a2", 1),
+            Ok(((-1, 0).into(), (-1, 1).into()))
+        );
+
+        // none of these should panic
+        assert_eq!(parse_move(""), Err(ParseError::Format("".to_string())));
+        assert_eq!(parse_move("f5"), Err(ParseError::Format("f5".to_string())));
+        assert_eq!(
+            parse_move("0, -1"),
+            Err(ParseError::Format("0, -1".to_string()))
+        );
+        assert_eq!(
+            parse_move("f5 -> f12"),
+            Err(ParseError::InvalidHex("f12".to_string()))
+        );
+        assert_eq!(
+            parse_move("0, 0 -> 9, 9"),
+            Err(ParseError::InvalidHex("9, 9".to_string()))
+        );
+        assert_eq!(
+            parse_move("-> f6"),
+            Err(ParseError::InvalidHex("".to_string()))
+        );
+        for input in [
+            "Nc3",
+            "z1z2",
+            "f99999999999 f1",
+            "é5é6",
+            "->",
+            "f5 -> -> f6",
+            "f-2147483648 f1",
+            "f5 -> f-2147483647",
+            "2147483647, 2147483647 -> 0, 0",
+            "-2147483648, 0 -> 0,0",
+        ] {
+            assert!(parse_move(input).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn squares() {
+        assert_eq!(square_name((0, -5).into(), 5), "f1");
+        assert_eq!(square_name((0, 5).into(), 5), "f11");
+        assert_eq!(square_name((-5, 0).into(), 5), "a1");
+        assert_eq!(square_name((5, -5).into(), 5), "l1");
+        assert_eq!(square_name((5, 0).into(), 5), "l6");
+
+        assert_eq!(parse_square("f1", 5), Some((0, -5).into()));
+        assert_eq!(parse_square("l6", 5), Some((5, 0).into()));
+        assert_eq!(parse_square("l7", 5), None);
+        assert_eq!(parse_square("j1", 5), None);
+        assert_eq!(parse_square("a0", 5), None);
+    }
+
+    fn round_trip(board: &HexBoard, mv: Move, team: Team, san: &str) {
+        assert_eq!(mv.to_san(board), san, "\n{}", board);
+        assert_eq!(Move::from_san(san, board, team), Ok(mv), "\n{}", board);
+    }
+
+    #[test]
+    fn knight_move() {
+        let board = HexBoard::new_initialize();
+        round_trip(
+            &board,
+            Move::new((-2, -3).into(), (-3, 0).into()),
+            Team::White,
+            "Nc3",
+        );
+        round_trip(
+            &board,
+            Move::new((2, 3).into(), (3, 0).into()),
+            Team::Black,
+            "Ni6",
+        );
+    }
+
+    #[test]
+    fn pawn_capture() {
+        let mut board = HexBoard::new_initialize();
+        board.move_piece((0, -1).into(), (0, 0).into()).unwrap();
+        board.move_piece((1, 1).into(), (1, 0).into()).unwrap();
+        round_trip(
+            &board,
+            Move::new((0, 0).into(), (1, 0).into()),
+            Team::White,
+            "fxg6",
+        );
+    }
+
+    #[test]
+    fn disambiguated_rook_move() {
+        let board = HexBoard::from_pieces([
+            ((-4, 0).into(), Piece::new(Name::Rook, Team::White)),
+            ((4, 0).into(), Piece::new(Name::Rook, Team::White)),
+            ((1, -5).into(), Piece::new(Name::King, Team::White)),
+            ((1, 4).into(), Piece::new(Name::King, Team::Black)),
+        ])
+        .unwrap();
+        round_trip(
+            &board,
+            Move::new((-4, 0).into(), (0, 0).into()),
+            Team::White,
+            "Rbf6",
+        );
+        assert_eq!(
+            Move::from_san("Rf6", &board, Team::White),
+            Err(SanError::Ambiguous("Rf6".to_string()))
+        );
+    }
+
+    #[test]
+    fn promotion_suffix() {
+        let board = HexBoard::from_pieces([
+            ((0, 4).into(), Piece::new(Name::Pawn, Team::White)),
+            ((1, -5).into(), Piece::new(Name::King, Team::White)),
+            ((4, 0).into(), Piece::new(Name::King, Team::Black)),
+        ])
+        .unwrap();
+        let (from, to) = ((0, 4).into(), (0, 5).into());
+        round_trip(
+            &board,
+            Move::promoting(from, to, Name::Knight),
+            Team::White,
+            "f11=N",
+        );
+        assert_eq!(Move::new(from, to).to_san(&board), "f11=Q");
+        assert_eq!(
+            Move::from_san("f11", &board, Team::White),
+            Ok(Move::new(from, to))
+        );
+        assert!(Move::from_san("f11=K", &board, Team::White).is_err());
+        assert!(Move::from_san("Kf2=Q", &board, Team::White).is_err());
+    }
+
+    #[test]
+    fn check_suffix() {
+        let board = HexBoard::from_pieces([
+            ((-4, 0).into(), Piece::new(Name::Rook, Team::White)),
+            ((1, -5).into(), Piece::new(Name::King, Team::White)),
+            ((1, 4).into(), Piece::new(Name::King, Team::Black)),
+        ])
+        .unwrap();
+        round_trip(
+            &board,
+            Move::new((-4, 0).into(), (-4, 4).into()),
+            Team::White,
+            "Rb6+",
+        );
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        let board = HexBoard::new_initialize();
+        let fen = board.to_fen();
+        assert_eq!(fen.split('/').count(), 11);
+        let read = HexBoard::from_fen(&fen).unwrap();
+        // every pawn is still on its starting hex, so can still double step
+        assert_eq!(read, board);
+        assert_eq!(read.to_fen(), fen);
+
+        let empty = HexBoard::new().to_fen();
+        assert!(empty.starts_with("6/7/8/9/10/11/10"));
+        assert_eq!(HexBoard::from_fen(&empty).unwrap(), HexBoard::new());
+    }
+
+    #[test]
+    fn bad_fen() {
+        assert_eq!(
+            HexBoard::from_fen("6/7"),
+            Err(FenError::RowCount {
+                expected: 11,
+                found: 2
+            })
+        );
+        assert_eq!(
+            HexBoard::from_fen("6/7/8/9/10/11/10/9/8/7/5"),
+            Err(FenError::RowLength(10))
+        );
+        assert_eq!(
+            HexBoard::from_fen("99999999999999999999/7/8/9/10/11/10/9/8/7/6"),
+            Err(FenError::RowLength(0))
+        );
+        assert_eq!(
+            HexBoard::from_fen("6/7/8/9/10/11/10/9/8/7/x5"),
+            Err(FenError::InvalidPiece('x'))
+        );
+        assert_eq!(
+            HexBoard::from_fen("6/7/8/9/10/11/10/9/8/7/kk4"),
+            Err(FenError::PlacementError(PlacementError::MultipleKings(
+                Team::Black
+            )))
+        );
+    }
+}
@@ -1,7 +1,6 @@
 use crate::coord::Coord;
 use num_derive::ToPrimitive;
-use once_cell::sync::OnceCell;
-use std::{collections::HashSet, fmt};
+use std::{fmt, str::FromStr};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MovesPossible {
@@ -9,9 +8,8 @@ pub struct MovesPossible {
     pub capture: bool,
 }
 
-static PAWN_DOUBLES: OnceCell<HashSet<Coord>> = OnceCell::new();
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ToPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Name {
     King,
     Queen,
@@ -22,24 +20,13 @@ pub enum Name {
 }
 
 impl Name {
+    /// what a pawn reaching the last rank can become, best first
+    pub const PROMOTIONS: [Name; 4] = [Name::Queen, Name::Rook, Name::Bishop, Name::Knight];
+
     fn verify_pawn(&self, f: Coord, t: Coord) -> Option<MovesPossible> {
-        let doubles = PAWN_DOUBLES.get_or_init(|| {
-            let mut doubles = HashSet::new();
-            doubles.extend(&[
-                Coord::new(-4, 1),
-                Coord::new(-3, 1),
-                Coord::new(-2, 1),
-                Coord::new(-1, 1),
-                Coord::new(0, 1),
-                Coord::new(1, 0),
-                Coord::new(1, -1),
-                Coord::new(1, -2),
-                Coord::new(1, -3),
-            ]);
-            doubles
-        });
-        // check trying to move one space forward, or two spaces forward
-        if f.q == t.q && (f.r + 1 == t.r || (doubles.contains(&t) && f.r + 2 == t.r)) {
+        // check trying to move one space forward, or two spaces forward - the
+        // board decides whether this pawn is still allowed its double step
+        if f.q == t.q && (f.r + 1 == t.r || f.r + 2 == t.r) {
             Some(MovesPossible {
                 _move: true,
                 capture: false,
@@ -55,31 +42,18 @@ impl Name {
     }
 
     fn verify_bishop(&self, f: Coord, t: Coord) -> Option<MovesPossible> {
-        const MOVEMENTS: &[Coord] = &[
-            Coord::new(1, -2),
-            Coord::new(2, -1),
-            Coord::new(1, 1),
-            Coord::new(-1, 2),
-            Coord::new(-2, 1),
-            Coord::new(-1, -1),
-        ];
-        let v = t - f;
-        for &m in MOVEMENTS {
-            let f = v / m;
-            // check that the movement requested is a non-zero integer multiple of the movement
-            // vector
-            if f.q == f.r && f * m == v {
-                return Some(MovesPossible {
-                    _move: true,
-                    capture: true,
-                });
-            }
+        if matches!(f.delta_to(t).decompose(), Some((dir, _)) if dir.is_diagonal()) {
+            Some(MovesPossible {
+                _move: true,
+                capture: true,
+            })
+        } else {
+            None
         }
-        None
     }
 
     fn verify_rook(&self, f: Coord, t: Coord) -> Option<MovesPossible> {
-        if f.q == t.q || f.r == t.r || f.s() == t.s() {
+        if matches!(f.delta_to(t).decompose(), Some((dir, _)) if !dir.is_diagonal()) {
             Some(MovesPossible {
                 _move: true,
                 capture: true,
@@ -90,8 +64,21 @@ impl Name {
     }
 
     fn verify_knight(&self, f: Coord, t: Coord) -> Option<MovesPossible> {
-        let v = t - f;
-        if (v.q * v.r * v.s()).abs() == 6 {
+        const MOVEMENTS: &[Coord] = &[
+            Coord::new(1, 2),
+            Coord::new(2, 1),
+            Coord::new(3, -1),
+            Coord::new(3, -2),
+            Coord::new(2, -3),
+            Coord::new(1, -3),
+            Coord::new(-1, -2),
+            Coord::new(-2, -1),
+            Coord::new(-3, 1),
+            Coord::new(-3, 2),
+            Coord::new(-2, 3),
+            Coord::new(-1, 3),
+        ];
+        if MOVEMENTS.contains(&(t - f)) {
             Some(MovesPossible {
                 _move: true,
                 capture: true,
@@ -102,8 +89,7 @@ impl Name {
     }
 
     fn verify_king(&self, f: Coord, t: Coord) -> Option<MovesPossible> {
-        let v = t - f;
-        if v.length() == 1 || (v.norm_squared() % 3 == 0 && v.norm_squared() / 3 == 1) {
+        if matches!(f.delta_to(t).decompose(), Some((_, 1))) {
             Some(MovesPossible {
                 _move: true,
                 capture: true,
@@ -113,6 +99,43 @@ impl Name {
         }
     }
 
+    /// the uppercase letter used for this piece in FEN and algebraic notation
+    pub const fn fen_char(&self) -> char {
+        match self {
+            Name::Pawn => 'P',
+            Name::Knight => 'N',
+            Name::Bishop => 'B',
+            Name::Rook => 'R',
+            Name::Queen => 'Q',
+            Name::King => 'K',
+        }
+    }
+
+    /// the piece named by a FEN letter of either case
+    pub fn from_fen_char(c: char) -> Option<Name> {
+        match c.to_ascii_uppercase() {
+            'P' => Some(Name::Pawn),
+            'N' => Some(Name::Knight),
+            'B' => Some(Name::Bishop),
+            'R' => Some(Name::Rook),
+            'Q' => Some(Name::Queen),
+            'K' => Some(Name::King),
+            _ => None,
+        }
+    }
+
+    /// the usual material value in pawns - the king is priceless so it counts
+    /// for nothing
+    pub const fn value(&self) -> i32 {
+        match self {
+            Name::Pawn => 1,
+            Name::Knight | Name::Bishop => 3,
+            Name::Rook => 5,
+            Name::Queen => 9,
+            Name::King => 0,
+        }
+    }
+
     pub fn verify_move(&self, f: Coord, t: Coord) -> Option<MovesPossible> {
         match self {
             Name::Pawn => self.verify_pawn(f, t),
@@ -143,11 +166,54 @@ impl fmt::Display for Name {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ToPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Team {
     White,
     Black,
 }
 
+#[derive(Debug, thiserror::Error, Clone, PartialEq, Eq)]
+pub enum ParsePieceError {
+    #[error("{0:?} is not a team")]
+    Team(String),
+    #[error("{0:?} is not a piece")]
+    Name(String),
+}
+
+/// parses the full name or its first letter in any case, e.g. `knight` or `N`
+impl FromStr for Name {
+    type Err = ParsePieceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Name::from_fen_char(c).ok_or_else(|| ParsePieceError::Name(s.to_string()));
+        }
+        match s.to_ascii_lowercase().as_str() {
+            "pawn" => Ok(Name::Pawn),
+            "knight" => Ok(Name::Knight),
+            "bishop" => Ok(Name::Bishop),
+            "rook" => Ok(Name::Rook),
+            "queen" => Ok(Name::Queen),
+            "king" => Ok(Name::King),
+            _ => Err(ParsePieceError::Name(s.to_string())),
+        }
+    }
+}
+
+/// parses `white`, `w`, `black` or `b` in any case
+impl FromStr for Team {
+    type Err = ParsePieceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "white" | "w" => Ok(Team::White),
+            "black" | "b" => Ok(Team::Black),
+            _ => Err(ParsePieceError::Team(s.to_string())),
+        }
+    }
+}
+
 impl Team {
     pub const fn flip(self) -> Team {
         match self {
@@ -171,6 +237,7 @@ impl fmt::Display for Team {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Piece {
     pub name: Name,
     pub team: Team,
@@ -186,21 +253,20 @@ impl Piece {
         self
     }
 
-    pub fn verify_move(&self, mut f: Coord, mut t: Coord) -> Option<MovesPossible> {
-        if let Team::Black = self.team {
-            f = f.reflect_q();
-            t = t.reflect_q();
+    /// the FEN letter for this piece - uppercase for white and lowercase for
+    /// black
+    pub fn fen_char(&self) -> char {
+        match self.team {
+            Team::White => self.name.fen_char(),
+            Team::Black => self.name.fen_char().to_ascii_lowercase(),
         }
-
-        self.name.verify_move(f, t)
     }
-}
 
-impl fmt::Display for Piece {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let c = match self.team {
+    /// the chess glyph for this piece, hollow for white and filled for black
+    pub fn unicode(&self) -> char {
+        match self.team {
             Team::White => match self.name {
-                Name::Pawn { .. } => '♙',
+                Name::Pawn => '♙',
                 Name::Knight => '♘',
                 Name::Bishop => '♗',
                 Name::Rook => '♖',
@@ -208,15 +274,227 @@ impl fmt::Display for Piece {
                 Name::King => '♔',
             },
             Team::Black => match self.name {
-                Name::Pawn { .. } => '♟',
+                Name::Pawn => '♟',
                 Name::Knight => '♞',
                 Name::Bishop => '♝',
                 Name::Rook => '♜',
                 Name::Queen => '♛',
                 Name::King => '♚',
             },
-        };
+        }
+    }
+
+    /// the letter for this piece, which is the same as `fen_char`
+    pub fn ascii(&self) -> char {
+        self.fen_char()
+    }
+
+    pub fn verify_move(&self, mut f: Coord, mut t: Coord) -> Option<MovesPossible> {
+        if let Team::Black = self.team {
+            f = f.reflect_q();
+            t = t.reflect_q();
+        }
+
+        self.name.verify_move(f, t)
+    }
+}
+
+impl fmt::Display for Piece {
+    /// the alternate flag (`{:#}`) writes a letter instead of a chess glyph -
+    /// uppercase for white and lowercase for black
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}", self.ascii())
+        } else {
+            write!(f, "{}", self.unicode())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knight_offsets() {
+        let offsets = [
+            (1, 2),
+            (2, 1),
+            (3, -1),
+            (3, -2),
+            (2, -3),
+            (1, -3),
+            (-1, -2),
+            (-2, -1),
+            (-3, 1),
+            (-3, 2),
+            (-2, 3),
+            (-1, 3),
+        ];
+        for (q, r) in offsets {
+            let t = Coord::new(q, r);
+            assert!(Name::Knight.verify_move(Coord::ZERO, t).is_some(), "{}", t);
+        }
 
-        write!(f, "{}", c)
+        let impostors = [(3, 0), (2, 2), (3, -3), (4, -1), (1, -4), (6, -3), (-3, 6)];
+        for (q, r) in impostors {
+            let t = Coord::new(q, r);
+            assert!(Name::Knight.verify_move(Coord::ZERO, t).is_none(), "{}", t);
+        }
+    }
+
+    #[test]
+    fn fen_chars_round_trip() {
+        for name in [
+            Name::King,
+            Name::Queen,
+            Name::Bishop,
+            Name::Knight,
+            Name::Rook,
+            Name::Pawn,
+        ] {
+            assert_eq!(Name::from_fen_char(name.fen_char()), Some(name));
+            let black = Piece::new(name, Team::Black).fen_char();
+            assert!(black.is_ascii_lowercase());
+            assert_eq!(Name::from_fen_char(black), Some(name));
+        }
+        assert_eq!(Name::from_fen_char('x'), None);
+        assert_eq!(Piece::new(Name::Knight, Team::White).fen_char(), 'N');
+        assert_eq!(Name::Queen.value(), 9);
+    }
+
+    #[test]
+    fn glyphs() {
+        let names = [
+            Name::King,
+            Name::Queen,
+            Name::Bishop,
+            Name::Knight,
+            Name::Rook,
+            Name::Pawn,
+        ];
+        let white: String = names
+            .iter()
+            .map(|&n| Piece::new(n, Team::White).unicode())
+            .collect();
+        let black: String = names
+            .iter()
+            .map(|&n| Piece::new(n, Team::Black).unicode())
+            .collect();
+        assert_eq!(white, "♔♕♗♘♖♙");
+        assert_eq!(black, "♚♛♝♞♜♟");
+        let ascii: String = names
+            .iter()
+            .map(|&n| Piece::new(n, Team::Black).ascii())
+            .collect();
+        assert_eq!(ascii, "kqbnrp");
+        assert_ne!(
+            Piece::new(Name::Pawn, Team::White).to_string(),
+            Piece::new(Name::Pawn, Team::Black).to_string()
+        );
+    }
+
+    #[test]
+    fn parse_names_and_teams() {
+        for name in [
+            Name::King,
+            Name::Queen,
+            Name::Bishop,
+            Name::Knight,
+            Name::Rook,
+            Name::Pawn,
+        ] {
+            let full = name.to_string();
+            assert_eq!(full.parse(), Ok(name));
+            assert_eq!(full.to_uppercase().parse(), Ok(name));
+            assert_eq!(name.fen_char().to_string().parse(), Ok(name));
+            assert_eq!(
+                name.fen_char().to_ascii_lowercase().to_string().parse(),
+                Ok(name)
+            );
+        }
+        for team in [Team::White, Team::Black] {
+            let full = team.to_string();
+            assert_eq!(full.parse(), Ok(team));
+            assert_eq!(full.to_uppercase().parse(), Ok(team));
+            assert_eq!(full[..1].parse(), Ok(team));
+            assert_eq!(full[..1].to_uppercase().parse(), Ok(team));
+        }
+        assert_eq!(
+            "wizard".parse::<Name>(),
+            Err(ParsePieceError::Name("wizard".to_string()))
+        );
+        assert_eq!(
+            "x".parse::<Name>(),
+            Err(ParsePieceError::Name("x".to_string()))
+        );
+        assert_eq!(
+            "red".parse::<Team>(),
+            Err(ParsePieceError::Team("red".to_string()))
+        );
+    }
+
+    #[test]
+    fn bishop_diagonals() {
+        let diagonals = [(1, -2), (2, -1), (1, 1), (-1, 2), (-2, 1), (-1, -1)];
+        for (q, r) in diagonals {
+            for k in 1..=5 {
+                let t = Coord::new(q * k, r * k);
+                assert!(Name::Bishop.verify_move(Coord::ZERO, t).is_some(), "{}", t);
+            }
+        }
+
+        // every vector nearby, which truncating division mustn't let through
+        for q in -10..=10 {
+            for r in -10..=10 {
+                let v = Coord::new(q, r);
+                let diagonal = diagonals
+                    .iter()
+                    .any(|&(dq, dr)| (1..=10).any(|k| dq * k == q && dr * k == r));
+                assert_eq!(
+                    Name::Bishop.verify_move(Coord::ZERO, v).is_some(),
+                    diagonal,
+                    "{}",
+                    v
+                );
+            }
+        }
+
+        let near_misses = [(0, 0), (1, -1), (2, -3), (3, -1), (2, 1), (-3, 2), (1, 2)];
+        for (q, r) in near_misses {
+            let t = Coord::new(q, r);
+            assert!(Name::Bishop.verify_move(Coord::ZERO, t).is_none(), "{}", t);
+        }
+    }
+
+    #[test]
+    fn rook_queen_and_king_lines() {
+        for q in -10..=10 {
+            for r in -10..=10 {
+                let v = Coord::new(q, r);
+                let axis = v != Coord::ZERO && (q == 0 || r == 0 || v.s() == 0);
+                assert_eq!(
+                    Name::Rook.verify_move(Coord::ZERO, v).is_some(),
+                    axis,
+                    "{}",
+                    v
+                );
+                let queen = axis || v.is_diagonal();
+                assert_eq!(
+                    Name::Queen.verify_move(Coord::ZERO, v).is_some(),
+                    queen,
+                    "{}",
+                    v
+                );
+                // one step along an axis or a single diagonal
+                let king = v.length() == if axis { 1 } else { 2 } && queen;
+                assert_eq!(
+                    Name::King.verify_move(Coord::ZERO, v).is_some(),
+                    king,
+                    "{}",
+                    v
+                );
+            }
+        }
     }
 }
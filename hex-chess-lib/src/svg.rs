@@ -0,0 +1,87 @@
+//! drawing the board as an svg image, coloured like the bevy frontend
+
+use std::fmt::Write;
+
+use crate::{board::HexBoard, coord::Coord};
+
+const LIGHT: &str = "#ffcf9e";
+const MID: &str = "#d18c45";
+const DARK: &str = "#e8ad70";
+
+/// the center of a flat topped hex of the given size, with y pointing down the
+/// page so white sits at the bottom
+fn hex_to_pixel(c: Coord, size: f32) -> (f32, f32) {
+    let sqrt_3 = 3f32.sqrt();
+    let x = size * 1.5 * c.q as f32;
+    let y = size * (sqrt_3 / 2.0 * c.q as f32 + sqrt_3 * c.r as f32);
+    (x, -y)
+}
+
+fn fill(c: Coord) -> &'static str {
+    [MID, DARK, LIGHT][c.color_index() as usize]
+}
+
+impl HexBoard {
+    /// draw the board as a standalone svg, with `radius` the distance in pixels
+    /// from the center of each hex to its corners
+    pub fn to_svg(&self, radius: f32) -> String {
+        let n = self.radius();
+        let half_width = radius * (1.5 * n as f32 + 1.0);
+        let half_height = radius * 3f32.sqrt() * (n as f32 + 0.5);
+        let mut svg = String::new();
+        // writing to a string can't fail
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+            -half_width,
+            -half_height,
+            2.0 * half_width,
+            2.0 * half_height
+        );
+        for c in self.hexes() {
+            let (x, y) = hex_to_pixel(c, radius);
+            let corners: Vec<String> = (0..6)
+                .map(|i| {
+                    let angle = std::f32::consts::FRAC_PI_3 * i as f32;
+                    format!("{},{}", x + radius * angle.cos(), y + radius * angle.sin())
+                })
+                .collect();
+            let _ = writeln!(
+                svg,
+                r#"<polygon points="{}" fill="{}"/>"#,
+                corners.join(" "),
+                fill(c)
+            );
+        }
+        for (c, piece) in self.iter() {
+            let (x, y) = hex_to_pixel(c, radius);
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                x,
+                y,
+                radius * 1.2,
+                piece.unicode()
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::{Name, Piece, Team};
+
+    #[test]
+    fn draws_every_hex_and_piece() {
+        let mut board = HexBoard::new();
+        board.place((0, -5).into(), Piece::new(Name::King, Team::White));
+        let svg = board.to_svg(20.0);
+        assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<polygon").count(), 91);
+        assert_eq!(svg.matches("<text").count(), 1);
+        assert!(svg.contains('♔'));
+    }
+}
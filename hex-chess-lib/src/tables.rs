@@ -0,0 +1,70 @@
+//! the hexes a knight or king can reach from every hex of the default board,
+//! worked out once on first use
+
+use crate::{
+    board::{board_hexes, HexBoard},
+    coord::Coord,
+    piece::Name,
+};
+use std::{collections::HashMap, sync::OnceLock};
+
+type Targets = HashMap<Coord, Vec<Coord>>;
+
+fn build(name: Name) -> Targets {
+    let coords: Vec<Coord> = board_hexes(HexBoard::DEFAULT_RADIUS).collect();
+    coords
+        .iter()
+        .map(|&from| {
+            let targets = coords
+                .iter()
+                .copied()
+                .filter(|&to| name.verify_move(from, to).is_some())
+                .collect();
+            (from, targets)
+        })
+        .collect()
+}
+
+/// the hexes on the default board a knight or king on `from` could move to,
+/// whatever is standing on them
+///
+/// panics for any other piece
+pub(crate) fn targets(name: Name, from: Coord) -> &'static [Coord] {
+    static KNIGHT: OnceLock<Targets> = OnceLock::new();
+    static KING: OnceLock<Targets> = OnceLock::new();
+    let table = match name {
+        Name::Knight => KNIGHT.get_or_init(|| build(Name::Knight)),
+        Name::King => KING.get_or_init(|| build(Name::King)),
+        _ => panic!("no target table for a {}", name),
+    };
+    table.get(&from).map_or(&[], Vec::as_slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corner_matches_geometry() {
+        let corner = Coord::new(5, -5);
+
+        let mut knight = targets(Name::Knight, corner).to_vec();
+        knight.sort_by_key(|c| (c.q, c.r));
+        let expected: Vec<Coord> = [(2, -4), (2, -3), (3, -2), (4, -2)]
+            .into_iter()
+            .map(Coord::from)
+            .collect();
+        assert_eq!(knight, expected);
+
+        let mut king = targets(Name::King, corner).to_vec();
+        king.sort_by_key(|c| (c.q, c.r));
+        let expected: Vec<Coord> = [(3, -4), (4, -5), (4, -4), (4, -3), (5, -4)]
+            .into_iter()
+            .map(Coord::from)
+            .collect();
+        assert_eq!(king, expected);
+
+        // off the board there is nothing
+        assert!(targets(Name::Knight, Coord::new(6, 0)).is_empty());
+    }
+}
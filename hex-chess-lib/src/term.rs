@@ -0,0 +1,207 @@
+//! drawing the board in a terminal with ansi colours, shaded like the bevy
+//! frontend
+
+use std::{
+    fmt,
+    io::{self, IsTerminal},
+};
+
+use crate::{
+    board::{write_border, HexBoard},
+    coord::Coord,
+    notation::square_name,
+    piece::Name,
+};
+
+/// the three tile shades, indexed by `Coord::color_index`
+const TILES: [(u8, u8, u8); 3] = [(209, 140, 69), (232, 173, 112), (255, 207, 158)];
+const LAST_MOVE: (u8, u8, u8) = (222, 204, 115);
+const CHECK: (u8, u8, u8) = (230, 77, 64);
+/// black text, so white and black glyphs both read on every shade
+const FOREGROUND: &str = "\x1b[30m";
+const RESET: &str = "\x1b[0m";
+/// room for the longest square name, like `f11`
+const LABEL_WIDTH: usize = 3;
+
+/// draws a board for a terminal, made by `HexBoard::display_colored`
+///
+/// without colour it draws the board the same as its `Display`, with
+/// markers around a king in check in place of the shading. the alternate
+/// flag (`{:#}`) renders pieces as letters either way
+#[derive(Debug, Clone, Copy)]
+pub struct TermRenderer<'a> {
+    board: &'a HexBoard,
+    color: bool,
+    coordinates: bool,
+    last_move: Option<(Coord, Coord)>,
+}
+
+impl<'a> TermRenderer<'a> {
+    pub fn new(board: &'a HexBoard) -> Self {
+        Self {
+            board,
+            color: true,
+            coordinates: false,
+            last_move: None,
+        }
+    }
+
+    /// whether to shade the hexes, which is on to start with
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// whether to name the hexes at either end of each row
+    pub fn coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+
+    /// the hexes a move was just made from and to, which are highlighted
+    pub fn last_move(mut self, last_move: Option<(Coord, Coord)>) -> Self {
+        self.last_move = last_move;
+        self
+    }
+
+    /// whether stdout looks like a terminal that shows colour - it has to be
+    /// a terminal, `NO_COLOR` can't be set and `TERM` can't be `dumb`
+    pub fn color_supported() -> bool {
+        io::stdout().is_terminal()
+            && std::env::var_os("NO_COLOR").is_none()
+            && std::env::var("TERM").map_or(true, |term| term != "dumb")
+    }
+
+    fn shade(&self, c: Coord, checked: bool) -> (u8, u8, u8) {
+        if checked {
+            CHECK
+        } else if matches!(self.last_move, Some((from, to)) if c == from || c == to) {
+            LAST_MOVE
+        } else {
+            TILES[c.color_index() as usize]
+        }
+    }
+}
+
+impl fmt::Display for TermRenderer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = self.board.radius();
+        let margin = if self.coordinates { LABEL_WIDTH + 1 } else { 0 };
+        write!(f, "{:1$}", "", margin)?;
+        write_border(f, n)?;
+        writeln!(f)?;
+        for row in 0..(2 * n + 1) {
+            let cols = 2 * n + 1 - n.abs_diff(row) as i32;
+            if self.coordinates {
+                let first = square_name(Coord::from_offset(row, 0, n), n);
+                write!(f, "{:>1$} ", first, LABEL_WIDTH)?;
+            }
+            write!(f, "{:1$}#", "", n.abs_diff(row) as usize)?;
+            let mut after_check = false;
+            for col in 0..cols {
+                let c = Coord::from_offset(row, col, n);
+                let piece = self.board.get(c).ok();
+                let checked = matches!(piece, Some(p) if p.name == Name::King && self.board.is_in_check(p.team));
+                let gap = if self.color {
+                    let (r, g, b) = self.shade(c, checked);
+                    write!(f, "\x1b[48;2;{};{};{}m{}", r, g, b, FOREGROUND)?;
+                    ' '
+                } else {
+                    match (checked, after_check) {
+                        (true, _) => '>',
+                        (false, true) => '<',
+                        (false, false) => ' ',
+                    }
+                };
+                after_check = checked;
+                match piece {
+                    Some(p) if f.alternate() => write!(f, "{}{:#}", gap, p),
+                    Some(p) => write!(f, "{}{}", gap, p),
+                    // the shading shows where the hex is
+                    None if self.color => write!(f, "{} ", gap),
+                    None => write!(f, "{}.", gap),
+                }?
+            }
+            if self.color {
+                write!(f, "{} #", RESET)?;
+            } else {
+                write!(f, "{}#", if after_check { '<' } else { ' ' })?;
+            }
+            if self.coordinates {
+                let last = square_name(Coord::from_offset(row, cols - 1, n), n);
+                write!(f, " {}", last)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "{:1$}", "", margin)?;
+        write_border(f, n)
+    }
+}
+
+impl HexBoard {
+    /// draw the board for a terminal, shaded with ansi colours unless
+    /// switched off with `TermRenderer::color`
+    pub fn display_colored(&self) -> TermRenderer<'_> {
+        TermRenderer::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::piece::{Piece, Team};
+
+    #[test]
+    fn plain_matches_display() {
+        let board = HexBoard::new_initialize();
+        let plain = board.display_colored().color(false);
+        assert_eq!(plain.to_string(), board.to_string());
+        assert_eq!(format!("{:#}", plain), format!("{:#}", board));
+        assert!(!plain.to_string().contains('\x1b'));
+    }
+
+    #[test]
+    fn names_the_ends_of_rows() {
+        let board = HexBoard::new_initialize();
+        let drawn = board
+            .display_colored()
+            .color(false)
+            .coordinates(true)
+            .to_string();
+        let lines: Vec<&str> = drawn.lines().collect();
+        assert_eq!(lines.len(), 13);
+        assert!(lines[1].starts_with(" f1      # "));
+        assert!(lines[1].ends_with("# l1"));
+        assert!(lines[11].starts_with(" a6      # "));
+        assert!(lines[11].ends_with("# f11"));
+        // the borders line up with the rows
+        assert!(lines[0].starts_with(&" ".repeat(LABEL_WIDTH + 1 + 6)));
+    }
+
+    #[test]
+    fn shades_hexes() {
+        let mut board = HexBoard::new();
+        board.place((0, -5).into(), Piece::new(Name::King, Team::White));
+        board.place((0, 5).into(), Piece::new(Name::King, Team::Black));
+        board.place((0, 0).into(), Piece::new(Name::Rook, Team::White));
+        let drawn = board
+            .display_colored()
+            .last_move(Some(((1, -1).into(), (0, 0).into())))
+            .to_string();
+        let shade = |(r, g, b): (u8, u8, u8)| format!("\x1b[48;2;{};{};{}m", r, g, b);
+
+        assert_eq!(drawn.matches(&shade(LAST_MOVE)).count(), 2);
+        assert_eq!(drawn.matches(&shade(CHECK)).count(), 1);
+        let tiles: usize = TILES
+            .iter()
+            .map(|&t| drawn.matches(&shade(t)).count())
+            .sum();
+        assert_eq!(tiles, board.hexes().count() - 3);
+        assert!(drawn
+            .lines()
+            .skip(1)
+            .take(11)
+            .all(|line| line.contains(RESET)));
+        assert!(!drawn.contains('.'));
+    }
+}
@@ -0,0 +1,429 @@
+//! just enough of the websocket protocol (RFC 6455) for hex-chess-server and
+//! the bevy client to talk over - the opening handshake from either end, and
+//! unfragmented or fragmented text messages along with pings and closes
+
+use std::io::{self, Read, Write};
+
+/// what the handshake appends to the client's key before hashing it
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// the longest request head or message accepted, to keep a bad client from
+/// using up memory
+const MAX_LEN: usize = 1 << 16;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xa;
+
+/// which end of a connection is writing, since clients have to mask their
+/// frames and servers mustn't
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Server,
+    Client,
+}
+
+/// a whole message from the other end
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Text(String),
+    Ping(Vec<u8>),
+    /// pongs and binary messages, which nothing here uses
+    Ignored,
+    Close,
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+/// the SHA-1 digest of `data`, which the handshake needs and nothing else
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, word) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// the `Sec-WebSocket-Accept` value answering a client's `Sec-WebSocket-Key`
+fn accept_key(key: &str) -> String {
+    base64::encode(sha1(format!("{}{}", key.trim(), GUID).as_bytes()))
+}
+
+/// read the head of an http request or response, up to the blank line
+fn read_head<R: Read>(reader: &mut R) -> io::Result<String> {
+    // byte at a time so the first frame after it doesn't get swallowed
+    let mut head = Vec::new();
+    let mut byte = [0];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() > MAX_LEN {
+            return Err(invalid("http head too long"));
+        }
+        reader.read_exact(&mut byte)?;
+        head.push(byte[0]);
+    }
+    String::from_utf8(head).map_err(|_| invalid("http head isn't utf-8"))
+}
+
+/// read an http upgrade request off `stream` and agree to it
+pub fn handshake<S: Read + Write>(stream: &mut S) -> io::Result<()> {
+    let head = read_head(stream)?;
+    let key = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-key"))
+        .map(|(_, value)| value)
+        .ok_or_else(|| invalid("not a websocket upgrade"))?;
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )?;
+    stream.flush()
+}
+
+/// ask the server at `host` to switch `stream` to websockets for `path`,
+/// and wait for it to agree
+pub fn request_upgrade<S: Read + Write>(stream: &mut S, host: &str, path: &str) -> io::Result<()> {
+    let key: Vec<u8> = (0..16).map(|_| fastrand::u8(..)).collect();
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\n\
+         Host: {}\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\n\
+         Sec-WebSocket-Version: 13\r\n\r\n",
+        path,
+        host,
+        base64::encode(key)
+    )?;
+    stream.flush()?;
+    if !read_head(stream)?.starts_with("HTTP/1.1 101") {
+        return Err(invalid("the server refused to switch to websockets"));
+    }
+    Ok(())
+}
+
+/// read one frame, giving whether it's the last of its message, its opcode
+/// and its unmasked payload
+fn read_raw<R: Read>(reader: &mut R) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header)?;
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0f;
+    let masked = header[1] & 0x80 != 0;
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if len > MAX_LEN as u64 {
+        return Err(invalid("frame too long"));
+    }
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok((fin, opcode, payload))
+}
+
+/// reads whole messages off a stream
+pub struct Reader<R> {
+    reader: R,
+    /// the opcode and payload so far of a message sent in fragments
+    partial: Option<(u8, Vec<u8>)>,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            partial: None,
+        }
+    }
+
+    /// read the next message, putting fragmented ones back together
+    pub fn read_frame(&mut self) -> io::Result<Frame> {
+        loop {
+            let (fin, opcode, payload) = read_raw(&mut self.reader)?;
+            match opcode {
+                // control frames can come between the fragments of a message
+                OP_CLOSE => return Ok(Frame::Close),
+                OP_PING => return Ok(Frame::Ping(payload)),
+                OP_PONG => return Ok(Frame::Ignored),
+                OP_TEXT | OP_BINARY if self.partial.is_none() => {
+                    self.partial = Some((opcode, payload))
+                }
+                OP_CONTINUATION if self.partial.is_some() => {
+                    let (_, data) = self.partial.as_mut().unwrap();
+                    if data.len() + payload.len() > MAX_LEN {
+                        return Err(invalid("message too long"));
+                    }
+                    data.extend(payload);
+                }
+                _ => return Err(invalid("unexpected opcode")),
+            }
+            if fin {
+                return match self.partial.take() {
+                    Some((OP_TEXT, data)) => String::from_utf8(data)
+                        .map(Frame::Text)
+                        .map_err(|_| invalid("text isn't utf-8")),
+                    _ => Ok(Frame::Ignored),
+                };
+            }
+        }
+    }
+}
+
+/// write a single frame, masked if it's from the client
+fn write_raw<W: Write>(writer: &mut W, side: Side, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mask_bit = match side {
+        Side::Server => 0,
+        Side::Client => 0x80,
+    };
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => frame.push(mask_bit | len as u8),
+        len @ 126..=0xffff => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    match side {
+        Side::Server => frame.extend_from_slice(payload),
+        Side::Client => {
+            let mask = fastrand::u32(..).to_be_bytes();
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        }
+    }
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+pub fn write_text<W: Write>(writer: &mut W, side: Side, text: &str) -> io::Result<()> {
+    write_raw(writer, side, OP_TEXT, text.as_bytes())
+}
+
+pub fn write_pong<W: Write>(writer: &mut W, side: Side, payload: &[u8]) -> io::Result<()> {
+    write_raw(writer, side, OP_PONG, payload)
+}
+
+pub fn write_close<W: Write>(writer: &mut W, side: Side) -> io::Result<()> {
+    write_raw(writer, side, OP_CLOSE, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a frame as a client would send it, masked with `mask`
+    fn client_frame(fin: bool, opcode: u8, payload: &[u8], mask: [u8; 4]) -> Vec<u8> {
+        let mut frame = vec![u8::from(fin) << 7 | opcode];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    #[test]
+    fn hashes_like_sha1() {
+        let hex =
+            |digest: [u8; 20]| -> String { digest.iter().map(|b| format!("{:02x}", b)).collect() };
+        assert_eq!(hex(sha1(b"")), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hex(sha1(b"The quick brown fox jumps over the lazy dog")),
+            "2fd4e1c67a2d28fced849ee1bb76e7391b93eb12"
+        );
+    }
+
+    #[test]
+    fn answers_the_handshake() {
+        // the example from RFC 6455 section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+
+        let request = "GET /chat HTTP/1.1\r\nHost: server.example.com\r\nUpgrade: websocket\r\n\
+                       Connection: Upgrade\r\nsec-websocket-key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n";
+        let mut stream = io::Cursor::new(request.as_bytes().to_vec());
+        handshake(&mut stream).unwrap();
+        let response = String::from_utf8(stream.into_inner()).unwrap();
+        assert!(response[request.len()..].starts_with("HTTP/1.1 101"));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        let mut stream = io::Cursor::new(b"GET / HTTP/1.1\r\n\r\n".to_vec());
+        assert!(handshake(&mut stream).is_err());
+    }
+
+    #[test]
+    fn reads_masked_and_fragmented_text() {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut input = client_frame(true, OP_TEXT, b"Hello", mask);
+        input.extend(client_frame(false, OP_TEXT, b"Hel", mask));
+        input.extend(client_frame(true, OP_PING, b"hi", mask));
+        input.extend(client_frame(true, OP_CONTINUATION, b"lo", mask));
+        let long = "x".repeat(300);
+        input.extend(client_frame(true, OP_TEXT, long.as_bytes(), mask));
+        input.extend(client_frame(true, OP_CLOSE, b"", mask));
+
+        let mut reader = Reader::new(io::Cursor::new(input));
+        assert_eq!(
+            reader.read_frame().unwrap(),
+            Frame::Text("Hello".to_string())
+        );
+        assert_eq!(reader.read_frame().unwrap(), Frame::Ping(b"hi".to_vec()));
+        assert_eq!(
+            reader.read_frame().unwrap(),
+            Frame::Text("Hello".to_string())
+        );
+        assert_eq!(reader.read_frame().unwrap(), Frame::Text(long));
+        assert_eq!(reader.read_frame().unwrap(), Frame::Close);
+        assert!(reader.read_frame().is_err());
+    }
+
+    #[test]
+    fn writes_unmasked_frames() {
+        let mut out = Vec::new();
+        write_text(&mut out, Side::Server, "Hello").unwrap();
+        assert_eq!(out, [0x81, 0x05, b'H', b'e', b'l', b'l', b'o']);
+
+        let mut out = Vec::new();
+        write_text(&mut out, Side::Server, &"x".repeat(256)).unwrap();
+        assert_eq!(out[..4], [0x81, 126, 0x01, 0x00]);
+        assert_eq!(out.len(), 4 + 256);
+    }
+
+    #[test]
+    fn clients_mask_what_they_write() {
+        let mut out = Vec::new();
+        write_text(&mut out, Side::Client, "Hello").unwrap();
+        write_pong(&mut out, Side::Client, b"hi").unwrap();
+        write_close(&mut out, Side::Client).unwrap();
+        assert_eq!(out[..2], [0x81, 0x80 | 5]);
+
+        let mut reader = Reader::new(io::Cursor::new(out));
+        assert_eq!(
+            reader.read_frame().unwrap(),
+            Frame::Text("Hello".to_string())
+        );
+        assert_eq!(reader.read_frame().unwrap(), Frame::Ignored);
+        assert_eq!(reader.read_frame().unwrap(), Frame::Close);
+    }
+
+    /// a stream that reads from `input` and writes to `output`
+    struct Duplex {
+        input: io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Duplex {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Duplex {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn requests_an_upgrade() {
+        let response = b"HTTP/1.1 101 Switching Protocols\r\n\r\n\x81\x02hi";
+        let mut stream = Duplex {
+            input: io::Cursor::new(response.to_vec()),
+            output: Vec::new(),
+        };
+        request_upgrade(&mut stream, "localhost:9001", "/play").unwrap();
+        let request = String::from_utf8(stream.output.clone()).unwrap();
+        assert!(request.starts_with("GET /play HTTP/1.1\r\n"));
+        assert!(request.contains("Host: localhost:9001\r\n"));
+        // the frame after the response is left to read
+        let mut reader = Reader::new(stream);
+        assert_eq!(reader.read_frame().unwrap(), Frame::Text("hi".to_string()));
+
+        let mut stream = Duplex {
+            input: io::Cursor::new(b"HTTP/1.1 404 Not Found\r\n\r\n".to_vec()),
+            output: Vec::new(),
+        };
+        assert!(request_upgrade(&mut stream, "localhost:9001", "/").is_err());
+    }
+}
@@ -0,0 +1,39 @@
+//! keys for zobrist hashing of positions
+//!
+//! rather than storing a table of random numbers the keys are derived with
+//! splitmix64 from the values they stand for, so boards of any radius hash
+//! consistently without needing a table sized for them
+
+use crate::{coord::Coord, piece::Piece};
+
+const fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// xored in when it is black's turn
+pub(crate) const BLACK_TO_MOVE: u64 = splitmix64(u64::MAX);
+
+/// the key for a pawn on `c` that may still double step
+pub(crate) fn unmoved_pawn(c: Coord) -> u64 {
+    // the tag bit is above anything a piece key packs
+    let packed = 1 << 48 | (c.q as u16 as u64) << 32 | (c.r as u16 as u64) << 16;
+    splitmix64(packed)
+}
+
+/// the key for a pawn having just skipped over `c` with a double step
+pub(crate) fn en_passant(c: Coord) -> u64 {
+    let packed = 2 << 48 | (c.q as u16 as u64) << 32 | (c.r as u16 as u64) << 16;
+    splitmix64(packed)
+}
+
+/// the key for `piece` standing on `c`
+pub(crate) fn piece(c: Coord, piece: Piece) -> u64 {
+    let packed = (c.q as u16 as u64) << 32
+        | (c.r as u16 as u64) << 16
+        | (piece.name as u64) << 8
+        | piece.team as u64;
+    splitmix64(packed)
+}
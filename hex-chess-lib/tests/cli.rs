@@ -0,0 +1,177 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+fn run(input: &str) -> String {
+    run_with_args(&[], input)
+}
+
+fn run_with_args(args: &[&str], input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hex-chess-lib"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start cli");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn plays_to_checkmate() {
+    let stdout = run("0, -3 -> 2, -4\n\
+                      1, 4 -> 2, 2\n\
+                      -1, -4 -> -4, 2\n\
+                      2, 2 -> 3, 0\n\
+                      -4, 2 -> 4, -2\n");
+    assert!(stdout.trim_end().ends_with("white wins by checkmate"));
+    assert!(stdout.contains("Checkmate!"));
+}
+
+#[test]
+fn rejects_out_of_turn_moves() {
+    let stdout = run("0, 1 -> 0, 0\n");
+    assert!(!stdout.contains("checkmate"));
+    assert_eq!(stdout.matches("white's turn").count(), 2);
+}
+
+#[test]
+fn reads_algebraic_notation() {
+    let stdout = run("Nc3\nf7->f6\nfoo\n");
+    // both moves are played and the nonsense just prompts again
+    assert_eq!(stdout.matches("white's turn").count(), 3);
+    assert_eq!(stdout.matches("black's turn").count(), 1);
+}
+
+#[test]
+fn speaks_json() {
+    let stdout = run_with_args(
+        &["--json"],
+        "{\"legal\":{\"from\":[1,-2]}}\n\
+         {\"move\":{\"from\":[0,-1],\"to\":[0,1]}}\n\
+         {\"move\":{\"from\":[4294967296,-1],\"to\":[0,0]}}\n\
+         {\"move\":{\"from\":[0,-1],\"to\":[0,0]}}\n",
+    );
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], r#"{"legal":[[1,-1],[1,0]]}"#);
+    assert!(lines[1].starts_with(r#"{"error":"#));
+    // a number too big for a coordinate isn't cut down to one that fits
+    assert_eq!(lines[2], r#"{"error":"`from` is too far off the board"}"#);
+    assert!(lines[3].starts_with(r#"{"state":{"#));
+    assert!(lines[3].contains(r#""turn":"black""#));
+    assert!(lines[3].contains(r#"{"at":[0,0],"piece":"P"}"#));
+}
+
+#[test]
+fn undoes_and_redoes_moves() {
+    let stdout = run("f5 -> f6\nundo\nredo\nundo\nundo\n");
+    // the move, its undo and its redo each print the board again
+    assert_eq!(stdout.matches("white's turn").count(), 4);
+    assert_eq!(stdout.matches("black's turn").count(), 2);
+}
+
+#[test]
+fn prints_material_after_moves() {
+    let stdout = run("0, -1 -> 0, 0\n1, 1 -> 1, 0\n0, 0 -> 1, 0\n");
+    assert_eq!(stdout.matches("material is even").count(), 2);
+    assert!(stdout.contains("white took a pawn"));
+    assert!(stdout.contains("white is up 1"));
+}
+
+/// the exit status and stderr of a run that's expected to fail
+fn fail_with_args(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_hex-chess-lib"))
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .expect("failed to start cli");
+    assert!(!output.status.success());
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn lists_legal_moves_without_redrawing() {
+    let stdout = run("legal e1\nlegal f5\nlegal f6\nmove f5 -> f6\nshow\n");
+    assert!(stdout.contains("Qa5 Qb4 Qc3 Qd2 Qe2 Qe3"));
+    assert!(stdout.contains("Move: f6\n"));
+    assert!(stdout.contains("no moves from f6"));
+    // only the move and `show` draw the board again
+    assert_eq!(stdout.matches("white's turn").count(), 1);
+    assert_eq!(stdout.matches("black's turn").count(), 2);
+}
+
+#[test]
+fn saves_and_loads_games() {
+    let path = std::env::temp_dir().join(format!("hex-chess-cli-{}.txt", std::process::id()));
+    let path = path.to_str().unwrap();
+    run(&format!("f5 -> f6\n1, 1 -> 1, 0\nsave {}\n", path));
+    assert_eq!(std::fs::read_to_string(path).unwrap().trim(), "1. f6 g6");
+
+    // the loaded game keeps its history, so the moves can be undone
+    let stdout = run(&format!("load {}\nundo\nundo\nundo\n", path));
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(stdout.matches("white's turn").count(), 4);
+    assert_eq!(stdout.matches("black's turn").count(), 1);
+}
+
+#[test]
+fn resigns() {
+    let stdout = run("resign\n");
+    assert!(stdout.trim_end().ends_with("black wins by resignation"));
+}
+
+#[test]
+fn analyzes_positions() {
+    let stdout = run_with_args(&["analyze", "K5/7/8/9/10/11/10/9/8/7/5k w - 0 1"], "");
+    assert!(stdout.trim_end().ends_with("draw by insufficient material"));
+
+    // the fen can be left unquoted
+    let fen = "K5/7/8/9/10/11/10/9/Q7/7/5k w - 0 1";
+    let mut args = vec!["analyze", "--depth", "2"];
+    args.extend(fen.split(' '));
+    let stdout = run_with_args(&args, "");
+    assert!(stdout.contains("white is up 9"));
+    assert!(stdout.contains("35 legal moves"));
+    assert!(stdout.contains("best move: Qf9#"));
+}
+
+#[test]
+fn plays_itself() {
+    let stdout = run_with_args(&["selfplay", "--depth", "1"], "");
+    assert!(stdout.starts_with("1. "));
+    assert!(stdout.contains("2. "));
+    assert!(stdout.trim_end().lines().last().unwrap().contains(" by "));
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let (code, stderr) = fail_with_args(&["fly"]);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("unknown command `fly`"));
+    assert!(stderr.contains("usage:"));
+    assert_eq!(fail_with_args(&["selfplay", "--depth", "deep"]).0, 2);
+    assert_eq!(fail_with_args(&["analyze"]).0, 2);
+    assert_eq!(fail_with_args(&["analyze", "not a fen"]).0, 1);
+
+    // flags only go with the commands they're for
+    assert_eq!(fail_with_args(&["play", "--depth", "5"]).0, 2);
+    assert_eq!(fail_with_args(&["--depth", "5"]).0, 2);
+    let (code, stderr) =
+        fail_with_args(&["analyze", "K5/7/8/9/10/11/10/9/8/7/5k w - 0 1", "--json"]);
+    assert_eq!(code, 2);
+    assert!(stderr.contains("unknown flag `--json`"));
+    assert_eq!(fail_with_args(&["--json", "selfplay"]).0, 2);
+}
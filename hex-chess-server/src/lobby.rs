@@ -0,0 +1,677 @@
+//! the games the server hosts and who is connected to each, kept apart from
+//! the sockets so the rules can be tested without a network
+
+use std::{
+    collections::HashMap,
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
+
+use hex_chess_lib::{
+    net::{self, ClientMsg, Message, MoveMsg, ServerMsg},
+    Game, Team,
+};
+
+pub type ClientId = u64;
+
+/// join codes leave out letters and digits that are easy to mix up
+const CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LEN: usize = 6;
+const TOKEN_LEN: usize = 16;
+/// how long a game nobody is connected to is kept for its players to rejoin
+const ABANDON_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// a team's place in a game, which it keeps while disconnected
+#[derive(Debug)]
+struct Seat {
+    /// what the player shows to get the seat back
+    token: String,
+    client: Option<ClientId>,
+}
+
+struct Lobby {
+    /// the authoritative copy of the game
+    game: Game,
+    /// each team's seat, indexed by team, which is empty until taken
+    seats: [Option<Seat>; 2],
+    spectators: Vec<ClientId>,
+    /// when the clock of the team to move last had time taken off it, which
+    /// is `None` until both seats are taken
+    clock_started: Option<Instant>,
+    /// when the last client left, which is `None` while anyone is connected
+    emptied: Option<Instant>,
+}
+
+impl Lobby {
+    fn clients(&self) -> impl Iterator<Item = ClientId> + '_ {
+        self.seats
+            .iter()
+            .flatten()
+            .filter_map(|seat| seat.client)
+            .chain(self.spectators.iter().copied())
+    }
+
+    fn full(&self) -> bool {
+        self.seats.iter().all(Option::is_some)
+    }
+
+    /// charge the team to move for the time since its clock was last charged
+    fn run_clock(&mut self, now: Instant) {
+        if let Some(started) = self.clock_started.replace(now) {
+            self.game.tick(now - started);
+        }
+    }
+
+    /// the message that starts `team`'s view of the game from where it is now
+    fn new_game(&self, team: Team) -> ServerMsg {
+        ServerMsg::Game(Message::NewGame {
+            fen: self.game.to_fen(),
+            team,
+            time_control: self.game.time_control(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Player(Team),
+    Spectator,
+}
+
+/// every lobby and connected client
+#[derive(Default)]
+pub struct Server {
+    clients: HashMap<ClientId, Sender<ServerMsg>>,
+    /// the lobby code and role of each client in a game
+    places: HashMap<ClientId, (String, Role)>,
+    lobbies: HashMap<String, Lobby>,
+    next_id: ClientId,
+}
+
+fn random_string(chars: &[u8], len: usize) -> String {
+    (0..len)
+        .map(|_| chars[fastrand::usize(..chars.len())] as char)
+        .collect()
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a new client, which is sent everything meant for it through `sender`
+    pub fn connect(&mut self, sender: Sender<ServerMsg>) -> ClientId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.clients.insert(id, sender);
+        id
+    }
+
+    /// forget a client, holding its seat for it to rejoin
+    pub fn disconnect(&mut self, id: ClientId) {
+        self.clients.remove(&id);
+        self.leave(id);
+    }
+
+    fn send(&self, id: ClientId, msg: ServerMsg) {
+        // a client that's gone is cleaned up once its reader notices
+        if let Some(sender) = self.clients.get(&id) {
+            let _ = sender.send(msg);
+        }
+    }
+
+    fn error(&self, id: ClientId, reason: impl ToString) {
+        self.send(id, ServerMsg::Error(reason.to_string()));
+    }
+
+    /// send `msg` to everyone in the lobby with `code` except `except`
+    fn broadcast(&self, code: &str, except: Option<ClientId>, msg: &ServerMsg) {
+        if let Some(lobby) = self.lobbies.get(code) {
+            for client in lobby.clients().filter(|&c| Some(c) != except) {
+                self.send(client, msg.clone());
+            }
+        }
+    }
+
+    /// take `id` out of whatever lobby it was in, throwing the lobby away
+    /// once it's over and nobody is left in it - an unfinished game is kept
+    /// for `ABANDON_TIMEOUT` in case its players come back
+    fn leave(&mut self, id: ClientId) {
+        let (code, role) = match self.places.remove(&id) {
+            Some(place) => place,
+            None => return,
+        };
+        let lobby = self.lobbies.get_mut(&code).unwrap();
+        match role {
+            Role::Player(team) => {
+                if let Some(seat) = &mut lobby.seats[team as usize] {
+                    seat.client = None;
+                }
+                let opponent = lobby.seats[team.flip() as usize].as_ref();
+                if let Some(client) = opponent.and_then(|seat| seat.client) {
+                    self.send(client, ServerMsg::Opponent { connected: false });
+                }
+            }
+            Role::Spectator => lobby.spectators.retain(|&c| c != id),
+        }
+        let lobby = self.lobbies.get_mut(&code).unwrap();
+        if lobby.clients().next().is_none() {
+            if lobby.game.finished() {
+                self.lobbies.remove(&code);
+            } else {
+                lobby.emptied = Some(Instant::now());
+            }
+        }
+    }
+
+    /// seat `id` as `team` in the lobby with `code`, sending it the game
+    fn seat(&mut self, id: ClientId, code: &str, team: Team, token: String) {
+        self.leave(id);
+        // leaving may have emptied and thrown away a finished game
+        let lobby = match self.lobbies.get_mut(code) {
+            Some(lobby) => lobby,
+            None => return self.error(id, "the game is over"),
+        };
+        let old = lobby.seats[team as usize].replace(Seat {
+            token: token.clone(),
+            client: Some(id),
+        });
+        lobby.emptied = None;
+        if lobby.full() && lobby.clock_started.is_none() {
+            lobby.clock_started = Some(Instant::now());
+        }
+        // a stale connection still holding the seat loses it
+        if let Some(old) = old.and_then(|seat| seat.client).filter(|&c| c != id) {
+            self.places.remove(&old);
+        }
+        self.places
+            .insert(id, (code.to_string(), Role::Player(team)));
+
+        let lobby = &self.lobbies[code];
+        self.send(id, ServerMsg::Seated { token });
+        self.send(id, lobby.new_game(team));
+        let opponent = lobby.seats[team.flip() as usize].as_ref();
+        self.send(
+            id,
+            ServerMsg::Opponent {
+                connected: opponent.is_some_and(|seat| seat.client.is_some()),
+            },
+        );
+        if let Some(client) = opponent.and_then(|seat| seat.client) {
+            self.send(client, ServerMsg::Opponent { connected: true });
+        }
+        if lobby.game.finished() {
+            self.send(id, ServerMsg::GameOver(lobby.game.result()));
+        }
+    }
+
+    pub fn handle(&mut self, id: ClientId, msg: ClientMsg) {
+        match msg {
+            ClientMsg::Create { team, time_control } => {
+                let mut code = random_string(CODE_CHARS, CODE_LEN);
+                while self.lobbies.contains_key(&code) {
+                    code = random_string(CODE_CHARS, CODE_LEN);
+                }
+                let game = match time_control {
+                    Some(time_control) => Game::with_time_control(time_control),
+                    None => Game::new(),
+                };
+                self.lobbies.insert(
+                    code.clone(),
+                    Lobby {
+                        game,
+                        seats: [None, None],
+                        spectators: Vec::new(),
+                        clock_started: None,
+                        emptied: None,
+                    },
+                );
+                let token = new_token();
+                self.send(
+                    id,
+                    ServerMsg::Created {
+                        code: code.clone(),
+                        token: token.clone(),
+                    },
+                );
+                self.seat(id, &code, team, token);
+            }
+            ClientMsg::Join { code } => {
+                let lobby = match self.lobbies.get(&code) {
+                    Some(lobby) => lobby,
+                    None => return self.error(id, format!("no game with code {}", code)),
+                };
+                match [Team::White, Team::Black]
+                    .into_iter()
+                    .find(|&team| lobby.seats[team as usize].is_none())
+                {
+                    Some(team) => self.seat(id, &code, team, new_token()),
+                    None => self.error(id, "the game is full"),
+                }
+            }
+            ClientMsg::Rejoin { code, token } => {
+                let team = self.lobbies.get(&code).and_then(|lobby| {
+                    [Team::White, Team::Black].into_iter().find(|&team| {
+                        lobby.seats[team as usize]
+                            .as_ref()
+                            .is_some_and(|seat| seat.token == token)
+                    })
+                });
+                match team {
+                    Some(team) => self.seat(id, &code, team, token),
+                    None => self.error(id, "no seat to rejoin"),
+                }
+            }
+            ClientMsg::Spectate { code } => {
+                if !self.lobbies.contains_key(&code) {
+                    return self.error(id, format!("no game with code {}", code));
+                }
+                self.leave(id);
+                let lobby = self.lobbies.get_mut(&code).unwrap();
+                lobby.spectators.push(id);
+                lobby.emptied = None;
+                let fen = lobby.game.to_fen();
+                let over = lobby.game.finished().then(|| lobby.game.result());
+                self.places.insert(id, (code, Role::Spectator));
+                self.send(id, ServerMsg::Spectating { fen });
+                if let Some(result) = over {
+                    self.send(id, ServerMsg::GameOver(result));
+                }
+            }
+            ClientMsg::Game(msg) => self.play(id, msg),
+        }
+    }
+
+    /// check a message from a seated player against the game, passing it on
+    /// to everyone else in the lobby if it holds up
+    fn play(&mut self, id: ClientId, msg: Message) {
+        let (code, team) = match self.places.get(&id) {
+            Some((code, Role::Player(team))) => (code.clone(), *team),
+            Some((_, Role::Spectator)) => return self.error(id, "spectators can't play"),
+            None => return self.error(id, "not in a game"),
+        };
+        let lobby = self.lobbies.get_mut(&code).unwrap();
+        if !lobby.full() {
+            return self.error(id, "waiting for an opponent");
+        }
+        let was_over = lobby.game.finished();
+        let applied = match &msg {
+            Message::NewGame { .. } => Err("only the server starts games".to_string()),
+            Message::Move(MoveMsg { mv, ply, checksum }) => {
+                lobby.run_clock(Instant::now());
+                if lobby.game.turn != team {
+                    Err(format!("it's {}'s turn to move", lobby.game.turn))
+                } else if *ply != net::ply(&lobby.game) {
+                    Err(format!(
+                        "expected ply {} but was sent ply {}",
+                        net::ply(&lobby.game),
+                        ply
+                    ))
+                } else {
+                    // tried on a copy so a refused move leaves nothing behind,
+                    // like the increment on the mover's clock
+                    let mut after = lobby.game.clone();
+                    match after.make_move(*mv) {
+                        Ok(_) if net::checksum(&after.to_fen()) != *checksum => {
+                            Err(format!("the position after {} doesn't match", mv))
+                        }
+                        Ok(_) => {
+                            lobby.game = after;
+                            Ok(())
+                        }
+                        Err(e) => Err(e.to_string()),
+                    }
+                }
+            }
+            Message::Resign => lobby.game.resign(team).map_err(|e| e.to_string()),
+            Message::DrawOffer => lobby.game.offer_draw(team).map_err(|e| e.to_string()),
+            Message::DrawAccept => lobby.game.accept_draw(team).map_err(|e| e.to_string()),
+            Message::Chat(_) => Ok(()),
+        };
+
+        let lobby = &self.lobbies[&code];
+        match applied {
+            Ok(()) => self.broadcast(&code, Some(id), &ServerMsg::Game(msg)),
+            Err(reason) => {
+                self.error(id, reason);
+                // put the sender back in step in case it played a move we didn't
+                if matches!(msg, Message::Move(_)) {
+                    self.send(id, lobby.new_game(team));
+                }
+            }
+        }
+        if !was_over && lobby.game.finished() {
+            self.broadcast(&code, None, &ServerMsg::GameOver(lobby.game.result()));
+        }
+    }
+
+    /// run the clock in every game under way, ending any where a flag falls
+    ///
+    /// clocks only start once both seats are taken
+    pub fn tick_clocks(&mut self) {
+        let now = Instant::now();
+        let mut flagged = Vec::new();
+        for (code, lobby) in &mut self.lobbies {
+            if !lobby.full() || lobby.game.finished() || lobby.game.time_control().is_none() {
+                continue;
+            }
+            lobby.run_clock(now);
+            if lobby.game.finished() {
+                flagged.push((code.clone(), lobby.game.result()));
+            }
+        }
+        for (code, result) in flagged {
+            self.broadcast(&code, None, &ServerMsg::GameOver(result));
+        }
+    }
+
+    /// throw away every lobby nobody has been connected to for
+    /// `ABANDON_TIMEOUT`
+    pub fn drop_abandoned(&mut self) {
+        let now = Instant::now();
+        self.lobbies.retain(|_, lobby| {
+            lobby
+                .emptied
+                .is_none_or(|emptied| now - emptied < ABANDON_TIMEOUT)
+        });
+    }
+}
+
+fn new_token() -> String {
+    (0..TOKEN_LEN).map(|_| fastrand::alphanumeric()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_chess_lib::{net::RemoteGame, GameResult, Move, TimeControl};
+    use std::{
+        sync::mpsc::{channel, Receiver},
+        time::Duration,
+    };
+
+    fn client(server: &mut Server) -> (ClientId, Receiver<ServerMsg>) {
+        let (sender, receiver) = channel();
+        (server.connect(sender), receiver)
+    }
+
+    fn inbox(receiver: &Receiver<ServerMsg>) -> Vec<ServerMsg> {
+        receiver.try_iter().collect()
+    }
+
+    /// a connected client with its inbox and its view of the game
+    type Player = (ClientId, Receiver<ServerMsg>, RemoteGame);
+
+    const BLITZ: TimeControl = TimeControl {
+        initial: Duration::from_secs(60),
+        increment: Duration::from_secs(5),
+    };
+
+    /// a game between two clients, along with the lobby code
+    fn started() -> (Server, [Player; 2], String) {
+        started_with(None)
+    }
+
+    fn started_with(time_control: Option<TimeControl>) -> (Server, [Player; 2], String) {
+        let mut server = Server::new();
+        let (white, white_inbox) = client(&mut server);
+        server.handle(
+            white,
+            ClientMsg::Create {
+                team: Team::White,
+                time_control,
+            },
+        );
+        let messages = inbox(&white_inbox);
+        let code = match &messages[0] {
+            ServerMsg::Created { code, .. } => code.clone(),
+            msg => panic!("expected a code, got {:?}", msg),
+        };
+        let new_game = |messages: &[ServerMsg]| {
+            messages
+                .iter()
+                .find_map(|msg| match msg {
+                    ServerMsg::Game(msg) => Some(RemoteGame::join(msg).unwrap()),
+                    _ => None,
+                })
+                .unwrap()
+        };
+        let white_game = new_game(&messages);
+        assert!(messages.contains(&ServerMsg::Opponent { connected: false }));
+
+        let (black, black_inbox) = client(&mut server);
+        server.handle(black, ClientMsg::Join { code: code.clone() });
+        let messages = inbox(&black_inbox);
+        let black_game = new_game(&messages);
+        assert_eq!(black_game.local, Team::Black);
+        assert_eq!(
+            inbox(&white_inbox),
+            [ServerMsg::Opponent { connected: true }]
+        );
+        (
+            server,
+            [
+                (white, white_inbox, white_game),
+                (black, black_inbox, black_game),
+            ],
+            code,
+        )
+    }
+
+    #[test]
+    fn relays_checked_moves() {
+        let (mut server, [(white, white_inbox, mut white_game), (black, black_inbox, _)], code) =
+            started();
+        let (spectator, spectator_inbox) = client(&mut server);
+        server.handle(spectator, ClientMsg::Spectate { code: code.clone() });
+        assert!(matches!(
+            inbox(&spectator_inbox)[..],
+            [ServerMsg::Spectating { .. }]
+        ));
+
+        let (_, msg) = white_game
+            .play(Move::new((0, -1).into(), (0, 0).into()))
+            .unwrap();
+        let msg = match msg {
+            Message::Move(msg) => msg,
+            _ => unreachable!(),
+        };
+        server.handle(white, ClientMsg::Game(Message::Move(msg)));
+        assert_eq!(inbox(&black_inbox), [ServerMsg::Game(Message::Move(msg))]);
+        assert_eq!(
+            inbox(&spectator_inbox),
+            [ServerMsg::Game(Message::Move(msg))]
+        );
+        assert!(inbox(&white_inbox).is_empty());
+
+        // playing out of turn, and spectators playing at all, get turned down
+        server.handle(white, ClientMsg::Game(Message::Move(msg)));
+        assert!(matches!(inbox(&white_inbox)[0], ServerMsg::Error(_)));
+        server.handle(spectator, ClientMsg::Game(Message::Resign));
+        assert!(matches!(inbox(&spectator_inbox)[..], [ServerMsg::Error(_)]));
+
+        // a move with the wrong checksum is refused and the sender resynced
+        let bad = MoveMsg {
+            mv: Move::new((1, 1).into(), (1, 0).into()),
+            ply: 1,
+            checksum: 0,
+        };
+        server.handle(black, ClientMsg::Game(Message::Move(bad)));
+        let messages = inbox(&black_inbox);
+        assert!(matches!(messages[0], ServerMsg::Error(_)));
+        let resynced = match &messages[1] {
+            ServerMsg::Game(msg) => RemoteGame::join(msg).unwrap(),
+            msg => panic!("expected a resync, got {:?}", msg),
+        };
+        assert_eq!(resynced.game.to_fen(), white_game.game.to_fen());
+        assert!(inbox(&white_inbox).is_empty());
+    }
+
+    #[test]
+    fn refused_moves_leave_the_clock_alone() {
+        let (mut server, [(white, white_inbox, _), _], code) = started_with(Some(BLITZ));
+        let bad = MoveMsg {
+            mv: Move::new((0, -1).into(), (0, 0).into()),
+            ply: 0,
+            checksum: 0,
+        };
+        for _ in 0..10 {
+            // stop the clock so only the move could change it
+            server.lobbies.get_mut(&code).unwrap().clock_started = None;
+            server.handle(white, ClientMsg::Game(Message::Move(bad)));
+            assert!(matches!(inbox(&white_inbox)[0], ServerMsg::Error(_)));
+        }
+        let game = &server.lobbies[&code].game;
+        assert_eq!(game.remaining(Team::White), Some(BLITZ.initial));
+        assert_eq!(net::ply(game), 0);
+    }
+
+    #[test]
+    fn clocks_wait_for_an_opponent() {
+        let mut server = Server::new();
+        let (white, white_inbox) = client(&mut server);
+        server.handle(
+            white,
+            ClientMsg::Create {
+                team: Team::White,
+                time_control: Some(BLITZ),
+            },
+        );
+        let code = match &inbox(&white_inbox)[0] {
+            ServerMsg::Created { code, .. } => code.clone(),
+            msg => panic!("expected a code, got {:?}", msg),
+        };
+        server.tick_clocks();
+        server.tick_clocks();
+        let lobby = &server.lobbies[&code];
+        assert_eq!(lobby.clock_started, None);
+        assert_eq!(lobby.game.remaining(Team::White), Some(BLITZ.initial));
+
+        let (black, _) = client(&mut server);
+        server.handle(black, ClientMsg::Join { code: code.clone() });
+        assert!(server.lobbies[&code].clock_started.is_some());
+    }
+
+    #[test]
+    fn rejoins_with_token() {
+        let (mut server, [(white, white_inbox, _), (black, black_inbox, _)], code) = started();
+        server.disconnect(white);
+        assert_eq!(
+            inbox(&black_inbox),
+            [ServerMsg::Opponent { connected: false }]
+        );
+        assert!(inbox(&white_inbox).is_empty());
+
+        let (again, again_inbox) = client(&mut server);
+        server.handle(
+            again,
+            ClientMsg::Rejoin {
+                code: code.clone(),
+                token: "wrong".to_string(),
+            },
+        );
+        assert!(matches!(inbox(&again_inbox)[..], [ServerMsg::Error(_)]));
+
+        // the token was handed out when the lobby was made
+        let token = server.lobbies[&code].seats[0]
+            .as_ref()
+            .unwrap()
+            .token
+            .clone();
+        server.handle(again, ClientMsg::Rejoin { code, token });
+        let messages = inbox(&again_inbox);
+        assert!(matches!(messages[0], ServerMsg::Seated { .. }));
+        assert!(matches!(
+            messages[1],
+            ServerMsg::Game(Message::NewGame {
+                team: Team::White,
+                ..
+            })
+        ));
+        assert_eq!(
+            inbox(&black_inbox),
+            [ServerMsg::Opponent { connected: true }]
+        );
+
+        // the seats are taken
+        let (third, third_inbox) = client(&mut server);
+        server.handle(
+            third,
+            ClientMsg::Join {
+                code: "NOPE".to_string(),
+            },
+        );
+        assert!(matches!(inbox(&third_inbox)[..], [ServerMsg::Error(_)]));
+        let code = server.places[&black].0.clone();
+        server.handle(third, ClientMsg::Join { code });
+        assert_eq!(
+            inbox(&third_inbox),
+            [ServerMsg::Error("the game is full".to_string())]
+        );
+    }
+
+    #[test]
+    fn resigning_ends_the_game_for_everyone() {
+        let (mut server, [(white, white_inbox, _), (black, black_inbox, _)], code) = started();
+        server.handle(black, ClientMsg::Game(Message::Resign));
+        let over = ServerMsg::GameOver(GameResult::Resignation(Team::White));
+        assert_eq!(
+            inbox(&white_inbox),
+            [ServerMsg::Game(Message::Resign), over.clone()]
+        );
+        assert_eq!(inbox(&black_inbox), [over]);
+
+        // the lobby goes once everyone has left a finished game
+        server.disconnect(white);
+        server.disconnect(black);
+        assert!(!server.lobbies.contains_key(&code));
+    }
+
+    #[test]
+    fn drops_abandoned_lobbies() {
+        let (mut server, [(white, _, _), (black, _, _)], code) = started();
+        server.disconnect(white);
+        server.disconnect(black);
+        server.drop_abandoned();
+        assert!(server.lobbies[&code].emptied.is_some());
+
+        // coming back in time keeps the game
+        let (again, _) = client(&mut server);
+        let token = server.lobbies[&code].seats[0]
+            .as_ref()
+            .unwrap()
+            .token
+            .clone();
+        server.handle(
+            again,
+            ClientMsg::Rejoin {
+                code: code.clone(),
+                token,
+            },
+        );
+        assert_eq!(server.lobbies[&code].emptied, None);
+        server.disconnect(again);
+
+        // a game that was never joined goes the same way
+        let (lonely, lonely_inbox) = client(&mut server);
+        server.handle(
+            lonely,
+            ClientMsg::Create {
+                team: Team::Black,
+                time_control: None,
+            },
+        );
+        let unjoined = match &inbox(&lonely_inbox)[0] {
+            ServerMsg::Created { code, .. } => code.clone(),
+            msg => panic!("expected a code, got {:?}", msg),
+        };
+        server.disconnect(lonely);
+
+        for lobby in server.lobbies.values_mut() {
+            lobby.emptied = lobby.emptied.map(|emptied| emptied - ABANDON_TIMEOUT);
+        }
+        server.drop_abandoned();
+        assert!(!server.lobbies.contains_key(&code));
+        assert!(!server.lobbies.contains_key(&unjoined));
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    io,
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use hex_chess_lib::{
+    net::{ClientMsg, ServerMsg},
+    ws::{self, write_close, write_pong, write_text, Frame, Reader, Side},
+};
+
+use crate::lobby::Server;
+
+mod lobby;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9001";
+/// how often clocks are checked for a fallen flag, and abandoned games for
+/// throwing away
+const CLOCK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// talk to one client until it goes away
+fn serve(mut stream: TcpStream, server: Arc<Mutex<Server>>) -> io::Result<()> {
+    ws::handshake(&mut stream)?;
+    // the reader answers pings while the writer thread sends game messages,
+    // so writes take turns
+    let writer = Arc::new(Mutex::new(stream.try_clone()?));
+    let (sender, receiver) = mpsc::channel::<ServerMsg>();
+    let id = server.lock().unwrap().connect(sender);
+    let mut reader = Reader::new(stream);
+
+    let outgoing = Arc::clone(&writer);
+    thread::spawn(move || {
+        // ends once the server drops the client's sender
+        for msg in receiver {
+            let text = serde_json::to_string(&msg).expect("server messages serialize");
+            if write_text(&mut *outgoing.lock().unwrap(), Side::Server, &text).is_err() {
+                break;
+            }
+        }
+    });
+
+    let result = loop {
+        let written = match reader.read_frame() {
+            Ok(Frame::Text(text)) => match serde_json::from_str::<ClientMsg>(&text) {
+                Ok(msg) => {
+                    server.lock().unwrap().handle(id, msg);
+                    Ok(())
+                }
+                Err(e) => {
+                    let error = ServerMsg::Error(format!("unreadable message - {}", e));
+                    let text = serde_json::to_string(&error).expect("server messages serialize");
+                    write_text(&mut *writer.lock().unwrap(), Side::Server, &text)
+                }
+            },
+            Ok(Frame::Ping(payload)) => {
+                write_pong(&mut *writer.lock().unwrap(), Side::Server, &payload)
+            }
+            Ok(Frame::Ignored) => Ok(()),
+            Ok(Frame::Close) => {
+                let _ = write_close(&mut *writer.lock().unwrap(), Side::Server);
+                break Ok(());
+            }
+            // the client went away without closing
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            break Err(e);
+        }
+    };
+    server.lock().unwrap().disconnect(id);
+    result
+}
+
+pub fn main() {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address).expect("unable to listen on address");
+    println!("listening on ws://{}", address);
+
+    let server = Arc::new(Mutex::new(Server::new()));
+    let clocks = Arc::clone(&server);
+    thread::spawn(move || loop {
+        thread::sleep(CLOCK_INTERVAL);
+        let mut server = clocks.lock().unwrap();
+        server.tick_clocks();
+        server.drop_abandoned();
+    });
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            let peer = stream.peer_addr();
+            if let Err(e) = serve(stream, server) {
+                match peer {
+                    Ok(peer) => eprintln!("{}: {}", peer, e),
+                    Err(_) => eprintln!("{}", e),
+                }
+            }
+        });
+    }
+}